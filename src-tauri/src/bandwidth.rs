@@ -0,0 +1,211 @@
+//! Bandwidth limiting of uploads and downloads with token buckets, applied to
+//! the data as it is sent and received.
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Longest a transfer sleeps before looking at the bucket again, so a changed
+/// limit applies to transfers that are already waiting.
+const MAX_WAIT: Duration = Duration::from_millis(250);
+
+/// Upload and download limits shared by every transfer of the app, across
+/// concurrent transfers and both zones.
+pub struct BandwidthLimiter {
+    pub upload: TokenBucket,
+    pub download: TokenBucket,
+}
+
+impl BandwidthLimiter {
+    /// Limits in KB/s (0 = unlimited).
+    pub fn new(upload_kbps: u64, download_kbps: u64) -> Self {
+        Self {
+            upload: TokenBucket::new(upload_kbps * 1024),
+            download: TokenBucket::new(download_kbps * 1024),
+        }
+    }
+
+    /// Change the limits (KB/s, 0 = unlimited); running transfers follow at once.
+    pub fn set_limits(&self, upload_kbps: u64, download_kbps: u64) {
+        self.upload.set_rate(upload_kbps * 1024);
+        self.download.set_rate(download_kbps * 1024);
+    }
+
+    /// Hold every transfer while paused, whatever the limits; returns whether
+    /// this changed anything.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        let upload = self.upload.set_paused(paused);
+        let download = self.download.set_paused(paused);
+        upload || download
+    }
+}
+
+/// Token bucket for a byte rate. Every piece of data takes its size in tokens
+/// before it goes over the wire; tokens refill at the rate, up to one second's
+/// worth. A piece larger than what is left puts the bucket in debt, which the
+/// next piece waits out.
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    /// Bytes per second (0 = unlimited)
+    rate: u64,
+    /// Negative while in debt
+    tokens: f64,
+    refilled: Instant,
+    /// No data goes through, however much is in the bucket
+    paused: bool,
+}
+
+impl BucketState {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.refilled = now;
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+    }
+}
+
+impl TokenBucket {
+    /// Bucket for `rate` bytes per second (0 = unlimited), starting full.
+    pub fn new(rate: u64) -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                rate,
+                tokens: rate as f64,
+                refilled: Instant::now(),
+                paused: false,
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, BucketState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_rate(&self, rate: u64) {
+        let mut state = self.state();
+        if state.rate == rate {
+            return;
+        }
+        state.refill();
+        state.rate = rate;
+        // No debt carries over into unlimited, nor a burst beyond the new rate
+        state.tokens = if rate == 0 {
+            0.0
+        } else {
+            state.tokens.min(rate as f64)
+        };
+    }
+
+    /// Stop all data until unpaused, whatever the rate. Returns whether the
+    /// bucket was paused the other way.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        let mut state = self.state();
+        let changed = state.paused != paused;
+        state.paused = paused;
+        changed
+    }
+
+    /// Wait until `bytes` may be sent or received.
+    pub async fn take(&self, bytes: u64) {
+        loop {
+            let wait = {
+                let mut state = self.state();
+                if state.paused {
+                    MAX_WAIT
+                } else if state.rate == 0 {
+                    return;
+                } else {
+                    state.refill();
+                    if state.tokens >= 0.0 {
+                        state.tokens -= bytes as f64;
+                        return;
+                    }
+                    Duration::from_secs_f64(-state.tokens / state.rate as f64).min(MAX_WAIT)
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_never_waits() {
+        let bucket = TokenBucket::new(0);
+        let start = Instant::now();
+        for _ in 0..100 {
+            bucket.take(10 * 1024 * 1024).await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debt_is_waited_out() {
+        // 1000 B/s, starting with one second of burst: 1000 + 300 bytes put the
+        // bucket 300 bytes in debt, which the next take waits out (0.3 s).
+        let bucket = TokenBucket::new(1000);
+        let start = Instant::now();
+        bucket.take(1000).await;
+        bucket.take(300).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        bucket.take(1).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(310), "{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lifting_the_limit_releases_waiters() {
+        let bucket = std::sync::Arc::new(TokenBucket::new(100));
+        bucket.take(100).await;
+        bucket.take(1000).await; // ten seconds of debt
+
+        let waiter = {
+            let bucket = bucket.clone();
+            tokio::spawn(async move { bucket.take(1).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        bucket.set_rate(0);
+
+        let start = Instant::now();
+        waiter.await.unwrap();
+        assert!(start.elapsed() <= MAX_WAIT, "{:?}", start.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_bucket_holds_takes_until_resumed() {
+        let bucket = std::sync::Arc::new(TokenBucket::new(0));
+        assert!(bucket.set_paused(true));
+        assert!(!bucket.set_paused(true));
+
+        let waiter = {
+            let bucket = bucket.clone();
+            tokio::spawn(async move { bucket.take(1).await })
+        };
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(!waiter.is_finished());
+        bucket.set_paused(false);
+
+        let start = Instant::now();
+        waiter.await.unwrap();
+        assert!(start.elapsed() <= MAX_WAIT, "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn set_rate_caps_the_burst() {
+        let bucket = TokenBucket::new(10_000);
+        bucket.set_rate(100);
+        let state = bucket.state();
+        assert_eq!(state.rate, 100);
+        assert!(state.tokens <= 100.0);
+    }
+}
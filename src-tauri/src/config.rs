@@ -1,4 +1,5 @@
 use crate::error::{AppError, AppResult};
+use chrono::{Datelike, Timelike};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::AppHandle;
@@ -31,8 +32,13 @@ pub struct AppConfig {
     /// Whether to sync on startup
     pub sync_on_startup: bool,
 
-    /// Maximum file size to sync (bytes, 0 = unlimited)
-    pub max_file_size_bytes: u64,
+    /// Maximum size of personal files to sync (bytes, 0 = unlimited)
+    #[serde(alias = "max_file_size_bytes")]
+    pub personal_max_file_size_bytes: u64,
+
+    /// Maximum size of shared files to sync (bytes, 0 = unlimited)
+    #[serde(default)]
+    pub shared_max_file_size_bytes: u64,
 
     /// Max upload speed in KB/s (0 = unlimited)
     #[serde(default)]
@@ -42,9 +48,143 @@ pub struct AppConfig {
     #[serde(default)]
     pub max_download_kbps: u64,
 
+    /// Weekly windows with their own speed limits or a sync pause; the first
+    /// window covering the current time wins over the limits above
+    #[serde(default)]
+    pub bandwidth_schedule: Vec<BandwidthRule>,
+
+    /// How many uploads run at the same time
+    #[serde(default = "default_parallel_transfers")]
+    pub max_parallel_uploads: usize,
+
+    /// How many downloads run at the same time
+    #[serde(default = "default_parallel_transfers")]
+    pub max_parallel_downloads: usize,
+
     /// Folders to include in sync (empty = sync all)
     #[serde(default)]
     pub sync_include_paths: Vec<String>,
+
+    /// How conflicts in personal files are resolved
+    #[serde(default)]
+    pub personal_conflict_policy: ConflictPolicy,
+
+    /// How conflicts in shared files are resolved
+    #[serde(default)]
+    pub shared_conflict_policy: ConflictPolicy,
+
+    /// Files larger than this are uploaded in resumable chunks (bytes, 0 = never)
+    #[serde(default = "default_chunked_upload_threshold")]
+    pub chunked_upload_threshold_bytes: u64,
+
+    /// Every this many hours a sync rehashes all local files instead of trusting
+    /// unchanged size/mtime/inode/ctime (0 = only when requested)
+    #[serde(default = "default_full_verify_interval")]
+    pub full_verify_interval_hours: u64,
+
+    /// Attempts per request before a failure that may be temporary is given up on
+    #[serde(default = "default_retry_attempts")]
+    pub retry_max_attempts: u32,
+
+    /// Longest wait between attempts of a request, in seconds; a server asking
+    /// for a longer wait (Retry-After) fails the request instead
+    #[serde(default = "default_retry_max_delay")]
+    pub retry_max_delay_secs: u64,
+
+    /// Longest wait before a file whose sync failed is tried again, in minutes;
+    /// the wait doubles with each failure (0 = try on every sync)
+    #[serde(default = "default_file_retry_max")]
+    pub file_retry_max_minutes: u64,
+}
+
+fn default_parallel_transfers() -> usize {
+    4
+}
+
+fn default_chunked_upload_threshold() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_full_verify_interval() -> u64 {
+    24
+}
+
+fn default_retry_attempts() -> u32 {
+    5
+}
+
+fn default_retry_max_delay() -> u64 {
+    60
+}
+
+fn default_file_retry_max() -> u64 {
+    60
+}
+
+/// Conflict handling policy for a sync zone.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Leave the file in conflict until the user resolves it
+    #[default]
+    Ask,
+    /// Rename the local file to "name (conflict <host> <date>).ext" and sync both
+    KeepBoth,
+    /// The side with the newer modification time wins
+    NewestWins,
+    /// The local version always wins
+    LocalWins,
+    /// The server version always wins
+    RemoteWins,
+}
+
+/// A weekly window of the bandwidth schedule, e.g. weekdays 8:00-17:00.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BandwidthRule {
+    /// Days the window starts on (1 = Monday ... 7 = Sunday)
+    pub days: Vec<u8>,
+    /// Start of the window in minutes after midnight
+    pub start_minute: u32,
+    /// End of the window in minutes after midnight (up to 1440); a window
+    /// ending at or before its start runs past midnight into the next day
+    pub end_minute: u32,
+    /// Max upload speed in KB/s during the window (0 = unlimited)
+    #[serde(default)]
+    pub upload_kbps: u64,
+    /// Max download speed in KB/s during the window (0 = unlimited)
+    #[serde(default)]
+    pub download_kbps: u64,
+    /// No sync runs and no data goes over the wire during the window (the
+    /// limits above do not apply); a run in progress when it starts is cancelled
+    #[serde(default)]
+    pub pause_sync: bool,
+}
+
+impl BandwidthRule {
+    /// Whether the window covers a minute of a day (1 = Monday)
+    fn covers(&self, day: u8, minute: u32) -> bool {
+        if self.start_minute < self.end_minute {
+            return self.days.contains(&day)
+                && (self.start_minute..self.end_minute).contains(&minute);
+        }
+        // Past midnight: the rest of the start day and the morning after it
+        let previous_day = if day == 1 { 7 } else { day - 1 };
+        (self.days.contains(&day) && minute >= self.start_minute)
+            || (self.days.contains(&previous_day) && minute < self.end_minute)
+    }
+}
+
+/// Bandwidth limits in force at some time.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct EffectiveBandwidth {
+    /// KB/s (0 = unlimited)
+    pub upload_kbps: u64,
+    /// KB/s (0 = unlimited)
+    pub download_kbps: u64,
+    /// Sync and transfers are paused by the schedule
+    pub paused: bool,
+    /// Index of the schedule window in force (None = the default limits)
+    pub rule: Option<usize>,
 }
 
 impl Default for AppConfig {
@@ -61,10 +201,21 @@ impl Default for AppConfig {
             sync_interval_secs: 300,
             watch_local_changes: true,
             sync_on_startup: true,
-            max_file_size_bytes: 0,
+            personal_max_file_size_bytes: 0,
+            shared_max_file_size_bytes: 0,
             max_upload_kbps: 0,
             max_download_kbps: 0,
+            bandwidth_schedule: Vec::new(),
+            max_parallel_uploads: default_parallel_transfers(),
+            max_parallel_downloads: default_parallel_transfers(),
             sync_include_paths: Vec::new(),
+            personal_conflict_policy: ConflictPolicy::Ask,
+            shared_conflict_policy: ConflictPolicy::Ask,
+            chunked_upload_threshold_bytes: default_chunked_upload_threshold(),
+            full_verify_interval_hours: default_full_verify_interval(),
+            retry_max_attempts: default_retry_attempts(),
+            retry_max_delay_secs: default_retry_max_delay(),
+            file_retry_max_minutes: default_file_retry_max(),
         }
     }
 }
@@ -84,6 +235,62 @@ impl AppConfig {
     pub fn shared_webdav_url(&self) -> String {
         format!("{}/dav/shared", self.server_url.trim_end_matches('/'))
     }
+
+    /// Get the file size limit for a zone ("personal" or "shared"), 0 = unlimited
+    pub fn max_file_size(&self, zone: &str) -> u64 {
+        match zone {
+            "shared" => self.shared_max_file_size_bytes,
+            _ => self.personal_max_file_size_bytes,
+        }
+    }
+
+    /// Get the WebDAV URL of the user's chunked-upload collection
+    pub fn uploads_webdav_url(&self) -> String {
+        format!(
+            "{}/dav/uploads/{}",
+            self.server_url.trim_end_matches('/'),
+            urlencoding::encode(&self.user_login)
+        )
+    }
+
+    /// Get the conflict policy configured for a zone ("personal" or "shared")
+    pub fn conflict_policy(&self, zone: &str) -> ConflictPolicy {
+        match zone {
+            "shared" => self.shared_conflict_policy,
+            _ => self.personal_conflict_policy,
+        }
+    }
+
+    /// Get the bandwidth limits in force now (local time)
+    pub fn effective_bandwidth(&self) -> EffectiveBandwidth {
+        self.bandwidth_at(chrono::Local::now().naive_local())
+    }
+
+    /// Get the bandwidth limits in force at a local time: those of the first
+    /// schedule window covering it, else the default limits
+    pub fn bandwidth_at(&self, time: chrono::NaiveDateTime) -> EffectiveBandwidth {
+        let day = time.weekday().number_from_monday() as u8;
+        let minute = time.hour() * 60 + time.minute();
+        let rule = self
+            .bandwidth_schedule
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.covers(day, minute));
+        match rule {
+            Some((index, rule)) => EffectiveBandwidth {
+                upload_kbps: rule.upload_kbps,
+                download_kbps: rule.download_kbps,
+                paused: rule.pause_sync,
+                rule: Some(index),
+            },
+            None => EffectiveBandwidth {
+                upload_kbps: self.max_upload_kbps,
+                download_kbps: self.max_download_kbps,
+                paused: false,
+                rule: None,
+            },
+        }
+    }
 }
 
 /// Sync status
@@ -141,6 +348,19 @@ pub fn save_debug_enabled(app: &AppHandle, enabled: bool) -> AppResult<()> {
 pub fn load_config(app: &AppHandle) -> Option<AppConfig> {
     let store = app.store(STORE_FILE).ok()?;
     let value = store.get(STORE_KEY)?;
+    config_from_value(value)
+}
+
+/// Read a stored config, upgrading the settings of older versions.
+fn config_from_value(mut value: serde_json::Value) -> Option<AppConfig> {
+    if let Some(fields) = value.as_object_mut() {
+        // The single size limit of old versions held for both zones
+        if !fields.contains_key("shared_max_file_size_bytes") {
+            if let Some(limit) = fields.get("max_file_size_bytes").cloned() {
+                fields.insert("shared_max_file_size_bytes".to_string(), limit);
+            }
+        }
+    }
     serde_json::from_value(value).ok()
 }
 
@@ -168,3 +388,119 @@ pub fn clear_config(app: &AppHandle) -> AppResult<()> {
         .save()
         .map_err(|e| AppError::config(format!("Nie udało się wyczyścić konfiguracji: {}", e)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M")
+            .unwrap()
+    }
+
+    fn office_hours() -> AppConfig {
+        AppConfig {
+            max_download_kbps: 10240,
+            bandwidth_schedule: vec![BandwidthRule {
+                days: vec![1, 2, 3, 4, 5],
+                start_minute: 8 * 60,
+                end_minute: 17 * 60,
+                upload_kbps: 512,
+                download_kbps: 0,
+                pause_sync: false,
+            }],
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn schedule_window_overrides_default_limits() {
+        let config = office_hours();
+        // 2026-10-12 is a Monday
+        let limits = config.bandwidth_at(at("2026-10-12", "08:00"));
+        assert_eq!(limits.upload_kbps, 512);
+        assert_eq!(limits.download_kbps, 0);
+        assert_eq!(limits.rule, Some(0));
+
+        // The end of the window is not part of it, nor is the weekend
+        for (date, time) in [
+            ("2026-10-12", "17:00"),
+            ("2026-10-12", "07:59"),
+            ("2026-10-17", "10:00"),
+        ] {
+            let limits = config.bandwidth_at(at(date, time));
+            assert_eq!(limits.rule, None, "{} {}", date, time);
+            assert_eq!(limits.upload_kbps, 0);
+            assert_eq!(limits.download_kbps, 10240);
+        }
+    }
+
+    #[test]
+    fn window_past_midnight_continues_into_next_day() {
+        let mut config = AppConfig::default();
+        config.bandwidth_schedule.push(BandwidthRule {
+            days: vec![7],
+            start_minute: 22 * 60,
+            end_minute: 6 * 60,
+            upload_kbps: 0,
+            download_kbps: 0,
+            pause_sync: true,
+        });
+
+        // Sunday 2026-10-18 23:00 and Monday 05:59, but not Sunday 05:00
+        assert!(config.bandwidth_at(at("2026-10-18", "23:00")).paused);
+        assert!(config.bandwidth_at(at("2026-10-19", "05:59")).paused);
+        assert!(!config.bandwidth_at(at("2026-10-18", "05:00")).paused);
+        assert!(!config.bandwidth_at(at("2026-10-19", "06:00")).paused);
+    }
+
+    #[test]
+    fn first_matching_window_wins() {
+        let mut config = office_hours();
+        config.bandwidth_schedule.push(BandwidthRule {
+            days: vec![1],
+            start_minute: 0,
+            end_minute: 1440,
+            upload_kbps: 0,
+            download_kbps: 0,
+            pause_sync: true,
+        });
+
+        let limits = config.bandwidth_at(at("2026-10-12", "12:00"));
+        assert_eq!(limits.rule, Some(0));
+        assert!(!limits.paused);
+        assert_eq!(config.bandwidth_at(at("2026-10-12", "20:00")).rule, Some(1));
+    }
+
+    /// A stored config as an older version wrote it, with one size limit.
+    fn old_config(max_file_size_bytes: u64) -> serde_json::Value {
+        let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("personal_max_file_size_bytes");
+        fields.remove("shared_max_file_size_bytes");
+        fields.insert(
+            "max_file_size_bytes".to_string(),
+            max_file_size_bytes.into(),
+        );
+        value
+    }
+
+    #[test]
+    fn old_size_limit_applies_to_both_zones() {
+        let config = config_from_value(old_config(1024)).unwrap();
+        assert_eq!(config.personal_max_file_size_bytes, 1024);
+        assert_eq!(config.shared_max_file_size_bytes, 1024);
+    }
+
+    #[test]
+    fn zone_size_limits_round_trip() {
+        let config = AppConfig {
+            personal_max_file_size_bytes: 1024,
+            shared_max_file_size_bytes: 0,
+            ..AppConfig::default()
+        };
+        let loaded = config_from_value(serde_json::to_value(&config).unwrap()).unwrap();
+        assert_eq!(loaded.max_file_size("personal"), 1024);
+        assert_eq!(loaded.max_file_size("shared"), 0);
+    }
+}
@@ -14,9 +14,15 @@ pub type DbPool = Arc<Mutex<Connection>>;
 pub struct FileState {
     pub path: String,
     pub sync_zone: String,
+    /// "file" or "dir"
+    pub kind: String,
     pub local_hash: Option<String>,
     pub local_mtime: Option<i64>,
     pub local_size: Option<i64>,
+    /// Inode and status-change time (ns) of the hashed file, where the OS has them;
+    /// with size and mtime they tell whether `local_hash` is still current
+    pub local_inode: Option<i64>,
+    pub local_ctime: Option<i64>,
     pub local_exists: bool,
     pub remote_etag: Option<String>,
     pub remote_mtime: Option<i64>,
@@ -29,6 +35,47 @@ pub struct FileState {
     pub last_synced_at: Option<String>,
     pub error_message: Option<String>,
     pub retry_count: i32,
+    /// Unix time before which a failed file is not tried again
+    pub next_retry_at: Option<i64>,
+}
+
+impl FileState {
+    pub fn is_dir(&self) -> bool {
+        self.kind == "dir"
+    }
+
+    /// The stored hash, if it was taken of a file with exactly this stat tuple.
+    pub fn cached_hash(&self, stat: &LocalStat) -> Option<&str> {
+        let unchanged = self.local_size == Some(stat.size)
+            && self.local_mtime == Some(stat.mtime)
+            && self.local_inode == stat.inode
+            && self.local_ctime == stat.ctime;
+        if unchanged {
+            self.local_hash.as_deref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Stat tuple of a local file. While it is unchanged, so is the file's hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalStat {
+    pub size: i64,
+    pub mtime: i64,
+    pub inode: Option<i64>,
+    pub ctime: Option<i64>,
+}
+
+/// A remote file or folder as the server last reported it.
+#[derive(Debug, Clone)]
+pub struct RemoteEntryRow {
+    pub path: String,
+    pub is_dir: bool,
+    pub etag: Option<String>,
+    pub size: Option<i64>,
+    pub last_modified: Option<String>,
+    pub checksum: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +99,18 @@ pub struct LocalTrashEntry {
     pub auto_delete_at: String,
 }
 
+/// An in-progress chunked upload; `chunks_done` chunks are already on the server.
+#[derive(Debug, Clone)]
+pub struct ChunkedUpload {
+    pub local_path: String,
+    pub remote_url: String,
+    pub upload_url: String,
+    pub file_size: i64,
+    pub file_mtime: i64,
+    pub chunk_size: i64,
+    pub chunks_done: i64,
+}
+
 // ==================== Init ====================
 
 /// Open or create the SQLite database with WAL mode.
@@ -156,6 +215,247 @@ pub fn migrate(conn: &Connection) -> AppResult<()> {
     )
     .map_err(|e| AppError::io(format!("Migration failed: {}", e)))?;
 
+    let version: i64 = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+        .map_err(|e| AppError::io(format!("Failed to read schema version: {}", e)))?;
+
+    if version < 2 {
+        conn.execute_batch(
+            "
+            BEGIN;
+            ALTER TABLE file_state ADD COLUMN conflict_type TEXT;
+            INSERT INTO schema_version (version) VALUES (2);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v2 failed: {}", e)))?;
+    }
+
+    if version < 3 {
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE IF NOT EXISTS partial_download (
+                local_path TEXT PRIMARY KEY,
+                etag TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT INTO schema_version (version) VALUES (3);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v3 failed: {}", e)))?;
+    }
+
+    if version < 4 {
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE IF NOT EXISTS chunked_upload (
+                local_path TEXT PRIMARY KEY,
+                remote_url TEXT NOT NULL,
+                upload_url TEXT NOT NULL,
+                file_size INTEGER NOT NULL,
+                file_mtime INTEGER NOT NULL,
+                chunk_size INTEGER NOT NULL,
+                chunks_done INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT INTO schema_version (version) VALUES (4);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v4 failed: {}", e)))?;
+    }
+
+    if version < 5 {
+        // SQLite cannot alter a CHECK constraint: rebuild file_state to allow 'skipped'
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE file_state_v5 (
+                path TEXT NOT NULL,
+                sync_zone TEXT NOT NULL CHECK (sync_zone IN ('personal', 'shared')),
+                local_hash TEXT,
+                local_mtime INTEGER,
+                local_size INTEGER,
+                local_exists INTEGER NOT NULL DEFAULT 1,
+                remote_etag TEXT,
+                remote_mtime INTEGER,
+                remote_size INTEGER,
+                remote_exists INTEGER NOT NULL DEFAULT 1,
+                sync_status TEXT NOT NULL DEFAULT 'unknown'
+                    CHECK (sync_status IN (
+                        'synced', 'local_new', 'local_modified', 'local_deleted',
+                        'remote_new', 'remote_modified', 'remote_deleted',
+                        'conflict', 'error', 'skipped', 'unknown'
+                    )),
+                last_synced_hash TEXT,
+                last_synced_mtime INTEGER,
+                last_synced_etag TEXT,
+                last_synced_at TEXT,
+                error_message TEXT,
+                retry_count INTEGER NOT NULL DEFAULT 0,
+                last_error_at TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                conflict_type TEXT,
+                PRIMARY KEY (path, sync_zone)
+            );
+            INSERT INTO file_state_v5 SELECT
+                path, sync_zone, local_hash, local_mtime, local_size, local_exists,
+                remote_etag, remote_mtime, remote_size, remote_exists, sync_status,
+                last_synced_hash, last_synced_mtime, last_synced_etag, last_synced_at,
+                error_message, retry_count, last_error_at, created_at, updated_at, conflict_type
+            FROM file_state;
+            DROP TABLE file_state;
+            ALTER TABLE file_state_v5 RENAME TO file_state;
+            CREATE INDEX IF NOT EXISTS idx_file_state_dirty
+                ON file_state(sync_status) WHERE sync_status NOT IN ('synced', 'unknown');
+            INSERT INTO schema_version (version) VALUES (5);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v5 failed: {}", e)))?;
+    }
+
+    if version < 6 {
+        // Rebuild sync_run to allow the 'cancelled' status
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE sync_run_v6 (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                completed_at TEXT,
+                status TEXT NOT NULL
+                    CHECK (status IN ('running', 'success', 'error', 'partial', 'cancelled')),
+                source TEXT,
+                files_uploaded INTEGER DEFAULT 0,
+                files_downloaded INTEGER DEFAULT 0,
+                files_deleted INTEGER DEFAULT 0,
+                files_conflicted INTEGER DEFAULT 0,
+                bytes_transferred INTEGER DEFAULT 0,
+                error_message TEXT,
+                duration_ms INTEGER
+            );
+            INSERT INTO sync_run_v6 SELECT
+                id, started_at, completed_at, status, source,
+                files_uploaded, files_downloaded, files_deleted, files_conflicted,
+                bytes_transferred, error_message, duration_ms
+            FROM sync_run;
+            DROP TABLE sync_run;
+            ALTER TABLE sync_run_v6 RENAME TO sync_run;
+            INSERT INTO schema_version (version) VALUES (6);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v6 failed: {}", e)))?;
+    }
+
+    if version < 7 {
+        // Directories are tracked too, so empty ones and deletions can be synced
+        conn.execute_batch(
+            "
+            BEGIN;
+            ALTER TABLE file_state ADD COLUMN kind TEXT NOT NULL DEFAULT 'file'
+                CHECK (kind IN ('file', 'dir'));
+            INSERT INTO schema_version (version) VALUES (7);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v7 failed: {}", e)))?;
+    }
+
+    if version < 8 {
+        // Stat cache: a file whose size, mtime, inode and ctime are unchanged is not rehashed
+        conn.execute_batch(
+            "
+            BEGIN;
+            ALTER TABLE file_state ADD COLUMN local_inode INTEGER;
+            ALTER TABLE file_state ADD COLUMN local_ctime INTEGER;
+            INSERT INTO schema_version (version) VALUES (8);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v8 failed: {}", e)))?;
+    }
+
+    if version < 9 {
+        // Remote snapshot kept up to date from sync-collection (RFC 6578) changes
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE IF NOT EXISTS remote_entry (
+                sync_zone TEXT NOT NULL,
+                path TEXT NOT NULL,
+                is_dir INTEGER NOT NULL DEFAULT 0,
+                etag TEXT,
+                size INTEGER,
+                last_modified TEXT,
+                checksum TEXT,
+                PRIMARY KEY (path, sync_zone)
+            );
+            CREATE TABLE IF NOT EXISTS sync_token (
+                sync_zone TEXT PRIMARY KEY,
+                collection_url TEXT NOT NULL,
+                token TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+            INSERT INTO schema_version (version) VALUES (9);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v9 failed: {}", e)))?;
+    }
+
+    if version < 10 {
+        // Folder change tags of the last remote walk: unchanged subtrees are not listed again
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE IF NOT EXISTS remote_dir_state (
+                sync_zone TEXT NOT NULL,
+                path TEXT NOT NULL,
+                etag TEXT NOT NULL,
+                PRIMARY KEY (path, sync_zone)
+            );
+            INSERT INTO schema_version (version) VALUES (10);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v10 failed: {}", e)))?;
+    }
+
+    if version < 11 {
+        // Backoff of files whose sync failed, so they are not retried on every run
+        conn.execute_batch(
+            "
+            BEGIN;
+            ALTER TABLE file_state ADD COLUMN next_retry_at INTEGER;
+            INSERT INTO schema_version (version) VALUES (11);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v11 failed: {}", e)))?;
+    }
+
+    if version < 12 {
+        // When each zone last had every local file rehashed, across app restarts
+        conn.execute_batch(
+            "
+            BEGIN;
+            CREATE TABLE IF NOT EXISTS full_verify (
+                sync_zone TEXT PRIMARY KEY,
+                verified_at INTEGER NOT NULL
+            );
+            INSERT INTO schema_version (version) VALUES (12);
+            COMMIT;
+            ",
+        )
+        .map_err(|e| AppError::io(format!("Migration to v12 failed: {}", e)))?;
+    }
+
     Ok(())
 }
 
@@ -171,7 +471,7 @@ pub fn get_file_state(pool: &DbPool, path: &str, zone: &str) -> AppResult<Option
             "SELECT path, sync_zone, local_hash, local_mtime, local_size, local_exists,
                     remote_etag, remote_mtime, remote_size, remote_exists,
                     sync_status, last_synced_hash, last_synced_mtime, last_synced_etag, last_synced_at,
-                    error_message, retry_count
+                    error_message, retry_count, kind, local_inode, local_ctime, next_retry_at
              FROM file_state
              WHERE path = ?1 AND sync_zone = ?2",
         )
@@ -197,6 +497,10 @@ pub fn get_file_state(pool: &DbPool, path: &str, zone: &str) -> AppResult<Option
                 last_synced_at: row.get(14)?,
                 error_message: row.get(15)?,
                 retry_count: row.get(16)?,
+                kind: row.get(17)?,
+                local_inode: row.get(18)?,
+                local_ctime: row.get(19)?,
+                next_retry_at: row.get(20)?,
             })
         })
         .optional()
@@ -215,9 +519,12 @@ pub fn upsert_file_state(pool: &DbPool, state: &FileState) -> AppResult<()> {
             path, sync_zone, local_hash, local_mtime, local_size, local_exists,
             remote_etag, remote_mtime, remote_size, remote_exists,
             sync_status, last_synced_hash, last_synced_mtime, last_synced_etag, last_synced_at,
-            error_message, retry_count, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, datetime('now'))
+            error_message, retry_count, kind, local_inode, local_ctime, next_retry_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, datetime('now'))
         ON CONFLICT(path, sync_zone) DO UPDATE SET
+            kind = excluded.kind,
+            local_inode = excluded.local_inode,
+            local_ctime = excluded.local_ctime,
             local_hash = excluded.local_hash,
             local_mtime = excluded.local_mtime,
             local_size = excluded.local_size,
@@ -233,6 +540,7 @@ pub fn upsert_file_state(pool: &DbPool, state: &FileState) -> AppResult<()> {
             last_synced_at = excluded.last_synced_at,
             error_message = excluded.error_message,
             retry_count = excluded.retry_count,
+            next_retry_at = excluded.next_retry_at,
             updated_at = datetime('now')",
         params![
             state.path,
@@ -252,6 +560,10 @@ pub fn upsert_file_state(pool: &DbPool, state: &FileState) -> AppResult<()> {
             state.last_synced_at,
             state.error_message,
             state.retry_count,
+            state.kind,
+            state.local_inode,
+            state.local_ctime,
+            state.next_retry_at,
         ],
     )
     .map_err(|e| AppError::io(format!("Failed to upsert file state: {}", e)))?;
@@ -283,7 +595,7 @@ pub fn list_files_by_zone(pool: &DbPool, zone: &str) -> AppResult<Vec<FileState>
             "SELECT path, sync_zone, local_hash, local_mtime, local_size, local_exists,
                     remote_etag, remote_mtime, remote_size, remote_exists,
                     sync_status, last_synced_hash, last_synced_mtime, last_synced_etag, last_synced_at,
-                    error_message, retry_count
+                    error_message, retry_count, kind, local_inode, local_ctime, next_retry_at
              FROM file_state
              WHERE sync_zone = ?1
              ORDER BY path",
@@ -310,6 +622,10 @@ pub fn list_files_by_zone(pool: &DbPool, zone: &str) -> AppResult<Vec<FileState>
                 last_synced_at: row.get(14)?,
                 error_message: row.get(15)?,
                 retry_count: row.get(16)?,
+                kind: row.get(17)?,
+                local_inode: row.get(18)?,
+                local_ctime: row.get(19)?,
+                next_retry_at: row.get(20)?,
             })
         })
         .map_err(|e| AppError::io(format!("Failed to list files by zone: {}", e)))?;
@@ -328,7 +644,7 @@ pub fn get_dirty_files(pool: &DbPool) -> AppResult<Vec<FileState>> {
             "SELECT path, sync_zone, local_hash, local_mtime, local_size, local_exists,
                     remote_etag, remote_mtime, remote_size, remote_exists,
                     sync_status, last_synced_hash, last_synced_mtime, last_synced_etag, last_synced_at,
-                    error_message, retry_count
+                    error_message, retry_count, kind, local_inode, local_ctime, next_retry_at
              FROM file_state
              WHERE sync_status NOT IN ('synced', 'unknown')
              ORDER BY path",
@@ -355,6 +671,10 @@ pub fn get_dirty_files(pool: &DbPool) -> AppResult<Vec<FileState>> {
                 last_synced_at: row.get(14)?,
                 error_message: row.get(15)?,
                 retry_count: row.get(16)?,
+                kind: row.get(17)?,
+                local_inode: row.get(18)?,
+                local_ctime: row.get(19)?,
+                next_retry_at: row.get(20)?,
             })
         })
         .map_err(|e| AppError::io(format!("Failed to query dirty files: {}", e)))?;
@@ -377,6 +697,7 @@ pub fn mark_synced(pool: &DbPool, path: &str, zone: &str, remote_etag: &str) ->
              last_synced_at = datetime('now'),
              error_message = NULL,
              retry_count = 0,
+             next_retry_at = NULL,
              last_error_at = NULL,
              updated_at = datetime('now')
          WHERE path = ?1 AND sync_zone = ?2",
@@ -387,6 +708,475 @@ pub fn mark_synced(pool: &DbPool, path: &str, zone: &str, remote_etag: &str) ->
     Ok(())
 }
 
+/// Store the current stat tuple of synced files whose content was rehashed and
+/// found unchanged, so the next scan can skip hashing them.
+pub fn refresh_local_stats(pool: &DbPool, zone: &str, stats: &[(String, LocalStat)]) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::io(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "UPDATE file_state
+                 SET local_size = ?3, local_mtime = ?4, local_inode = ?5, local_ctime = ?6
+                 WHERE path = ?1 AND sync_zone = ?2 AND sync_status = 'synced'",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for (path, stat) in stats {
+            stmt.execute(params![path, zone, stat.size, stat.mtime, stat.inode, stat.ctime])
+                .map_err(|e| AppError::io(format!("Failed to refresh local stat: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| AppError::io(format!("Failed to commit local stats: {}", e)))
+}
+
+/// Let every failed file be tried again by the next run, whatever its backoff.
+pub fn clear_retry_backoff(pool: &DbPool) -> AppResult<usize> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "UPDATE file_state SET next_retry_at = NULL
+         WHERE sync_status = 'error' AND next_retry_at IS NOT NULL",
+        [],
+    )
+    .map_err(|e| AppError::io(format!("Failed to clear retry backoff: {}", e)))
+}
+
+/// Unix time of the last full verify of a zone (every local file rehashed), if any.
+pub fn get_last_full_verify(pool: &DbPool, zone: &str) -> AppResult<Option<i64>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.query_row(
+        "SELECT verified_at FROM full_verify WHERE sync_zone = ?1",
+        params![zone],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| AppError::io(format!("Failed to get last full verify: {}", e)))
+}
+
+pub fn set_last_full_verify(pool: &DbPool, zone: &str, verified_at: i64) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "INSERT INTO full_verify (sync_zone, verified_at) VALUES (?1, ?2)
+         ON CONFLICT(sync_zone) DO UPDATE SET verified_at = excluded.verified_at",
+        params![zone, verified_at],
+    )
+    .map_err(|e| AppError::io(format!("Failed to record full verify: {}", e)))?;
+
+    Ok(())
+}
+
+/// Record directories that exist on both sides as synced, keeping existing rows.
+pub fn track_synced_dirs(pool: &DbPool, zone: &str, paths: &[&str]) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::io(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO file_state (
+                    path, sync_zone, kind, local_exists, remote_exists,
+                    sync_status, last_synced_at
+                ) VALUES (?1, ?2, 'dir', 1, 1, 'synced', datetime('now'))
+                ON CONFLICT(path, sync_zone) DO NOTHING",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for path in paths {
+            stmt.execute(params![path, zone])
+                .map_err(|e| AppError::io(format!("Failed to track directory: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| AppError::io(format!("Failed to commit directories: {}", e)))
+}
+
+/// Delete the row of `path` and of everything below it.
+pub fn delete_file_states_under(pool: &DbPool, path: &str, zone: &str) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "DELETE FROM file_state
+         WHERE sync_zone = ?1
+           AND (path = ?2 OR substr(path, 1, length(?3)) = ?3)",
+        params![zone, path, format!("{}/", path)],
+    )
+    .map_err(|e| AppError::io(format!("Failed to delete file states: {}", e)))?;
+
+    Ok(())
+}
+
+/// Re-key the rows of a moved file, or of every file under a moved directory,
+/// from `from` to `to`. Stale rows already at the destination are replaced.
+pub fn rename_file_states(pool: &DbPool, zone: &str, from: &str, to: &str) -> AppResult<usize> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let from_prefix = format!("{}/", from);
+    let to_prefix = format!("{}/", to);
+
+    conn.execute(
+        "DELETE FROM file_state
+         WHERE sync_zone = ?1
+           AND (path = ?2 OR substr(path, 1, length(?3)) = ?3)",
+        params![zone, to, to_prefix],
+    )
+    .map_err(|e| AppError::io(format!("Failed to clear move destination: {}", e)))?;
+
+    conn.execute(
+        "UPDATE file_state
+         SET path = ?3 || substr(path, length(?2) + 1),
+             updated_at = datetime('now')
+         WHERE sync_zone = ?1
+           AND (path = ?2 OR substr(path, 1, length(?4)) = ?4)",
+        params![zone, from, to, from_prefix],
+    )
+    .map_err(|e| AppError::io(format!("Failed to rename file state: {}", e)))
+}
+
+// ==================== Remote Snapshot ====================
+
+/// The sync-token the remote snapshot of a zone is current with, if it was taken
+/// from `collection_url` (a changed server or account starts over). Empty when
+/// the server does not support sync-collection.
+pub fn get_sync_token(pool: &DbPool, zone: &str, collection_url: &str) -> AppResult<Option<String>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.query_row(
+        "SELECT token FROM sync_token WHERE sync_zone = ?1 AND collection_url = ?2",
+        params![zone, collection_url],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| AppError::io(format!("Failed to get sync token: {}", e)))
+}
+
+pub fn list_remote_entries(pool: &DbPool, zone: &str) -> AppResult<Vec<RemoteEntryRow>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, is_dir, etag, size, last_modified, checksum
+             FROM remote_entry WHERE sync_zone = ?1",
+        )
+        .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+
+    let rows = stmt
+        .query_map(params![zone], |row| {
+            Ok(RemoteEntryRow {
+                path: row.get(0)?,
+                is_dir: row.get::<_, i64>(1)? != 0,
+                etag: row.get(2)?,
+                size: row.get(3)?,
+                last_modified: row.get(4)?,
+                checksum: row.get(5)?,
+            })
+        })
+        .map_err(|e| AppError::io(format!("Failed to list remote entries: {}", e)))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row.map_err(|e| AppError::io(format!("Failed to read row: {}", e)))?);
+    }
+    Ok(results)
+}
+
+/// Apply one batch of remote changes to the snapshot of a zone and store the
+/// sync-token of `collection_url` it brings the snapshot up to, all at once.
+/// `reset` replaces the whole snapshot (the changes are a complete listing).
+/// A removed folder takes everything below it along.
+pub fn apply_remote_changes(
+    pool: &DbPool,
+    zone: &str,
+    collection_url: &str,
+    reset: bool,
+    changed: &[RemoteEntryRow],
+    removed: &[String],
+    sync_token: &str,
+) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::io(format!("Failed to begin transaction: {}", e)))?;
+    if reset {
+        tx.execute("DELETE FROM remote_entry WHERE sync_zone = ?1", params![zone])
+            .map_err(|e| AppError::io(format!("Failed to clear remote entries: {}", e)))?;
+    }
+    {
+        let mut delete = tx
+            .prepare(
+                "DELETE FROM remote_entry
+                 WHERE sync_zone = ?1
+                   AND (path = ?2 OR substr(path, 1, length(?3)) = ?3)",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for path in removed {
+            delete
+                .execute(params![zone, path, format!("{}/", path)])
+                .map_err(|e| AppError::io(format!("Failed to delete remote entry: {}", e)))?;
+        }
+
+        let mut upsert = tx
+            .prepare(
+                "INSERT INTO remote_entry (
+                    path, sync_zone, is_dir, etag, size, last_modified, checksum
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT(path, sync_zone) DO UPDATE SET
+                    is_dir = excluded.is_dir,
+                    etag = excluded.etag,
+                    size = excluded.size,
+                    last_modified = excluded.last_modified,
+                    checksum = excluded.checksum",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for entry in changed {
+            upsert
+                .execute(params![
+                    entry.path,
+                    zone,
+                    entry.is_dir as i64,
+                    entry.etag,
+                    entry.size,
+                    entry.last_modified,
+                    entry.checksum,
+                ])
+                .map_err(|e| AppError::io(format!("Failed to store remote entry: {}", e)))?;
+        }
+    }
+    tx.execute(
+        "INSERT INTO sync_token (sync_zone, collection_url, token, updated_at)
+         VALUES (?1, ?2, ?3, datetime('now'))
+         ON CONFLICT(sync_zone) DO UPDATE SET
+            collection_url = excluded.collection_url,
+            token = excluded.token,
+            updated_at = datetime('now')",
+        params![zone, collection_url, sync_token],
+    )
+    .map_err(|e| AppError::io(format!("Failed to save sync token: {}", e)))?;
+    tx.commit()
+        .map_err(|e| AppError::io(format!("Failed to commit remote changes: {}", e)))
+}
+
+/// Remember that `collection_url` does not support sync-collection (stored as
+/// an empty token). The snapshot stays: the remote walk that follows replaces it.
+pub fn mark_sync_collection_unsupported(pool: &DbPool, zone: &str, collection_url: &str) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "INSERT INTO sync_token (sync_zone, collection_url, token, updated_at)
+         VALUES (?1, ?2, '', datetime('now'))
+         ON CONFLICT(sync_zone) DO UPDATE SET
+            collection_url = excluded.collection_url,
+            token = '',
+            updated_at = datetime('now')",
+        params![zone, collection_url],
+    )
+    .map_err(|e| AppError::io(format!("Failed to save sync token: {}", e)))?;
+
+    Ok(())
+}
+
+/// Change tag of every folder of a zone as of the last remote walk ("" = the root).
+pub fn list_remote_dir_tags(pool: &DbPool, zone: &str) -> AppResult<Vec<(String, String)>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut stmt = conn
+        .prepare("SELECT path, etag FROM remote_dir_state WHERE sync_zone = ?1")
+        .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+
+    let rows = stmt
+        .query_map(params![zone], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| AppError::io(format!("Failed to list remote folders: {}", e)))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row.map_err(|e| AppError::io(format!("Failed to read row: {}", e)))?);
+    }
+    Ok(results)
+}
+
+/// Replace the remote snapshot and folder change tags of a zone with the result
+/// of a full remote walk.
+pub fn save_remote_walk(
+    pool: &DbPool,
+    zone: &str,
+    entries: &[RemoteEntryRow],
+    dir_tags: &[(String, String)],
+) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::io(format!("Failed to begin transaction: {}", e)))?;
+    tx.execute("DELETE FROM remote_entry WHERE sync_zone = ?1", params![zone])
+        .map_err(|e| AppError::io(format!("Failed to clear remote entries: {}", e)))?;
+    tx.execute("DELETE FROM remote_dir_state WHERE sync_zone = ?1", params![zone])
+        .map_err(|e| AppError::io(format!("Failed to clear remote folders: {}", e)))?;
+    {
+        let mut insert_entry = tx
+            .prepare(
+                "INSERT OR REPLACE INTO remote_entry (
+                    path, sync_zone, is_dir, etag, size, last_modified, checksum
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for entry in entries {
+            insert_entry
+                .execute(params![
+                    entry.path,
+                    zone,
+                    entry.is_dir as i64,
+                    entry.etag,
+                    entry.size,
+                    entry.last_modified,
+                    entry.checksum,
+                ])
+                .map_err(|e| AppError::io(format!("Failed to store remote entry: {}", e)))?;
+        }
+
+        let mut insert_tag = tx
+            .prepare(
+                "INSERT OR REPLACE INTO remote_dir_state (path, sync_zone, etag)
+                 VALUES (?1, ?2, ?3)",
+            )
+            .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+        for (path, etag) in dir_tags {
+            insert_tag
+                .execute(params![path, zone, etag])
+                .map_err(|e| AppError::io(format!("Failed to store remote folder: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| AppError::io(format!("Failed to commit remote walk: {}", e)))
+}
+
+// ==================== Conflicts ====================
+
+/// A file waiting for the user to resolve a conflict.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConflictEntry {
+    pub path: String,
+    pub sync_zone: String,
+    pub conflict_type: Option<String>,
+    pub local_hash: Option<String>,
+    pub local_size: Option<i64>,
+    pub local_mtime: Option<i64>,
+    pub local_exists: bool,
+    pub remote_etag: Option<String>,
+    pub remote_size: Option<i64>,
+    pub remote_mtime: Option<i64>,
+    pub remote_exists: bool,
+    pub detected_at: String,
+}
+
+pub fn set_conflict_type(
+    pool: &DbPool,
+    path: &str,
+    zone: &str,
+    conflict_type: &str,
+) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "UPDATE file_state SET conflict_type = ?3 WHERE path = ?1 AND sync_zone = ?2",
+        params![path, zone, conflict_type],
+    )
+    .map_err(|e| AppError::io(format!("Failed to set conflict type: {}", e)))?;
+
+    Ok(())
+}
+
+/// List all unresolved conflicts across both zones.
+pub fn list_conflicts(pool: &DbPool) -> AppResult<Vec<ConflictEntry>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, sync_zone, conflict_type,
+                    local_hash, local_size, local_mtime, local_exists,
+                    remote_etag, remote_size, remote_mtime, remote_exists,
+                    updated_at
+             FROM file_state
+             WHERE sync_status = 'conflict'
+             ORDER BY sync_zone, path",
+        )
+        .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ConflictEntry {
+                path: row.get(0)?,
+                sync_zone: row.get(1)?,
+                conflict_type: row.get(2)?,
+                local_hash: row.get(3)?,
+                local_size: row.get(4)?,
+                local_mtime: row.get(5)?,
+                local_exists: row.get::<_, i64>(6)? != 0,
+                remote_etag: row.get(7)?,
+                remote_size: row.get(8)?,
+                remote_mtime: row.get(9)?,
+                remote_exists: row.get::<_, i64>(10)? != 0,
+                detected_at: row.get(11)?,
+            })
+        })
+        .map_err(|e| AppError::io(format!("Failed to query conflicts: {}", e)))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::io(format!("Failed to collect conflicts: {}", e)))
+}
+
+pub fn count_conflicts(pool: &DbPool) -> AppResult<usize> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM file_state WHERE sync_status = 'conflict'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::io(format!("Failed to count conflicts: {}", e)))?;
+
+    Ok(count as usize)
+}
+
 // ==================== Sync Run Tracking ====================
 
 pub fn start_sync_run(pool: &DbPool, source: &str) -> AppResult<i64> {
@@ -581,3 +1371,162 @@ pub fn cleanup_expired_trash(pool: &DbPool) -> AppResult<usize> {
 
     Ok(count)
 }
+
+// ==================== Partial Downloads ====================
+
+/// Etag of the remote version a partial download of `local_path` belongs to.
+pub fn get_partial_download(pool: &DbPool, local_path: &str) -> AppResult<Option<String>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.query_row(
+        "SELECT etag FROM partial_download WHERE local_path = ?1",
+        params![local_path],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| AppError::io(format!("Failed to get partial download: {}", e)))
+}
+
+pub fn save_partial_download(pool: &DbPool, local_path: &str, etag: &str) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "INSERT INTO partial_download (local_path, etag, updated_at)
+         VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(local_path) DO UPDATE SET
+            etag = excluded.etag,
+            updated_at = datetime('now')",
+        params![local_path, etag],
+    )
+    .map_err(|e| AppError::io(format!("Failed to save partial download: {}", e)))?;
+
+    Ok(())
+}
+
+pub fn delete_partial_download(pool: &DbPool, local_path: &str) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "DELETE FROM partial_download WHERE local_path = ?1",
+        params![local_path],
+    )
+    .map_err(|e| AppError::io(format!("Failed to delete partial download: {}", e)))?;
+
+    Ok(())
+}
+
+/// Forget partial downloads not touched for `max_age_days`, then return the
+/// target paths of the remaining ones.
+pub fn prune_partial_downloads(pool: &DbPool, max_age_days: u32) -> AppResult<Vec<String>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "DELETE FROM partial_download WHERE updated_at <= datetime('now', ?1)",
+        params![format!("-{} days", max_age_days)],
+    )
+    .map_err(|e| AppError::io(format!("Failed to prune partial downloads: {}", e)))?;
+
+    let mut stmt = conn
+        .prepare("SELECT local_path FROM partial_download")
+        .map_err(|e| AppError::io(format!("Failed to prepare statement: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| AppError::io(format!("Failed to query partial downloads: {}", e)))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::io(format!("Failed to collect partial downloads: {}", e)))
+}
+
+// ==================== Chunked Uploads ====================
+
+pub fn get_chunked_upload(pool: &DbPool, local_path: &str) -> AppResult<Option<ChunkedUpload>> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.query_row(
+        "SELECT local_path, remote_url, upload_url, file_size, file_mtime, chunk_size, chunks_done
+         FROM chunked_upload WHERE local_path = ?1",
+        params![local_path],
+        |row| {
+            Ok(ChunkedUpload {
+                local_path: row.get(0)?,
+                remote_url: row.get(1)?,
+                upload_url: row.get(2)?,
+                file_size: row.get(3)?,
+                file_mtime: row.get(4)?,
+                chunk_size: row.get(5)?,
+                chunks_done: row.get(6)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| AppError::io(format!("Failed to get chunked upload: {}", e)))
+}
+
+pub fn save_chunked_upload(pool: &DbPool, upload: &ChunkedUpload) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "INSERT OR REPLACE INTO chunked_upload (
+            local_path, remote_url, upload_url, file_size, file_mtime,
+            chunk_size, chunks_done, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))",
+        params![
+            upload.local_path,
+            upload.remote_url,
+            upload.upload_url,
+            upload.file_size,
+            upload.file_mtime,
+            upload.chunk_size,
+            upload.chunks_done,
+        ],
+    )
+    .map_err(|e| AppError::io(format!("Failed to save chunked upload: {}", e)))?;
+
+    Ok(())
+}
+
+pub fn set_chunked_upload_progress(
+    pool: &DbPool,
+    local_path: &str,
+    chunks_done: i64,
+) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "UPDATE chunked_upload SET chunks_done = ?2, updated_at = datetime('now')
+         WHERE local_path = ?1",
+        params![local_path, chunks_done],
+    )
+    .map_err(|e| AppError::io(format!("Failed to update chunked upload: {}", e)))?;
+
+    Ok(())
+}
+
+pub fn delete_chunked_upload(pool: &DbPool, local_path: &str) -> AppResult<()> {
+    let conn = pool
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    conn.execute(
+        "DELETE FROM chunked_upload WHERE local_path = ?1",
+        params![local_path],
+    )
+    .map_err(|e| AppError::io(format!("Failed to delete chunked upload: {}", e)))?;
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::db::FileState;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Action to perform during sync, determined by 3-way diff.
 #[derive(Debug, Clone, serde::Serialize)]
@@ -28,10 +28,50 @@ pub enum SyncAction {
         remote_path: String,
         conflict_type: ConflictType,
     },
+    /// Replay a move seen on one side on the other side — the content is unchanged,
+    /// so nothing is transferred. For a directory this is one MOVE of the collection.
+    Move {
+        /// Relative path before the move (the result's `path` is the new one).
+        from: String,
+        /// Path before the move on the side the move is replayed on.
+        source: String,
+        /// Path after the move on that side.
+        destination: String,
+        side: MoveSide,
+    },
+    /// Create a folder that exists only remotely (e.g. an empty one).
+    CreateLocalDir {
+        local_path: String,
+    },
+    /// Create a folder that exists only locally.
+    CreateRemoteDir {
+        remote_path: String,
+    },
+    /// Remove a local folder deleted on the remote, if it is empty by now.
+    DeleteLocalDir {
+        local_path: String,
+    },
+    /// Remove a remote folder deleted locally, if it is empty by now.
+    DeleteRemoteDir {
+        remote_path: String,
+    },
     /// No action needed — file is in sync.
     Skip,
 }
 
+impl SyncAction {
+    /// True for the actions on folders rather than files.
+    pub fn is_directory(&self) -> bool {
+        matches!(
+            self,
+            SyncAction::CreateLocalDir { .. }
+                | SyncAction::CreateRemoteDir { .. }
+                | SyncAction::DeleteLocalDir { .. }
+                | SyncAction::DeleteRemoteDir { .. }
+        )
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub enum ConflictType {
     /// Both local and remote were modified independently.
@@ -42,6 +82,15 @@ pub enum ConflictType {
     DeletedRemotelyModifiedLocally,
 }
 
+/// Side a `SyncAction::Move` is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum MoveSide {
+    /// Moved on the server → rename the local file.
+    Local,
+    /// Moved locally → WebDAV MOVE on the server.
+    Remote,
+}
+
 /// Snapshot of a local file from filesystem scan.
 #[derive(Debug, Clone)]
 pub struct LocalFileInfo {
@@ -58,6 +107,8 @@ pub struct RemoteFileInfo {
     pub etag: Option<String>,
     pub mtime: Option<i64>,
     pub size: Option<i64>,
+    /// Server-provided checksums ("SHA1:… BLAKE3:…"), if any.
+    pub checksum: Option<String>,
 }
 
 /// Result of the 3-way diff for a single file.
@@ -80,10 +131,15 @@ pub struct DiffResult {
 /// | missing    | exists     | existed    | DeleteLocal was done / DL |
 /// | exists     | missing    | existed    | DeleteRemote was done / U |
 /// | missing    | missing    | existed    | Already synced delete     |
+///
+/// A delete paired with a new file of the same content (local: `last_synced_hash`,
+/// remote: etag and size) becomes a `Move`; see `detect_moves`. Files left out of
+/// the diff are listed in `excluded`, so a folder holding some is not moved whole.
 pub fn compute_diff(
     local_files: &[LocalFileInfo],
     remote_files: &[RemoteFileInfo],
     known_states: &[FileState],
+    excluded: &ExcludedPaths,
     sync_zone: &str,
     local_base: &str,
     remote_base: &str,
@@ -132,9 +188,374 @@ pub fn compute_diff(
 
     // Filter out Skip actions
     results.retain(|r| !matches!(r.action, SyncAction::Skip));
+
+    let moves = detect_moves(&results, &local_map, &remote_map, &known_map, excluded);
+    if moves.is_empty() {
+        return results;
+    }
+
+    // The delete and the new-file action of every moved path are replaced by the move
+    let moved: HashSet<&str> = moves
+        .iter()
+        .flat_map(|m| m.files.iter().flat_map(|(from, to)| [from.as_str(), to.as_str()]))
+        .collect();
+    let mut merged: Vec<DiffResult> = results
+        .iter()
+        .filter(|r| !moved.contains(r.path.as_str()))
+        .cloned()
+        .collect();
+    for m in moves {
+        let (source, destination) = match m.side {
+            MoveSide::Local => (
+                format!("{}/{}", local_base, m.from),
+                format!("{}/{}", local_base, m.to),
+            ),
+            MoveSide::Remote => (
+                format!("{}/{}", remote_base, m.from),
+                format!("{}/{}", remote_base, m.to),
+            ),
+        };
+        merged.push(DiffResult {
+            path: m.to,
+            action: SyncAction::Move {
+                from: m.from,
+                source,
+                destination,
+                side: m.side,
+            },
+        });
+    }
+    merged.sort_by(|a, b| a.path.cmp(&b.path));
+    merged
+}
+
+/// Compute folder actions from the folders found on both sides and the `dir`
+/// rows of the last sync. Folders have no content, so only presence is compared:
+///
+/// | Local   | Remote  | Last Known | Action          |
+/// |---------|---------|------------|-----------------|
+/// | exists  | missing | missing    | CreateRemoteDir |
+/// | missing | exists  | missing    | CreateLocalDir  |
+/// | exists  | missing | existed    | DeleteLocalDir  |
+/// | missing | exists  | existed    | DeleteRemoteDir |
+///
+/// A folder delete is only emitted for the outermost deleted folder, and not at
+/// all while the other side still adds or changes something inside it — then the
+/// folder is recreated instead. Folders inside a move are handled by the move.
+pub fn compute_dir_diff(
+    local_dirs: &[String],
+    remote_dirs: &[String],
+    known_dirs: &[FileState],
+    file_actions: &[DiffResult],
+    sync_zone: &str,
+    local_base: &str,
+    remote_base: &str,
+) -> Vec<DiffResult> {
+    let local: HashSet<&str> = local_dirs.iter().map(String::as_str).collect();
+    let remote: HashSet<&str> = remote_dirs.iter().map(String::as_str).collect();
+    let known: HashSet<&str> = known_dirs
+        .iter()
+        .filter(|s| s.sync_zone == sync_zone && s.is_dir())
+        .map(|s| s.path.as_str())
+        .collect();
+
+    let is_under = |path: &str, dir: &str| {
+        path == dir || path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+    };
+    let moved: Vec<&str> = file_actions
+        .iter()
+        .filter_map(|r| match &r.action {
+            SyncAction::Move { from, .. } => Some([from.as_str(), r.path.as_str()]),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    // Paths the other side keeps content at, which a folder delete must not remove
+    let kept_remotely: Vec<&str> = file_actions
+        .iter()
+        .filter(|r| {
+            matches!(
+                r.action,
+                SyncAction::Download { .. } | SyncAction::Conflict { .. }
+            )
+        })
+        .map(|r| r.path.as_str())
+        .chain(remote.iter().copied().filter(|d| !known.contains(d)))
+        .collect();
+    let kept_locally: Vec<&str> = file_actions
+        .iter()
+        .filter(|r| {
+            matches!(
+                r.action,
+                SyncAction::Upload { .. } | SyncAction::Conflict { .. }
+            )
+        })
+        .map(|r| r.path.as_str())
+        .chain(local.iter().copied().filter(|d| !known.contains(d)))
+        .collect();
+
+    let mut all_paths: Vec<&str> = local.union(&remote).copied().collect();
+    all_paths.extend(known.iter().filter(|p| !local.contains(*p) && !remote.contains(*p)));
+    all_paths.sort();
+
+    let mut deleted_local: Vec<&str> = Vec::new();
+    let mut deleted_remote: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+
+    for path in all_paths {
+        if moved.iter().any(|m| is_under(path, m)) {
+            continue;
+        }
+        let local_path = format!("{}/{}", local_base, path);
+        let remote_path = format!("{}/{}", remote_base, path);
+
+        let action = match (local.contains(path), remote.contains(path), known.contains(path)) {
+            (true, false, false) => SyncAction::CreateRemoteDir { remote_path },
+            (false, true, false) => SyncAction::CreateLocalDir { local_path },
+            (true, false, true) => {
+                // Sorted order: an enclosing deleted folder is always seen first
+                if deleted_local.iter().any(|d| is_under(path, d))
+                    || kept_locally.iter().any(|p| is_under(p, path))
+                {
+                    continue;
+                }
+                deleted_local.push(path);
+                SyncAction::DeleteLocalDir { local_path }
+            }
+            (false, true, true) => {
+                if deleted_remote.iter().any(|d| is_under(path, d))
+                    || kept_remotely.iter().any(|p| is_under(p, path))
+                {
+                    continue;
+                }
+                deleted_remote.push(path);
+                SyncAction::DeleteRemoteDir { remote_path }
+            }
+            _ => continue,
+        };
+        results.push(DiffResult {
+            path: path.to_string(),
+            action,
+        });
+    }
     results
 }
 
+/// Paths on each side left out of the diff (files above the size limit, dotfiles).
+/// They still sit in their folders, so a folder moved whole takes them along.
+#[derive(Debug, Default)]
+pub struct ExcludedPaths<'a> {
+    pub local: Vec<&'a str>,
+    pub remote: Vec<&'a str>,
+}
+
+/// A detected move: a single file, or a directory covering several files.
+struct DetectedMove {
+    from: String,
+    to: String,
+    side: MoveSide,
+    /// Relative (from, to) paths of the files covered by this move.
+    files: Vec<(String, String)>,
+}
+
+/// Pair deletes with new files of identical content and collapse them into moves.
+///
+/// Local moves pair a `DeleteRemote` (file gone locally, unchanged on the server)
+/// with a never-synced local file whose hash equals the old `last_synced_hash`.
+/// Remote moves pair a `DeleteLocal` with a never-synced remote file carrying the
+/// old etag and size. Only unambiguous pairs are used — when several candidates
+/// share a key (e.g. empty files) they stay plain deletes and transfers.
+fn detect_moves(
+    results: &[DiffResult],
+    local_map: &HashMap<&str, &LocalFileInfo>,
+    remote_map: &HashMap<&str, &RemoteFileInfo>,
+    known_map: &HashMap<&str, &FileState>,
+    excluded: &ExcludedPaths,
+) -> Vec<DetectedMove> {
+    let mut local_gone: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut local_new: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut remote_gone: HashMap<(&str, i64), Vec<&str>> = HashMap::new();
+    let mut remote_new: HashMap<(&str, i64), Vec<&str>> = HashMap::new();
+
+    for r in results {
+        let path = r.path.as_str();
+        let known = known_map.get(path).copied();
+        match (&r.action, known) {
+            (SyncAction::DeleteRemote { .. }, Some(k)) => {
+                if let Some(hash) = k.last_synced_hash.as_deref() {
+                    local_gone.entry(hash).or_default().push(path);
+                }
+            }
+            (SyncAction::Upload { .. }, None) => {
+                if let Some(local) = local_map.get(path) {
+                    local_new.entry(local.hash.as_str()).or_default().push(path);
+                }
+            }
+            (SyncAction::DeleteLocal { .. }, Some(k)) => {
+                if let (Some(etag), Some(size)) = (k.last_synced_etag.as_deref(), k.remote_size) {
+                    remote_gone.entry((etag, size)).or_default().push(path);
+                }
+            }
+            (SyncAction::Download { .. }, None) => {
+                if let Some(remote) = remote_map.get(path) {
+                    if let (Some(etag), Some(size)) = (remote.etag.as_deref(), remote.size) {
+                        remote_new.entry((etag, size)).or_default().push(path);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut moves = Vec::new();
+    for (side, pairs) in [
+        (MoveSide::Remote, unique_pairs(&local_gone, &local_new)),
+        (MoveSide::Local, unique_pairs(&remote_gone, &remote_new)),
+    ] {
+        // The side the move happened on — where nothing may be left under the old folder
+        let (origin, target, target_excluded): (Vec<&str>, Vec<&str>, &[&str]) = match side {
+            MoveSide::Remote => (
+                local_map.keys().copied().collect(),
+                remote_map.keys().copied().collect(),
+                &excluded.remote,
+            ),
+            MoveSide::Local => (
+                remote_map.keys().copied().collect(),
+                local_map.keys().copied().collect(),
+                &excluded.local,
+            ),
+        };
+        moves.extend(collapse_directory_moves(
+            side,
+            pairs,
+            &origin,
+            &target,
+            target_excluded,
+        ));
+    }
+    moves
+}
+
+/// Pair keys that identify exactly one disappeared and one new path.
+fn unique_pairs<K: std::hash::Hash + Eq>(
+    gone: &HashMap<K, Vec<&str>>,
+    new: &HashMap<K, Vec<&str>>,
+) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = gone
+        .iter()
+        .filter_map(|(key, from)| match (from.as_slice(), new.get(key)?.as_slice()) {
+            ([from], [to]) => Some((from.to_string(), to.to_string())),
+            _ => None,
+        })
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// Merge file moves that together are a directory rename into one move of the
+/// directory. A folder qualifies when nothing is left under it on the side it was
+/// moved on, the other side holds exactly the moved files under it and nothing
+/// left out of the diff (moving the folder would take that along), each landed at
+/// the same relative place under the new folder, and the new folder does not exist
+/// yet on the other side. The outermost qualifying folder wins.
+fn collapse_directory_moves(
+    side: MoveSide,
+    pairs: Vec<(String, String)>,
+    origin: &[&str],
+    target: &[&str],
+    target_excluded: &[&str],
+) -> Vec<DetectedMove> {
+    let mut moves = Vec::new();
+    let mut done: HashSet<String> = HashSet::new();
+
+    for (from, to) in &pairs {
+        if done.contains(from) {
+            continue;
+        }
+
+        let collapsed = directory_candidates(from, to).into_iter().find_map(|(from_dir, to_dir)| {
+            let covered: Vec<(String, String)> = pairs
+                .iter()
+                .filter(|(f, t)| {
+                    f.strip_prefix(&from_dir)
+                        .zip(t.strip_prefix(&to_dir))
+                        .is_some_and(|(f_rest, t_rest)| f_rest.starts_with('/') && f_rest == t_rest)
+                })
+                .cloned()
+                .collect();
+            let mut moved_from: Vec<&str> = covered.iter().map(|(f, _)| f.as_str()).collect();
+            moved_from.sort();
+
+            let qualifies = paths_under(origin, &from_dir).is_empty()
+                && paths_under(target, &from_dir) == moved_from
+                && paths_under(target_excluded, &from_dir).is_empty()
+                && paths_under(target, &to_dir).is_empty()
+                && !target.contains(&to_dir.as_str());
+            qualifies.then_some((from_dir, to_dir, covered))
+        });
+
+        match collapsed {
+            Some((from_dir, to_dir, covered)) => {
+                done.extend(covered.iter().map(|(f, _)| f.clone()));
+                moves.push(DetectedMove {
+                    from: from_dir,
+                    to: to_dir,
+                    side,
+                    files: covered,
+                });
+            }
+            None => {
+                done.insert(from.clone());
+                moves.push(DetectedMove {
+                    from: from.clone(),
+                    to: to.clone(),
+                    side,
+                    files: vec![(from.clone(), to.clone())],
+                });
+            }
+        }
+    }
+    moves
+}
+
+/// Paths strictly inside `dir`, sorted.
+fn paths_under<'a>(paths: &[&'a str], dir: &str) -> Vec<&'a str> {
+    let prefix = format!("{}/", dir);
+    let mut inside: Vec<&str> = paths
+        .iter()
+        .copied()
+        .filter(|p| p.starts_with(&prefix))
+        .collect();
+    inside.sort();
+    inside
+}
+
+/// Folder pairs a file move could be part of, outermost first:
+/// `old/a/x.txt` → `new/a/x.txt` gives (`old`, `new`) and (`old/a`, `new/a`).
+fn directory_candidates(from: &str, to: &str) -> Vec<(String, String)> {
+    let from_parts: Vec<&str> = from.split('/').collect();
+    let to_parts: Vec<&str> = to.split('/').collect();
+
+    // Length of the shared trailing components (the part kept by the rename)
+    let shared = from_parts
+        .iter()
+        .rev()
+        .zip(to_parts.iter().rev())
+        .take_while(|(f, t)| f == t)
+        .count();
+
+    let mut candidates = Vec::new();
+    for keep in (1..=shared).rev() {
+        let from_dir = from_parts[..from_parts.len() - keep].join("/");
+        let to_dir = to_parts[..to_parts.len() - keep].join("/");
+        if from_dir.is_empty() || to_dir.is_empty() {
+            continue;
+        }
+        candidates.push((from_dir, to_dir));
+    }
+    candidates
+}
+
 /// Determine the sync action for a single file based on 3-way comparison.
 fn diff_file(
     local: Option<&LocalFileInfo>,
@@ -147,6 +568,28 @@ fn diff_file(
     let local_path = format!("{}/{}", local_base, path);
     let remote_path = format!("{}/{}", remote_base, path);
 
+    // An unresolved conflict stays a conflict until it is resolved — its row holds
+    // the scan snapshot, so the change checks below would report it as unchanged.
+    if known.is_some_and(|k| k.sync_status == "conflict") {
+        let conflict_type = match (local, remote) {
+            (Some(_), Some(_)) => Some(ConflictType::BothModified),
+            (None, Some(_)) => Some(ConflictType::DeletedLocallyModifiedRemotely),
+            (Some(_), None) => Some(ConflictType::DeletedRemotelyModifiedLocally),
+            (None, None) => None,
+        };
+        if let Some(conflict_type) = conflict_type {
+            return SyncAction::Conflict {
+                local_path,
+                remote_path,
+                conflict_type,
+            };
+        }
+    }
+
+    // A file that never synced (skipped as too large, or failed) has a row but no
+    // baseline to compare with; a missing side was never there, not deleted
+    let known = known.filter(|k| k.last_synced_at.is_some());
+
     match (local, remote, known) {
         // New local file, not on remote, never synced → Upload
         (Some(_), None, None) => SyncAction::Upload {
@@ -292,6 +735,7 @@ mod tests {
             etag: Some(etag.to_string()),
             mtime: Some(1000),
             size: Some(size),
+            checksum: None,
         }
     }
 
@@ -299,9 +743,12 @@ mod tests {
         FileState {
             path: path.to_string(),
             sync_zone: "personal".to_string(),
+            kind: "file".to_string(),
             local_hash: Some(hash.to_string()),
             local_mtime: Some(1000),
             local_size: Some(size),
+            local_inode: None,
+            local_ctime: None,
             local_exists: true,
             remote_etag: Some(etag.to_string()),
             remote_mtime: Some(1000),
@@ -314,16 +761,84 @@ mod tests {
             last_synced_at: Some("2026-01-01T00:00:00Z".to_string()),
             error_message: None,
             retry_count: 0,
+            next_retry_at: None,
         }
     }
 
+    /// Diff with nothing left out, under the /local and /remote bases.
+    fn diff(
+        local: &[LocalFileInfo],
+        remote: &[RemoteFileInfo],
+        known: &[FileState],
+        zone: &str,
+    ) -> Vec<DiffResult> {
+        let excluded = ExcludedPaths::default();
+        compute_diff(local, remote, known, &excluded, zone, "/local", "/remote")
+    }
+
     #[test]
     fn new_local_file_uploads() {
         let local = vec![make_local("doc.txt", "abc", 100)];
         let remote: Vec<RemoteFileInfo> = vec![];
         let known: Vec<FileState> = vec![];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
+        assert_eq!(result.len(), 1);
+        assert!(matches!(result[0].action, SyncAction::Upload { .. }));
+    }
+
+    fn make_unsynced(
+        path: &str,
+        status: &str,
+        local_size: Option<i64>,
+        remote_size: Option<i64>,
+    ) -> FileState {
+        FileState {
+            local_hash: None,
+            local_mtime: None,
+            local_size,
+            local_exists: local_size.is_some(),
+            remote_etag: None,
+            remote_mtime: None,
+            remote_size,
+            remote_exists: remote_size.is_some(),
+            sync_status: status.to_string(),
+            last_synced_hash: None,
+            last_synced_mtime: None,
+            last_synced_etag: None,
+            last_synced_at: None,
+            ..make_known(path, "", "", 0)
+        }
+    }
+
+    #[test]
+    fn skipped_local_file_uploads_once_allowed() {
+        // Skipped as too large before it ever synced, then the limit was raised
+        let local = vec![make_local("big.iso", "abc", 100)];
+        let known = vec![make_unsynced("big.iso", "skipped", Some(100), None)];
+        let result = diff(&local, &[], &known, "personal");
+        assert_eq!(result.len(), 1);
+        assert!(matches!(result[0].action, SyncAction::Upload { .. }));
+    }
+
+    #[test]
+    fn skipped_remote_file_downloads_once_allowed() {
+        // Same for a shared file that only exists on the server
+        let remote = vec![make_remote("big.iso", "e1", 100)];
+        let known = vec![FileState {
+            sync_zone: "shared".to_string(),
+            ..make_unsynced("big.iso", "skipped", None, Some(100))
+        }];
+        let result = diff(&[], &remote, &known, "shared");
         assert_eq!(result.len(), 1);
+        assert!(matches!(result[0].action, SyncAction::Download { .. }));
+    }
+
+    #[test]
+    fn failed_new_file_is_not_deleted() {
+        // Upload of a new file failed; its error row has no baseline either
+        let local = vec![make_local("doc.txt", "abc", 100)];
+        let known = vec![make_unsynced("doc.txt", "error", None, None)];
+        let result = diff(&local, &[], &known, "personal");
         assert!(matches!(result[0].action, SyncAction::Upload { .. }));
     }
 
@@ -332,7 +847,7 @@ mod tests {
         let local: Vec<LocalFileInfo> = vec![];
         let remote = vec![make_remote("doc.txt", "etag1", 100)];
         let known: Vec<FileState> = vec![];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(result[0].action, SyncAction::Download { .. }));
     }
@@ -342,7 +857,7 @@ mod tests {
         let local = vec![make_local("doc.txt", "abc", 100)];
         let remote = vec![make_remote("doc.txt", "etag1", 100)];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert!(result.is_empty()); // Skip filtered out
     }
 
@@ -351,7 +866,7 @@ mod tests {
         let local = vec![make_local("doc.txt", "new_hash", 200)];
         let remote = vec![make_remote("doc.txt", "etag1", 100)];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(result[0].action, SyncAction::Upload { .. }));
     }
@@ -361,7 +876,7 @@ mod tests {
         let local = vec![make_local("doc.txt", "abc", 100)];
         let remote = vec![make_remote("doc.txt", "new_etag", 200)];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(result[0].action, SyncAction::Download { .. }));
     }
@@ -371,7 +886,7 @@ mod tests {
         let local = vec![make_local("doc.txt", "new_hash", 200)];
         let remote = vec![make_remote("doc.txt", "new_etag", 300)];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(
             result[0].action,
@@ -387,7 +902,7 @@ mod tests {
         let local = vec![make_local("doc.txt", "abc", 100)];
         let remote: Vec<RemoteFileInfo> = vec![];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(result[0].action, SyncAction::DeleteLocal { .. }));
     }
@@ -397,17 +912,298 @@ mod tests {
         let local: Vec<LocalFileInfo> = vec![];
         let remote = vec![make_remote("doc.txt", "etag1", 100)];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
         assert_eq!(result.len(), 1);
         assert!(matches!(result[0].action, SyncAction::DeleteRemote { .. }));
     }
 
+    #[test]
+    fn unresolved_conflict_stays_conflict() {
+        let local = vec![make_local("doc.txt", "abc", 100)];
+        let remote = vec![make_remote("doc.txt", "etag1", 100)];
+        let mut state = make_known("doc.txt", "abc", "etag1", 100);
+        state.sync_status = "conflict".to_string();
+        let result = diff(&local, &remote, &[state], "personal");
+        assert_eq!(result.len(), 1);
+        assert!(matches!(
+            result[0].action,
+            SyncAction::Conflict {
+                conflict_type: ConflictType::BothModified,
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn both_deleted_skips() {
         let local: Vec<LocalFileInfo> = vec![];
         let remote: Vec<RemoteFileInfo> = vec![];
         let known = vec![make_known("doc.txt", "abc", "etag1", 100)];
-        let result = compute_diff(&local, &remote, &known, "personal", "/local", "/remote");
+        let result = diff(&local, &remote, &known, "personal");
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn local_rename_moves_remote() {
+        let local = vec![make_local("new.txt", "abc", 100)];
+        let remote = vec![make_remote("old.txt", "etag1", 100)];
+        let known = vec![make_known("old.txt", "abc", "etag1", 100)];
+        let result = diff(&local, &remote, &known, "personal");
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "new.txt");
+        match &result[0].action {
+            SyncAction::Move {
+                from,
+                source,
+                destination,
+                side,
+            } => {
+                assert_eq!(from, "old.txt");
+                assert_eq!(source, "/remote/old.txt");
+                assert_eq!(destination, "/remote/new.txt");
+                assert_eq!(*side, MoveSide::Remote);
+            }
+            other => panic!("expected Move, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn remote_rename_moves_local() {
+        let local = vec![make_local("old.txt", "abc", 100)];
+        let remote = vec![make_remote("new.txt", "etag1", 100)];
+        let known = vec![make_known("old.txt", "abc", "etag1", 100)];
+        let result = diff(&local, &remote, &known, "personal");
+        assert_eq!(result.len(), 1);
+        match &result[0].action {
+            SyncAction::Move {
+                from,
+                destination,
+                side,
+                ..
+            } => {
+                assert_eq!(from, "old.txt");
+                assert_eq!(destination, "/local/new.txt");
+                assert_eq!(*side, MoveSide::Local);
+            }
+            other => panic!("expected Move, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn directory_rename_collapses_into_one_move() {
+        let local = vec![
+            make_local("new/a.txt", "h1", 10),
+            make_local("new/sub/b.txt", "h2", 20),
+        ];
+        let remote = vec![
+            make_remote("old/a.txt", "e1", 10),
+            make_remote("old/sub/b.txt", "e2", 20),
+        ];
+        let known = vec![
+            make_known("old/a.txt", "h1", "e1", 10),
+            make_known("old/sub/b.txt", "h2", "e2", 20),
+        ];
+        let result = diff(&local, &remote, &known, "personal");
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "new");
+        assert!(matches!(
+            &result[0].action,
+            SyncAction::Move { from, side: MoveSide::Remote, .. } if from == "old"
+        ));
+    }
+
+    #[test]
+    fn directory_holding_excluded_files_moves_files_one_by_one() {
+        // The server folder also holds a dotfile and a file above the size limit,
+        // which a MOVE of the whole folder would take along
+        let local = vec![
+            make_local("new/a.txt", "h1", 10),
+            make_local("new/b.txt", "h2", 20),
+        ];
+        let remote = vec![
+            make_remote("old/a.txt", "e1", 10),
+            make_remote("old/b.txt", "e2", 20),
+        ];
+        let known = vec![
+            make_known("old/a.txt", "h1", "e1", 10),
+            make_known("old/b.txt", "h2", "e2", 20),
+        ];
+        for left_out in ["old/.notes", "old/huge.iso"] {
+            let excluded = ExcludedPaths {
+                local: vec![],
+                remote: vec![left_out],
+            };
+            let result = compute_diff(
+                &local, &remote, &known, &excluded, "personal", "/local", "/remote",
+            );
+            let moved: Vec<&str> = result
+                .iter()
+                .filter_map(|r| match &r.action {
+                    SyncAction::Move { from, .. } => Some(from.as_str()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(moved, vec!["old/a.txt", "old/b.txt"]);
+        }
+
+        // Left out locally only: the server folder holds just the moved files
+        let excluded = ExcludedPaths {
+            local: vec!["old/.notes"],
+            remote: vec![],
+        };
+        let result = compute_diff(
+            &local, &remote, &known, &excluded, "personal", "/local", "/remote",
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "new");
+    }
+
+    #[test]
+    fn partly_moved_directory_moves_files_one_by_one() {
+        // old/b.txt was edited after the rename, so the folder cannot move as a whole
+        let local = vec![
+            make_local("new/a.txt", "h1", 10),
+            make_local("new/b.txt", "edited", 25),
+        ];
+        let remote = vec![
+            make_remote("old/a.txt", "e1", 10),
+            make_remote("old/b.txt", "e2", 20),
+        ];
+        let known = vec![
+            make_known("old/a.txt", "h1", "e1", 10),
+            make_known("old/b.txt", "h2", "e2", 20),
+        ];
+        let result = diff(&local, &remote, &known, "personal");
+        let actions: Vec<(&str, &str)> = result
+            .iter()
+            .map(|r| {
+                let kind = match r.action {
+                    SyncAction::Move { .. } => "move",
+                    SyncAction::Upload { .. } => "upload",
+                    SyncAction::DeleteRemote { .. } => "delete_remote",
+                    _ => "other",
+                };
+                (r.path.as_str(), kind)
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("new/a.txt", "move"),
+                ("new/b.txt", "upload"),
+                ("old/b.txt", "delete_remote"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ambiguous_content_is_not_a_move() {
+        let local = vec![make_local("c.txt", "same", 0), make_local("d.txt", "same", 0)];
+        let remote = vec![
+            make_remote("a.txt", "e1", 0),
+            make_remote("b.txt", "e2", 0),
+        ];
+        let known = vec![
+            make_known("a.txt", "same", "e1", 0),
+            make_known("b.txt", "same", "e2", 0),
+        ];
+        let result = diff(&local, &remote, &known, "personal");
+        assert_eq!(result.len(), 4);
+        assert!(!result
+            .iter()
+            .any(|r| matches!(r.action, SyncAction::Move { .. })));
+    }
+
+    fn make_known_dir(path: &str) -> FileState {
+        FileState {
+            kind: "dir".to_string(),
+            local_hash: None,
+            last_synced_hash: None,
+            last_synced_etag: None,
+            ..make_known(path, "", "", 0)
+        }
+    }
+
+    fn dirs(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn new_empty_dirs_are_created_on_the_other_side() {
+        let result = compute_dir_diff(
+            &dirs(&["mine"]),
+            &dirs(&["theirs"]),
+            &[],
+            &[],
+            "personal",
+            "/local",
+            "/remote",
+        );
+        assert_eq!(result.len(), 2);
+        assert!(matches!(
+            &result[0].action,
+            SyncAction::CreateRemoteDir { remote_path } if remote_path == "/remote/mine"
+        ));
+        assert!(matches!(
+            &result[1].action,
+            SyncAction::CreateLocalDir { local_path } if local_path == "/local/theirs"
+        ));
+    }
+
+    #[test]
+    fn deleted_dir_tree_is_removed_once_from_the_top() {
+        let known = vec![make_known_dir("a"), make_known_dir("a/b")];
+        let result = compute_dir_diff(
+            &[],
+            &dirs(&["a", "a/b"]),
+            &known,
+            &[],
+            "personal",
+            "/local",
+            "/remote",
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "a");
+        assert!(matches!(result[0].action, SyncAction::DeleteRemoteDir { .. }));
+    }
+
+    #[test]
+    fn dir_with_new_remote_content_is_not_deleted() {
+        let known = vec![make_known_dir("a")];
+        let file_actions = diff(&[], &[make_remote("a/new.txt", "e1", 10)], &[], "personal");
+        let result = compute_dir_diff(
+            &[],
+            &dirs(&["a"]),
+            &known,
+            &file_actions,
+            "personal",
+            "/local",
+            "/remote",
+        );
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn dirs_inside_a_move_are_left_to_the_move() {
+        let file_actions = vec![DiffResult {
+            path: "new".to_string(),
+            action: SyncAction::Move {
+                from: "old".to_string(),
+                source: "/remote/old".to_string(),
+                destination: "/remote/new".to_string(),
+                side: MoveSide::Remote,
+            },
+        }];
+        let known = vec![make_known_dir("old"), make_known_dir("old/sub")];
+        let result = compute_dir_diff(
+            &dirs(&["new", "new/sub"]),
+            &dirs(&["old", "old/sub"]),
+            &known,
+            &file_actions,
+            "personal",
+            "/local",
+            "/remote",
+        );
         assert!(result.is_empty());
     }
 }
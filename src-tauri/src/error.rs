@@ -10,6 +10,9 @@ pub enum AppError {
     Network(String),
     Io(String),
     Internal(String),
+    Cancelled(String),
+    /// A conditional write was refused (HTTP 412): the remote changed meanwhile.
+    PreconditionFailed(String),
 }
 
 impl AppError {
@@ -36,6 +39,22 @@ impl AppError {
     pub fn internal(message: impl Into<String>) -> Self {
         Self::Internal(message.into())
     }
+
+    pub fn cancelled() -> Self {
+        Self::Cancelled("Synchronizacja anulowana".to_string())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled(_))
+    }
+
+    pub fn precondition_failed(message: impl Into<String>) -> Self {
+        Self::PreconditionFailed(message.into())
+    }
+
+    pub fn is_precondition_failed(&self) -> bool {
+        matches!(self, Self::PreconditionFailed(_))
+    }
 }
 
 impl fmt::Display for AppError {
@@ -46,7 +65,9 @@ impl fmt::Display for AppError {
             | Self::Sync(message)
             | Self::Network(message)
             | Self::Io(message)
-            | Self::Internal(message) => f.write_str(message),
+            | Self::Internal(message)
+            | Self::Cancelled(message)
+            | Self::PreconditionFailed(message) => f.write_str(message),
         }
     }
 }
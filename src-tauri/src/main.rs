@@ -2,16 +2,20 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod auth;
+mod bandwidth;
 mod config;
 mod db;
 mod diff;
 mod error;
+mod multistatus;
 mod sync;
+#[cfg(test)]
+mod test_server;
 mod transfer;
 mod trash;
 mod watcher;
 
-use config::{ActivityEntry, AppConfig, SyncStatus};
+use config::{ActivityEntry, AppConfig, EffectiveBandwidth, SyncStatus};
 use error::{AppError, AppResult};
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
@@ -150,6 +154,17 @@ fn validate_config(config: &AppConfig) -> AppResult<()> {
         return Err(AppError::config("Ścieżki synchronizacji muszą być różne"));
     }
 
+    for rule in &config.bandwidth_schedule {
+        if rule.days.is_empty() || rule.days.iter().any(|day| !(1..=7).contains(day)) {
+            return Err(AppError::config(
+                "Każde okno harmonogramu przepustowości musi mieć wybrane dni tygodnia",
+            ));
+        }
+        if rule.start_minute >= 24 * 60 || rule.end_minute > 24 * 60 {
+            return Err(AppError::config("Nieprawidłowe godziny w harmonogramie przepustowości"));
+        }
+    }
+
     Ok(())
 }
 
@@ -161,11 +176,14 @@ fn path_is_within(path: &Path, allowed_roots: &[PathBuf]) -> bool {
     allowed_roots.iter().any(|root| path.starts_with(root))
 }
 
-fn record_sync_attempt(state: &AppState, source: &str) {
+fn record_sync_attempt(state: &AppState, source: &str, scope: &sync::SyncScope) {
     let now = Instant::now();
     let mut scheduler = state.scheduler();
     scheduler.startup_sync_done = true;
-    scheduler.last_sync_attempt = Some(now);
+    // A targeted run does not look at remote changes, so the interval sync stays due
+    if matches!(scope, sync::SyncScope::Full) {
+        scheduler.last_sync_attempt = Some(now);
+    }
     if source == "watcher" {
         scheduler.last_watch_sync_attempt = Some(now);
     }
@@ -216,6 +234,7 @@ async fn run_sync_once(
     app: &tauri::AppHandle,
     state: &AppState,
     source: &str,
+    scope: &sync::SyncScope,
 ) -> AppResult<()> {
     let cfg = state.config().clone();
     log::info!(
@@ -234,23 +253,28 @@ async fn run_sync_once(
         AppError::auth("Brak tokenu logowania")
     })?;
 
-    record_sync_attempt(state, source);
-    state
+    record_sync_attempt(state, source, scope);
+    if !state.sync_engine.is_paused() {
+        refresh_tray(app, &SyncStatus::Syncing);
+    }
+    let result = state
         .sync_engine
-        .sync_all(app, &cfg, &token.token, source)
-        .await
+        .sync_all(app, &cfg, &token.token, source, scope)
+        .await;
+    refresh_tray(app, &state.sync_engine.get_status());
+    result
 }
 
 /// Sync scheduler loop — reacts instantly to file changes (Synology Drive-style)
 /// instead of polling on a fixed interval.
 ///
 /// Uses `tokio::select!` to await whichever fires first:
-///   - watcher channel (file changed locally → sync immediately after debounce)
+///   - watcher channel (file changed locally → sync those files after debounce)
 ///   - interval timer (periodic full sync as safety net)
 ///   - startup trigger (first sync after login)
 async fn run_scheduler_loop(app: tauri::AppHandle) {
     // Take the watcher receiver so we can await on it directly.
-    let mut watcher_rx: Option<tokio::sync::mpsc::Receiver<()>> = {
+    let mut watcher_rx: Option<watcher::WatchReceiver> = {
         let state = app.state::<AppState>();
         let mut watcher = state.watcher();
         watcher.take_receiver()
@@ -262,16 +286,27 @@ async fn run_scheduler_loop(app: tauri::AppHandle) {
 
     let mut interval = tokio::time::interval(Duration::from_secs(5));
 
+    // Local changes not synced yet (kept while a watcher sync would come too soon)
+    let mut pending = watcher::PendingChanges::default();
+
+    // Inside a "pause sync" window of the bandwidth schedule
+    let mut schedule_paused = false;
+
     loop {
         // Wait for either: interval tick, file change, or both.
         let source = if let Some(rx) = &mut watcher_rx {
             tokio::select! {
                 _ = interval.tick() => "interval_or_startup",
-                _ = rx.recv() => {
+                event = rx.recv() => {
+                    if let Some(event) = event {
+                        pending.add(event);
+                    }
                     // Debounce: wait a bit for more changes to settle,
-                    // draining any events that arrive during the window.
+                    // collecting any events that arrive during the window.
                     tokio::time::sleep(WATCHER_DEBOUNCE).await;
-                    while rx.try_recv().is_ok() {}
+                    while let Some(event) = rx.try_recv() {
+                        pending.add(event);
+                    }
                     "watcher"
                 }
             }
@@ -286,18 +321,33 @@ async fn run_scheduler_loop(app: tauri::AppHandle) {
             continue;
         }
 
+        // The limits follow the schedule on their own (run_bandwidth_schedule_loop)
+        let paused = cfg.effective_bandwidth().paused;
+        if paused != schedule_paused {
+            schedule_paused = paused;
+            log::info!(
+                "Bandwidth schedule: automatic sync {}",
+                if paused { "paused" } else { "resumed" }
+            );
+        }
+        // Due syncs and watched changes wait for the end of the window
+        if schedule_paused {
+            continue;
+        }
+
         // Determine what kind of sync to run.
         let now = Instant::now();
+        // Changes made while paused are kept for after resume
+        let watch_too_soon = state.sync_engine.is_paused() || {
+            let scheduler = state.scheduler();
+            scheduler
+                .last_watch_sync_attempt
+                .map(|last| now.duration_since(last) < Duration::from_secs(3))
+                .unwrap_or(false)
+        };
         let final_source = if source == "watcher" {
-            // Watcher-triggered: skip if too soon after last watcher sync
-            let too_soon = {
-                let scheduler = state.scheduler();
-                scheduler
-                    .last_watch_sync_attempt
-                    .map(|last| now.duration_since(last) < Duration::from_secs(3))
-                    .unwrap_or(false)
-            };
-            if too_soon {
+            // Watcher-triggered: wait if too soon after last watcher sync
+            if watch_too_soon || pending.is_empty() {
                 continue;
             }
             "watcher"
@@ -319,12 +369,23 @@ async fn run_scheduler_loop(app: tauri::AppHandle) {
                 "startup"
             } else if should_interval {
                 "interval"
+            } else if !pending.is_empty() && !watch_too_soon {
+                // Changes held back earlier
+                "watcher"
             } else {
                 continue;
             }
         };
 
-        let result = run_sync_once(&app, &state, final_source).await;
+        // A full run covers every pending change; a watcher run only those
+        let changes = std::mem::take(&mut pending);
+        let scope = if final_source == "watcher" {
+            changes.scope(&cfg.personal_sync_path, &cfg.shared_sync_path)
+        } else {
+            sync::SyncScope::Full
+        };
+
+        let result = run_sync_once(&app, &state, final_source, &scope).await;
 
         if final_source == "startup" {
             let mut scheduler = state.scheduler();
@@ -333,10 +394,26 @@ async fn run_scheduler_loop(app: tauri::AppHandle) {
 
         if let Err(err) = result {
             log::warn!("Background sync failed: {}", err);
+            // Not synced yet — try again with the next run
+            pending.merge(changes);
         }
     }
 }
 
+/// Keep the bandwidth limits in step with the schedule, apart from the scheduler
+/// loop, which waits for a whole sync run: a window that starts or ends mid-run
+/// applies to its transfers right away.
+async fn run_bandwidth_schedule_loop(app: tauri::AppHandle) {
+    const SCHEDULE_CHECK: Duration = Duration::from_secs(10);
+
+    let engine = app.state::<AppState>().sync_engine.clone();
+    engine
+        .follow_bandwidth_schedule(SCHEDULE_CHECK, || {
+            app.state::<AppState>().config().effective_bandwidth()
+        })
+        .await;
+}
+
 // ==================== Tauri Commands ====================
 
 /// Login with login and password
@@ -482,22 +559,38 @@ fn update_config(
 ) -> Result<(), String> {
     validate_config(&config).map_err(|e| e.to_string())?;
     config::save_config(&app, &config).map_err(|e| e.to_string())?;
+    state.sync_engine.set_bandwidth_limits(&config);
     *state.config() = config;
     reset_scheduler_for_login_or_config(&state);
     configure_watcher_for_current_config(&state).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Get the bandwidth limits in force now, from the schedule or the defaults
+#[tauri::command]
+fn get_effective_bandwidth(state: State<'_, AppState>) -> EffectiveBandwidth {
+    state.config().effective_bandwidth()
+}
+
 /// Trigger manual sync
 #[tauri::command]
 async fn trigger_sync(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
-    run_sync_once(&app, &state, "manual")
+    run_sync_once(&app, &state, "manual", &sync::SyncScope::Full)
         .await
         .map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
+/// Sync now, rehashing every local file instead of trusting the stat cache
+#[tauri::command]
+async fn verify_sync(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    state.sync_engine.request_full_verify();
+    run_sync_once(&app, &state, "manual", &sync::SyncScope::Full)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Get recent activity log
 #[tauri::command]
 fn get_activity(state: State<'_, AppState>, limit: Option<usize>) -> Vec<ActivityEntry> {
@@ -511,12 +604,50 @@ fn get_sync_history(state: State<'_, AppState>, limit: Option<usize>) -> Result<
     db::list_sync_runs(&pool, limit.unwrap_or(50)).map_err(|e| e.to_string())
 }
 
+/// Resolve a sync conflict with the strategy chosen in the conflict dialog
+#[tauri::command]
+async fn resolve_conflict(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    zone: String,
+    path: String,
+    resolution: sync::ConflictResolution,
+) -> Result<(), String> {
+    let cfg = state.config().clone();
+    if !cfg.is_configured() {
+        return Err("Aplikacja nie jest skonfigurowana".to_string());
+    }
+
+    let token = auth::get_token(&cfg.user_login)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Brak tokenu logowania".to_string())?;
+
+    let result = state
+        .sync_engine
+        .resolve_conflict(&cfg, &token.token, &zone, &path, resolution)
+        .await;
+    refresh_tray(&app, &state.sync_engine.get_status());
+    result.map_err(|e| e.to_string())
+}
+
+/// List unresolved conflicts persisted in the sync database
+#[tauri::command]
+fn list_conflicts(state: State<'_, AppState>) -> Result<Vec<db::ConflictEntry>, String> {
+    state.sync_engine.list_conflicts().map_err(|e| e.to_string())
+}
+
 /// Pause synchronization
 #[tauri::command]
 fn pause_sync(state: State<'_, AppState>) {
     state.sync_engine.pause();
 }
 
+/// Cancel the sync run in progress
+#[tauri::command]
+fn cancel_sync(state: State<'_, AppState>) -> bool {
+    state.sync_engine.cancel()
+}
+
 /// Resume synchronization
 #[tauri::command]
 fn resume_sync(state: State<'_, AppState>) {
@@ -657,6 +788,16 @@ fn update_tray_icon(app: &AppHandle, status: &SyncStatus) {
     }
 }
 
+/// Update both the tray icon and the tray menu to reflect the given status
+fn refresh_tray(app: &AppHandle, status: &SyncStatus) {
+    update_tray_icon(app, status);
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        if let Ok(menu) = build_tray_menu(app, status) {
+            let _ = tray.set_menu(Some(menu));
+        }
+    }
+}
+
 fn show_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.show();
@@ -763,6 +904,13 @@ fn build_tray_menu(app: &AppHandle, status: &SyncStatus) -> tauri::Result<Menu<t
         !matches!(status, SyncStatus::NotConfigured | SyncStatus::Syncing),
         None::<&str>,
     )?;
+    let cancel_item = MenuItem::with_id(
+        app,
+        "cancel_sync",
+        "Przerwij synchronizację",
+        app.state::<AppState>().sync_engine.is_running(),
+        None::<&str>,
+    )?;
     let sep3 = PredefinedMenuItem::separator(app)?;
     let settings_item = MenuItem::with_id(app, "settings", "Ustawienia...", true, None::<&str>)?;
     let sep4 = PredefinedMenuItem::separator(app)?;
@@ -778,6 +926,7 @@ fn build_tray_menu(app: &AppHandle, status: &SyncStatus) -> tauri::Result<Menu<t
             &sep2,
             &sync_now,
             &pause_resume,
+            &cancel_item,
             &sep3,
             &settings_item,
             &sep4,
@@ -806,19 +955,13 @@ fn handle_tray_menu_event(app: &AppHandle, event: MenuEvent) {
                 let login = cfg.user_login.clone();
                 if let Ok(Some(token)) = auth::get_token(&login) {
                     let engine = state.sync_engine.clone();
-                    // Update tray icon to syncing
-                    update_tray_icon(&app, &SyncStatus::Syncing);
-                    let result_status = match engine.sync_all(&app, &cfg, &token.token, "manual").await {
-                        Ok(()) => SyncStatus::Idle,
-                        Err(_) => engine.get_status(),
-                    };
-                    update_tray_icon(&app, &result_status);
-                    // Rebuild tray menu with new status
-                    if let Some(tray) = app.tray_by_id("main-tray") {
-                        if let Ok(menu) = build_tray_menu(&app, &result_status) {
-                            let _ = tray.set_menu(Some(menu));
-                        }
-                    }
+                    // Update tray to syncing (enables "cancel")
+                    refresh_tray(&app, &SyncStatus::Syncing);
+                    let _ = engine
+                        .sync_all(&app, &cfg, &token.token, "manual", &sync::SyncScope::Full)
+                        .await;
+                    // Idle, Conflict or Error — whatever the run ended with
+                    refresh_tray(&app, &engine.get_status());
                 }
             });
         }
@@ -830,13 +973,10 @@ fn handle_tray_menu_event(app: &AppHandle, event: MenuEvent) {
             } else {
                 engine.pause();
             }
-            let new_status = engine.get_status();
-            update_tray_icon(app, &new_status);
-            if let Some(tray) = app.tray_by_id("main-tray") {
-                if let Ok(menu) = build_tray_menu(app, &new_status) {
-                    let _ = tray.set_menu(Some(menu));
-                }
-            }
+            refresh_tray(app, &engine.get_status());
+        }
+        "cancel_sync" => {
+            app.state::<AppState>().sync_engine.cancel();
         }
         "settings" => {
             show_window(app);
@@ -917,10 +1057,15 @@ fn main() {
             get_sync_status,
             get_config,
             update_config,
+            get_effective_bandwidth,
             trigger_sync,
+            verify_sync,
             get_activity,
             get_sync_history,
+            resolve_conflict,
+            list_conflicts,
             pause_sync,
+            cancel_sync,
             resume_sync,
             open_folder,
             pick_folder,
@@ -958,6 +1103,11 @@ fn main() {
                 tauri::async_runtime::spawn(run_scheduler_loop(handle));
             }
 
+            {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(run_bandwidth_schedule_loop(handle));
+            }
+
             // Background update check — once per day
             {
                 let handle = app.handle().clone();
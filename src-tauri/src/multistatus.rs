@@ -0,0 +1,377 @@
+//! Parsing of WebDAV XML responses: `multistatus` bodies of PROPFIND and
+//! sync-collection REPORT requests, and `error` bodies of failed requests.
+//!
+//! Elements are matched by namespace and local name, so any prefix a server
+//! picks (`D:`, `d:`, `lp1:`, `ns0:`, a default namespace…) works, and text is
+//! read with entities and CDATA decoded.
+
+use crate::error::{AppError, AppResult};
+use roxmltree::{Document, Node};
+
+const DAV: &str = "DAV:";
+const OWNCLOUD: &str = "http://owncloud.org/ns";
+const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+/// A parsed `multistatus` body.
+#[derive(Debug, Clone, Default)]
+pub struct Multistatus {
+    pub responses: Vec<DavResponse>,
+    /// New sync-token of a sync-collection REPORT
+    pub sync_token: Option<String>,
+}
+
+/// One `response` element of a multistatus.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DavResponse {
+    /// Href as sent: entities decoded, still percent-encoded
+    pub href: String,
+    /// Status of the member itself, given instead of properties (e.g. 404 for a
+    /// member removed since the sync-token, 507 for a truncated result)
+    pub status: Option<u16>,
+    /// Properties from `propstat` blocks with a 2xx status; the ones the server
+    /// reports as missing (404) or forbidden are left out
+    pub props: DavProps,
+}
+
+impl DavResponse {
+    /// Whether the member itself is fine (no status, or a 2xx one).
+    pub fn is_success(&self) -> bool {
+        self.status.is_none_or(|status| (200..300).contains(&status))
+    }
+}
+
+/// Properties of a resource, as far as the server reported them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DavProps {
+    pub is_collection: bool,
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+    /// Without surrounding quotes
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub display_name: Option<String>,
+    /// `cs:getctag` of a collection, without surrounding quotes
+    pub ctag: Option<String>,
+    /// `oc:checksums` entries, e.g. "SHA1:… MD5:…"
+    pub checksums: Option<String>,
+    pub file_id: Option<String>,
+    pub permissions: Option<String>,
+}
+
+/// Parse a `multistatus` body. A `DAV:error` body or any other root element is
+/// an error.
+pub fn parse_multistatus(xml: &str) -> AppResult<Multistatus> {
+    let doc = parse_document(xml)?;
+    let root = doc.root_element();
+
+    if is_dav(root, "error") {
+        return Err(AppError::network(format!(
+            "WebDAV error: {}",
+            error_conditions(root).join(", ")
+        )));
+    }
+    if !is_dav(root, "multistatus") {
+        return Err(AppError::network(format!(
+            "Unexpected WebDAV response: <{}>",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut multistatus = Multistatus::default();
+    for child in root.children().filter(Node::is_element) {
+        if is_dav(child, "response") {
+            if let Some(response) = parse_response(child) {
+                multistatus.responses.push(response);
+            }
+        } else if is_dav(child, "sync-token") {
+            multistatus.sync_token = element_text(child);
+        }
+    }
+    Ok(multistatus)
+}
+
+/// Conditions named by a `DAV:error` body (e.g. "valid-sync-token"); empty when
+/// the body is not one.
+pub fn parse_error_conditions(xml: &str) -> Vec<String> {
+    match parse_document(xml) {
+        Ok(doc) if is_dav(doc.root_element(), "error") => error_conditions(doc.root_element()),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_document(xml: &str) -> AppResult<Document<'_>> {
+    Document::parse(xml.trim_start_matches('\u{feff}'))
+        .map_err(|e| AppError::network(format!("Invalid WebDAV XML: {}", e)))
+}
+
+/// Local names of the DAV: conditions inside an `error` element.
+fn error_conditions(error: Node) -> Vec<String> {
+    error
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().namespace() == Some(DAV))
+        .map(|n| n.tag_name().name().to_string())
+        .collect()
+}
+
+/// Parse one `response`; None without an href.
+fn parse_response(response: Node) -> Option<DavResponse> {
+    let mut parsed = DavResponse::default();
+    let mut href = None;
+
+    for child in response.children().filter(Node::is_element) {
+        if is_dav(child, "href") {
+            // Only the first href names the resource (a status response may list more)
+            if href.is_none() {
+                href = element_text(child);
+            }
+        } else if is_dav(child, "status") {
+            parsed.status = element_text(child).as_deref().and_then(parse_status);
+        } else if is_dav(child, "propstat") {
+            let status = child
+                .children()
+                .find(|n| is_dav(*n, "status"))
+                .and_then(element_text)
+                .as_deref()
+                .and_then(parse_status);
+            // A propstat without a status is taken as 200
+            if status.is_some_and(|s| !(200..300).contains(&s)) {
+                continue;
+            }
+            if let Some(prop) = child.children().find(|n| is_dav(*n, "prop")) {
+                read_props(prop, &mut parsed.props);
+            }
+        }
+    }
+
+    parsed.href = href?;
+    Some(parsed)
+}
+
+/// Collect the known properties of a `prop` element into `props`.
+fn read_props(prop: Node, props: &mut DavProps) {
+    for node in prop.children().filter(Node::is_element) {
+        let name = node.tag_name();
+        match (name.namespace(), name.name()) {
+            (Some(DAV), "resourcetype") => {
+                props.is_collection |= node.children().any(|n| is_dav(n, "collection"));
+            }
+            (Some(DAV), "getcontentlength") => {
+                props.content_length = element_text(node).and_then(|s| s.parse().ok());
+            }
+            (Some(DAV), "getcontenttype") => props.content_type = element_text(node),
+            (Some(DAV), "getetag") => props.etag = element_text(node).map(unquote),
+            (Some(DAV), "getlastmodified") => props.last_modified = element_text(node),
+            (Some(DAV), "displayname") => props.display_name = element_text(node),
+            (Some(CALENDARSERVER), "getctag") => props.ctag = element_text(node).map(unquote),
+            (Some(OWNCLOUD), "checksums") => {
+                let checksums: Vec<String> = node
+                    .children()
+                    .filter(|n| n.is_element() && n.tag_name().name() == "checksum")
+                    .filter_map(element_text)
+                    .collect();
+                props.checksums = Some(checksums.join(" ")).filter(|s| !s.is_empty());
+            }
+            (Some(OWNCLOUD), "fileid") => props.file_id = element_text(node),
+            (Some(OWNCLOUD), "permissions") => props.permissions = element_text(node),
+            _ => {}
+        }
+    }
+}
+
+fn is_dav(node: Node, local_name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(DAV)
+        && node.tag_name().name() == local_name
+}
+
+/// Trimmed text content of an element (text and CDATA), None when empty.
+fn element_text(node: Node) -> Option<String> {
+    let text: String = node
+        .children()
+        .filter(Node::is_text)
+        .filter_map(|n| n.text())
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Status code of an HTTP status line ("HTTP/1.1 404 Not Found").
+fn parse_status(line: &str) -> Option<u16> {
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn unquote(value: String) -> String {
+    value.trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEXTCLOUD_PROPFIND: &str =
+        include_str!("../tests/fixtures/webdav/nextcloud_propfind.xml");
+    const APACHE_PROPFIND: &str = include_str!("../tests/fixtures/webdav/apache_propfind.xml");
+    const NGINX_PROPFIND: &str = include_str!("../tests/fixtures/webdav/nginx_propfind.xml");
+    const SABRE_PROPFIND: &str = include_str!("../tests/fixtures/webdav/sabre_propfind.xml");
+    const SABRE_SYNC_COLLECTION: &str =
+        include_str!("../tests/fixtures/webdav/sabre_sync_collection.xml");
+    const SABRE_INVALID_TOKEN: &str =
+        include_str!("../tests/fixtures/webdav/sabre_invalid_sync_token.xml");
+    const PREFIXED_PROPFIND: &str = include_str!("../tests/fixtures/webdav/prefixed_propfind.xml");
+
+    fn find<'a>(multistatus: &'a Multistatus, href: &str) -> &'a DavResponse {
+        multistatus
+            .responses
+            .iter()
+            .find(|r| r.href == href)
+            .unwrap_or_else(|| panic!("no response for {}", href))
+    }
+
+    #[test]
+    fn nextcloud_folder_and_files() {
+        let ms = parse_multistatus(NEXTCLOUD_PROPFIND).unwrap();
+        assert_eq!(ms.responses.len(), 4);
+
+        let root = find(&ms, "/remote.php/dav/files/anna/Projects/");
+        assert!(root.props.is_collection);
+        assert_eq!(root.props.etag.as_deref(), Some("6650a5e1c0f3a"));
+        // Reported as 404 for folders: left out
+        assert_eq!(root.props.content_length, None);
+        assert_eq!(root.props.checksums, None);
+
+        let report = find(&ms, "/remote.php/dav/files/anna/Projects/Q1%20report.pdf");
+        assert!(!report.props.is_collection);
+        assert_eq!(report.props.content_length, Some(482133));
+        assert_eq!(report.props.etag.as_deref(), Some("a1f0c2e5b7d9e3f4a6b8c0d2e4f6a8b0"));
+        assert_eq!(
+            report.props.last_modified.as_deref(),
+            Some("Tue, 14 May 2024 09:12:44 GMT")
+        );
+        assert_eq!(report.props.content_type.as_deref(), Some("application/pdf"));
+        assert_eq!(
+            report.props.checksums.as_deref(),
+            Some("SHA1:5d41402abc4b2a76b9719d911017c592aaf4c3b2 MD5:5d41402abc4b2a76b9719d911017c592")
+        );
+        assert_eq!(report.props.file_id.as_deref(), Some("00001234oc8x1kq9zy5w"));
+        assert_eq!(report.props.permissions.as_deref(), Some("RGDNVW"));
+    }
+
+    #[test]
+    fn entities_in_hrefs_are_decoded() {
+        let ms = parse_multistatus(NEXTCLOUD_PROPFIND).unwrap();
+        let file = find(&ms, "/remote.php/dav/files/anna/Projects/R&D%20notes.txt");
+        assert_eq!(file.props.content_length, Some(12));
+    }
+
+    #[test]
+    fn file_named_collection_is_not_a_folder() {
+        let ms = parse_multistatus(NEXTCLOUD_PROPFIND).unwrap();
+        let file = find(&ms, "/remote.php/dav/files/anna/Projects/collection.txt");
+        assert!(!file.props.is_collection);
+        assert_eq!(file.props.content_length, Some(3));
+    }
+
+    #[test]
+    fn apache_mod_dav_live_property_prefixes() {
+        let ms = parse_multistatus(APACHE_PROPFIND).unwrap();
+        assert_eq!(ms.responses.len(), 3);
+
+        let dir = find(&ms, "/dav/personal/Docs/");
+        assert!(dir.props.is_collection);
+        assert_eq!(dir.props.etag, None);
+
+        let file = find(&ms, "/dav/personal/Docs/plan%20v2.odt");
+        assert!(!file.props.is_collection);
+        assert_eq!(file.props.content_length, Some(20480));
+        assert_eq!(file.props.etag.as_deref(), Some("5000-61a0c9e4b3f80"));
+        assert_eq!(file.props.checksums, None);
+
+        let sub = find(&ms, "/dav/personal/Docs/Archive/");
+        assert!(sub.props.is_collection);
+    }
+
+    #[test]
+    fn nginx_dav_ext_listing() {
+        let ms = parse_multistatus(NGINX_PROPFIND).unwrap();
+        assert_eq!(ms.responses.len(), 3);
+
+        let dir = find(&ms, "/dav/shared/");
+        assert!(dir.props.is_collection);
+
+        let file = find(&ms, "/dav/shared/budget.xlsx");
+        assert_eq!(file.props.content_length, Some(9876));
+        assert_eq!(file.props.display_name.as_deref(), Some("budget.xlsx"));
+        assert_eq!(file.props.last_modified.as_deref(), Some("Mon, 06 May 2024 07:30:00 GMT"));
+        assert_eq!(file.props.etag, None);
+
+        assert!(find(&ms, "/dav/shared/photos/").props.is_collection);
+    }
+
+    #[test]
+    fn sabre_collection_ctag() {
+        let ms = parse_multistatus(SABRE_PROPFIND).unwrap();
+        let root = find(&ms, "/dav/personal/");
+        assert!(root.props.is_collection);
+        assert_eq!(root.props.ctag.as_deref(), Some("http://sabre.io/ns/sync/42"));
+        assert_eq!(root.props.etag, None);
+
+        let file = find(&ms, "/dav/personal/notes.md");
+        assert_eq!(file.props.etag.as_deref(), Some("3f2a7c"));
+        assert_eq!(file.props.ctag, None);
+    }
+
+    #[test]
+    fn sabre_sync_collection_changes_and_removals() {
+        let ms = parse_multistatus(SABRE_SYNC_COLLECTION).unwrap();
+        assert_eq!(ms.sync_token.as_deref(), Some("http://sabre.io/ns/sync/57"));
+        assert_eq!(ms.responses.len(), 3);
+
+        let changed = find(&ms, "/dav/personal/notes.md");
+        assert!(changed.is_success());
+        assert_eq!(changed.props.etag.as_deref(), Some("9b1e44"));
+
+        let removed = find(&ms, "/dav/personal/old/draft.txt");
+        assert_eq!(removed.status, Some(404));
+        assert!(!removed.is_success());
+        assert_eq!(removed.props, DavProps::default());
+
+        let truncated = find(&ms, "/dav/personal/");
+        assert_eq!(truncated.status, Some(507));
+    }
+
+    #[test]
+    fn invalid_sync_token_error() {
+        assert_eq!(
+            parse_error_conditions(SABRE_INVALID_TOKEN),
+            vec!["valid-sync-token".to_string()]
+        );
+        assert!(parse_multistatus(SABRE_INVALID_TOKEN).is_err());
+        assert!(parse_error_conditions(SABRE_SYNC_COLLECTION).is_empty());
+    }
+
+    #[test]
+    fn arbitrary_prefixes_default_namespace_and_cdata() {
+        let ms = parse_multistatus(PREFIXED_PROPFIND).unwrap();
+        assert_eq!(ms.responses.len(), 3);
+
+        let dir = find(&ms, "/dav/personal/a%26b/");
+        assert!(dir.props.is_collection);
+        assert_eq!(dir.props.etag.as_deref(), Some("d1"));
+
+        // href in CDATA, default DAV: namespace on the response
+        let file = find(&ms, "/dav/personal/a%26b/<odd> name.txt");
+        assert_eq!(file.props.content_length, Some(7));
+        assert_eq!(file.props.etag.as_deref(), Some("f7"));
+
+        // A prop in a foreign namespace with a DAV-like name is ignored
+        let other = find(&ms, "/dav/personal/a%26b/plain.txt");
+        assert_eq!(other.props.etag.as_deref(), Some("p1"));
+        assert!(!other.props.is_collection);
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(parse_multistatus("<html><body>Bad gateway</body></html>").is_err());
+        assert!(parse_multistatus("not xml at all").is_err());
+    }
+}
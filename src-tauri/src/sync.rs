@@ -1,13 +1,38 @@
-use crate::config::{ActivityEntry, AppConfig, SyncStatus};
+use crate::bandwidth::BandwidthLimiter;
+use crate::config::{ActivityEntry, AppConfig, ConflictPolicy, EffectiveBandwidth, SyncStatus};
 use crate::db::{self, DbPool, FileState, SyncRunStats};
-use crate::diff::{compute_diff, LocalFileInfo, RemoteFileInfo, SyncAction};
+use crate::diff::{
+    compute_diff, compute_dir_diff, ConflictType, DiffResult, ExcludedPaths, LocalFileInfo,
+    MoveSide, RemoteFileInfo, SyncAction,
+};
 use crate::error::{AppError, AppResult};
-use crate::transfer::WebDavTransfer;
+use crate::transfer::{self, WebDavTransfer, WritePrecondition};
 use crate::trash::LocalTrashManager;
+use futures_util::stream::{self, StreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::{Mutex, MutexGuard};
-use tauri::{AppHandle, Emitter};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
 use tauri_plugin_notification::NotificationExt;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+/// Bytes read per step when hashing a file.
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+/// Reads at least this large are hashed on several threads (rayon).
+const HASH_RAYON_MIN_BYTES: usize = 128 * 1024;
+/// Files hashed at the same time; large ones also fan out over rayon.
+const MAX_CONCURRENT_HASHERS: usize = 4;
+static HASH_SLOTS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_HASHERS);
+/// Wait before a file whose sync failed is tried again, doubled for each further failure.
+const FILE_RETRY_BASE_SECS: u64 = 60;
+/// Largest never-synced file downloaded just to tell whether it matches the local
+/// copy, when the server has no checksum for it.
+const MAX_COMPARE_DOWNLOAD_BYTES: i64 = 16 * 1024 * 1024;
+/// Error for a run or transfer asked for during a pause window of the schedule.
+const SCHEDULE_PAUSED: &str = "Synchronizacja wstrzymana przez harmonogram przepustowości";
 
 /// Payload emitted to the frontend via the `sync-progress` event.
 #[derive(Clone, serde::Serialize)]
@@ -27,12 +52,135 @@ struct SyncFileProgressPayload {
     total: usize,
 }
 
+/// Scan results of one zone indexed by relative path.
+struct ScanLookups<'a> {
+    local: HashMap<&'a str, &'a LocalFileInfo>,
+    remote: HashMap<&'a str, &'a RemoteFileInfo>,
+    known: HashMap<&'a str, &'a FileState>,
+    /// Synced files whose server copy is unchanged, by content hash — server-side
+    /// copy sources for new local files with the same content.
+    copy_sources: HashMap<&'a str, &'a FileState>,
+}
+
+/// Concurrency limits and progress counter shared by the actions of one zone run.
+struct TransferSlots {
+    uploads: Semaphore,
+    downloads: Semaphore,
+    /// Actions started so far (the `current` of `sync-file-progress`)
+    started: AtomicUsize,
+    total: usize,
+    /// Once cancelled, actions still waiting for a slot are not started
+    cancel: CancellationToken,
+}
+
+/// Runs the actions the worker pool hands out: [`ZoneExecutor`] on the server and
+/// the local disk, a fake in tests.
+trait ActionExecutor {
+    /// Create the remote folders the actions of a phase put files or folders into.
+    async fn create_remote_parents(&self, actions: &[&DiffResult]);
+    /// An action got its slot and starts, as the `current` of `total` actions.
+    fn started(&self, diff_result: &DiffResult, current: usize, total: usize);
+    /// Run the action. Returns None when nothing was counted as done.
+    async fn execute(&self, diff_result: &DiffResult) -> AppResult<Option<u64>>;
+}
+
+/// Runs the actions of one zone run through the sync engine.
+struct ZoneExecutor<'a, R: Runtime> {
+    engine: &'a SyncEngine,
+    app: &'a AppHandle<R>,
+    ctx: &'a ZoneContext<'a>,
+    lookups: &'a ScanLookups<'a>,
+}
+
+impl<R: Runtime> ActionExecutor for ZoneExecutor<'_, R> {
+    async fn create_remote_parents(&self, actions: &[&DiffResult]) {
+        for parent in remote_parents(actions) {
+            let _ = self.ctx.transfer.mkcol_recursive(parent).await;
+        }
+    }
+
+    fn started(&self, diff_result: &DiffResult, current: usize, total: usize) {
+        let _ = self.app.emit(
+            "sync-file-progress",
+            SyncFileProgressPayload {
+                path: diff_result.path.clone(),
+                action: action_label(&diff_result.action).to_string(),
+                current,
+                total,
+            },
+        );
+    }
+
+    async fn execute(&self, diff_result: &DiffResult) -> AppResult<Option<u64>> {
+        self.engine
+            .run_action(self.app, self.ctx, self.lookups, diff_result)
+            .await
+    }
+}
+
+/// User's choice for resolving a conflicted file (sent by the frontend dialog).
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+pub enum ConflictResolution {
+    /// Local version wins: upload it (or propagate the local delete).
+    #[serde(rename = "keep_local")]
+    Local,
+    /// Server version wins: download it (or propagate the remote delete).
+    #[serde(rename = "keep_remote")]
+    Remote,
+    /// Keep both: the local file is renamed to a conflicted copy and both are synced.
+    #[serde(rename = "keep_both")]
+    Both,
+}
+
+/// Which files a sync run looks at.
+#[derive(Debug, Clone)]
+pub enum SyncScope {
+    /// Scan both zones completely.
+    Full,
+    /// Only these files, relative to each zone's root (reported by the watcher).
+    /// A zone whose paths touch a folder is scanned completely after all.
+    Paths {
+        personal: Vec<String>,
+        shared: Vec<String>,
+    },
+}
+
+/// How much of one zone a sync run scans.
+enum ZoneScan<'a> {
+    /// Everything; `verify` rehashes files the stat cache would skip.
+    Full { verify: bool },
+    /// Only these paths, relative to the zone root.
+    Paths(&'a [String]),
+}
+
+/// Per-zone state shared by every action executed during one sync run.
+struct ZoneContext<'a> {
+    db: &'a DbPool,
+    transfer: &'a WebDavTransfer,
+    trash: &'a LocalTrashManager,
+    zone: &'a str,
+    local_base: &'a Path,
+    conflict_policy: ConflictPolicy,
+    /// Remote files as of the scan, hidden ones included, kept up to date as the
+    /// run deletes and moves them: what a remote folder still holds
+    remote_tree: Mutex<BTreeSet<String>>,
+}
+
 /// Sync engine that performs native bidirectional sync via WebDAV + SQLite state.
 pub struct SyncEngine {
     status: Mutex<SyncStatus>,
     activity_log: Mutex<Vec<ActivityEntry>>,
     /// Lazily initialized DB pool (None until first sync).
     db: Mutex<Option<DbPool>>,
+    /// Cancels the sync run in progress (None when idle).
+    cancel: Mutex<Option<CancellationToken>>,
+    /// Zones whose next full run rehashes all local files, ignoring the stat
+    /// cache (until one gets through the zone).
+    full_verify_requested: Mutex<HashSet<&'static str>>,
+    /// Bandwidth limits shared by all transfers of both zones.
+    bandwidth: Arc<BandwidthLimiter>,
+    /// Partial downloads of the previous app run have been cleaned up.
+    partials_cleaned: AtomicBool,
 }
 
 impl SyncEngine {
@@ -41,6 +189,45 @@ impl SyncEngine {
             status: Mutex::new(SyncStatus::NotConfigured),
             activity_log: Mutex::new(Vec::new()),
             db: Mutex::new(None),
+            cancel: Mutex::new(None),
+            full_verify_requested: Mutex::new(HashSet::new()),
+            bandwidth: Arc::new(BandwidthLimiter::new(0, 0)),
+            partials_cleaned: AtomicBool::new(false),
+        }
+    }
+
+    /// Apply the bandwidth limits the config puts in force now (schedule
+    /// included), to running transfers as well.
+    pub fn set_bandwidth_limits(&self, config: &AppConfig) {
+        self.apply_bandwidth(config.effective_bandwidth());
+    }
+
+    /// Set the limiter to `limits`. A pause window lets no data through, and
+    /// its start cancels the run in progress rather than leaving it stalled.
+    fn apply_bandwidth(&self, limits: EffectiveBandwidth) {
+        self.bandwidth
+            .set_limits(limits.upload_kbps, limits.download_kbps);
+        if self.bandwidth.set_paused(limits.paused) && limits.paused {
+            if let Some(cancel) = self.cancel_guard().as_ref() {
+                cancel.cancel();
+                log::info!("Sync cancelled by a pause window of the bandwidth schedule");
+            }
+        }
+    }
+
+    /// Every `period`, apply the limits `limits` says are in force, so a schedule
+    /// window that starts or ends throttles, stops or frees running transfers as
+    /// well. Never returns.
+    pub async fn follow_bandwidth_schedule(
+        &self,
+        period: Duration,
+        limits: impl Fn() -> EffectiveBandwidth,
+    ) {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            self.apply_bandwidth(limits());
         }
     }
 
@@ -61,17 +248,21 @@ impl SyncEngine {
         Ok(guard.as_ref().expect("just initialized").clone())
     }
 
-    /// Run a full bidirectional sync for both personal and shared files.
-    pub async fn sync_all(
+    /// Run a bidirectional sync for both personal and shared files.
+    pub async fn sync_all<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         config: &AppConfig,
         token: &str,
         source: &str,
+        scope: &SyncScope,
     ) -> AppResult<()> {
         if !config.is_configured() {
             return Err(AppError::sync("Aplikacja nie jest skonfigurowana"));
         }
+        if config.effective_bandwidth().paused {
+            return Err(AppError::sync(SCHEDULE_PAUSED));
+        }
 
         {
             let mut status = self.status_guard();
@@ -84,6 +275,18 @@ impl SyncEngine {
             *status = SyncStatus::Syncing;
         }
 
+        let cancel = CancellationToken::new();
+        *self.cancel_guard() = Some(cancel.clone());
+        self.set_bandwidth_limits(config);
+
+        // Leftovers of the previous app run, before this run resumes any of them
+        if !self.partials_cleaned.swap(true, Ordering::SeqCst) {
+            let roots = vec![config.personal_sync_path.clone(), config.shared_sync_path.clone()];
+            if let Err(e) = self.cleanup_partial_downloads(roots).await {
+                log::warn!("Failed to clean up partial downloads: {}", e);
+            }
+        }
+
         // Notify frontend that sync has started
         let _ = app.emit(
             "sync-progress",
@@ -108,10 +311,34 @@ impl SyncEngine {
             .get_db()
             .map_err(|e| self.set_error_status(e.to_string()))?;
 
+        let full = matches!(scope, SyncScope::Full);
+        let verify_personal = full && self.full_verify_due(&db, config, "personal");
+        let verify_shared = full && self.full_verify_due(&db, config, "shared");
+        let (personal_scan, shared_scan) = match scope {
+            SyncScope::Full => (
+                ZoneScan::Full {
+                    verify: verify_personal,
+                },
+                ZoneScan::Full {
+                    verify: verify_shared,
+                },
+            ),
+            SyncScope::Paths { personal, shared } => {
+                (ZoneScan::Paths(personal), ZoneScan::Paths(shared))
+            }
+        };
+
         // Start sync run tracking
         let run_id = db::start_sync_run(&db, source)
             .map_err(|e| self.set_error_status(e.to_string()))?;
 
+        // Asked for by the user: failed files are tried again at once
+        if source == "manual" {
+            if let Err(e) = db::clear_retry_backoff(&db) {
+                log::warn!("Cannot clear the retry backoff of failed files: {}", e);
+            }
+        }
+
         let mut total_stats = SyncRunStats {
             files_uploaded: 0,
             files_downloaded: 0,
@@ -130,8 +357,9 @@ impl SyncEngine {
                 &config.personal_sync_path,
                 &config.personal_webdav_url(),
                 token,
-                source,
+                &cancel,
                 config,
+                personal_scan,
             )
             .await;
 
@@ -142,28 +370,41 @@ impl SyncEngine {
                 total_stats.files_deleted += stats.files_deleted;
                 total_stats.files_conflicted += stats.files_conflicted;
                 total_stats.bytes_transferred += stats.bytes_transferred;
-                self.log_activity("sync_personal", "", "success", None);
+                let status = if cancel.is_cancelled() { "cancelled" } else { "success" };
+                self.log_activity("sync_personal", "", status, None);
                 true
             }
+            Err(e) if e.is_cancelled() => {
+                self.log_activity("sync_personal", "", "cancelled", None);
+                false
+            }
             Err(e) => {
                 self.log_activity("sync_personal", "", "error", Some(e.to_string()));
                 false
             }
         };
+        // A verify interrupted by a cancel or an error is repeated by the next run
+        if verify_personal && personal_ok && !cancel.is_cancelled() {
+            self.full_verify_done(&db, "personal");
+        }
 
-        // Sync shared zone
-        let shared_result = self
-            .sync_zone(
+        // Sync shared zone (unless the user cancelled during the personal one)
+        let shared_result = if cancel.is_cancelled() {
+            Err(AppError::cancelled())
+        } else {
+            self.sync_zone(
                 app,
                 &db,
                 "shared",
                 &config.shared_sync_path,
                 &config.shared_webdav_url(),
                 token,
-                source,
+                &cancel,
                 config,
+                shared_scan,
             )
-            .await;
+            .await
+        };
 
         let shared_ok = match &shared_result {
             Ok(stats) => {
@@ -172,14 +413,22 @@ impl SyncEngine {
                 total_stats.files_deleted += stats.files_deleted;
                 total_stats.files_conflicted += stats.files_conflicted;
                 total_stats.bytes_transferred += stats.bytes_transferred;
-                self.log_activity("sync_shared", "", "success", None);
+                let status = if cancel.is_cancelled() { "cancelled" } else { "success" };
+                self.log_activity("sync_shared", "", status, None);
                 true
             }
+            Err(e) if e.is_cancelled() => {
+                self.log_activity("sync_shared", "", "cancelled", None);
+                false
+            }
             Err(e) => {
                 self.log_activity("sync_shared", "", "error", Some(e.to_string()));
                 false
             }
         };
+        if verify_shared && shared_ok && !cancel.is_cancelled() {
+            self.full_verify_done(&db, "shared");
+        }
 
         // Cleanup expired local trash
         let trash_dir = default_trash_dir();
@@ -191,8 +440,17 @@ impl SyncEngine {
             + total_stats.files_downloaded
             + total_stats.files_deleted) as usize;
 
-        let (run_status, final_status, emit_phase, emit_message) =
-            if personal_ok && shared_ok {
+        let unresolved_conflicts = db::count_conflicts(&db).unwrap_or(0);
+
+        let (run_status, mut final_status, emit_phase, emit_message) =
+            if cancel.is_cancelled() {
+                (
+                    "cancelled",
+                    SyncStatus::Idle,
+                    "cancelled".to_string(),
+                    "Synchronizacja anulowana".to_string(),
+                )
+            } else if personal_ok && shared_ok {
                 let message = if total_files > 0 {
                     format!("Zsynchronizowano {} plików", total_files)
                 } else {
@@ -220,8 +478,20 @@ impl SyncEngine {
             };
 
         let _ = db::complete_sync_run(&db, run_id, run_status, &total_stats);
+        *self.cancel_guard() = None;
+
+        // Unresolved conflicts outrank a successful run until the user resolves them.
+        if unresolved_conflicts > 0 && final_status == SyncStatus::Idle {
+            final_status = SyncStatus::Conflict;
+        }
 
-        self.set_status(final_status);
+        // A pause during the run outlasts it
+        {
+            let mut status = self.status_guard();
+            if *status != SyncStatus::Paused {
+                *status = final_status;
+            }
+        }
 
         let _ = app.emit(
             "sync-progress",
@@ -241,31 +511,101 @@ impl SyncEngine {
     }
 
     /// Sync a single zone (personal or shared).
-    async fn sync_zone(
+    async fn sync_zone<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         db: &DbPool,
         zone: &str,
         local_base: &Path,
         webdav_url: &str,
         token: &str,
-        _source: &str,
+        cancel: &CancellationToken,
         config: &AppConfig,
+        scan: ZoneScan<'_>,
     ) -> AppResult<SyncRunStats> {
+        let mut stats = SyncRunStats {
+            files_uploaded: 0,
+            files_downloaded: 0,
+            files_deleted: 0,
+            files_conflicted: 0,
+            bytes_transferred: 0,
+            error_message: None,
+        };
+        if let ZoneScan::Paths([]) = scan {
+            return Ok(stats);
+        }
+
         log::info!("Starting sync zone '{}' local={}", zone, local_base.display());
 
-        let transfer = WebDavTransfer::new_with_limits(
-            webdav_url,
-            token,
-            config.max_upload_kbps,
-            config.max_download_kbps,
-        );
+        let transfer = WebDavTransfer::new(webdav_url, token)
+            .with_db(db.clone())
+            .with_bandwidth_limiter(self.bandwidth.clone())
+            .with_retry_policy(retry_policy(config))
+            .with_chunked_uploads(
+                &config.uploads_webdav_url(),
+                config.chunked_upload_threshold_bytes,
+            )
+            .with_cancel(cancel.clone());
 
-        // 1. Scan local files
-        let mut local_files = scan_local_files(local_base)?;
+        // Rows of the last sync; their stat tuples spare rehashing unchanged files
+        let mut known_rows = db::list_files_by_zone(db, zone)?;
+        // Synced rows a targeted run does not scan, still usable as copy sources
+        let mut unscanned_rows = Vec::new();
+        let max_file_size = config.max_file_size(zone);
 
-        // 2. Scan remote files
-        let mut remote_files = scan_remote_files(&transfer).await?;
+        // 1-2. Scan local and remote files: only the paths the watcher reported
+        // when they are all plain files, everything otherwise (those above the
+        // zone's size limit are not hashed)
+        let targeted = match scan {
+            ZoneScan::Paths(paths) => {
+                let scans =
+                    scan_touched_paths(&transfer, local_base, paths, &known_rows, max_file_size)
+                        .await?;
+                if scans.is_none() {
+                    log::info!("Zone '{}': changes touch folders, scanning everything", zone);
+                }
+                scans.map(|scans| (scans, paths))
+            }
+            ZoneScan::Full { .. } => None,
+        };
+        let (local_scan, remote_scan) = match targeted {
+            Some((scans, paths)) => {
+                let touched: HashSet<&str> = paths.iter().map(String::as_str).collect();
+                (known_rows, unscanned_rows) = known_rows
+                    .into_iter()
+                    .partition(|s| touched.contains(s.path.as_str()));
+                scans
+            }
+            None => {
+                let verify = matches!(scan, ZoneScan::Full { verify: true });
+                let stat_cache = file_stat_cache(&known_rows);
+                let local_scan =
+                    scan_local_files(local_base, max_file_size, (!verify).then_some(&stat_cache))
+                        .await?;
+                drop(stat_cache);
+                (local_scan, scan_remote(&transfer, db, zone, webdav_url, verify).await?)
+            }
+        };
+        let LocalScan {
+            files: mut local_files,
+            dirs: mut local_dirs,
+            too_large: mut local_too_large,
+            hidden: local_hidden,
+            refreshed_stats,
+        } = local_scan;
+        if let Err(e) = db::refresh_local_stats(db, zone, &refreshed_stats) {
+            log::warn!("Zone '{}': cannot store local stat cache: {}", zone, e);
+        }
+        let RemoteScan {
+            files: mut remote_files,
+            dirs: mut remote_dirs,
+            hidden: remote_hidden,
+        } = remote_scan;
+        let remote_tree: BTreeSet<String> = remote_files
+            .iter()
+            .map(|f| f.path.clone())
+            .chain(remote_hidden.iter().cloned())
+            .collect();
 
         // Apply selective sync filter to BOTH local and remote (if configured).
         // A file is kept iff it lives inside any included folder.
@@ -285,91 +625,206 @@ impl SyncEngine {
 
             local_files.retain(|f| keep(&f.path));
             remote_files.retain(|f| keep(&f.path));
+            local_dirs.retain(|d| keep(d));
+            remote_dirs.retain(|d| keep(d));
+            local_too_large.retain(|(path, _)| keep(path));
+        }
+
+        // Files above the size limit on either side are left out of the diff entirely
+        // (so neither copy is deleted) and recorded as skipped
+        let mut too_large: HashMap<String, (Option<i64>, Option<i64>)> = HashMap::new();
+        for (path, size) in local_too_large {
+            too_large.entry(path).or_default().0 = Some(size);
+        }
+        if max_file_size > 0 {
+            for file in &remote_files {
+                if file.size.is_some_and(|size| size as u64 > max_file_size) {
+                    too_large.entry(file.path.clone()).or_default().1 = file.size;
+                }
+            }
+        }
+        for file in &local_files {
+            if let Some(sizes) = too_large.get_mut(&file.path) {
+                sizes.0 = Some(file.size);
+            }
+        }
+        for file in &remote_files {
+            if let Some(sizes) = too_large.get_mut(&file.path) {
+                sizes.1 = file.size;
+            }
         }
+        local_files.retain(|f| !too_large.contains_key(&f.path));
+        remote_files.retain(|f| !too_large.contains_key(&f.path));
+        for (path, (local_size, remote_size)) in &too_large {
+            if let Err(e) =
+                self.record_too_large(db, zone, path, *local_size, *remote_size, max_file_size)
+            {
+                log::warn!("Failed to record skipped file {}: {}", path, e);
+            }
+        }
+
         let local_files = local_files;
         let remote_files = remote_files;
         log::debug!("Zone '{}': {} local files found", zone, local_files.len());
         log::debug!("Zone '{}': {} remote files found", zone, remote_files.len());
+        if !too_large.is_empty() {
+            log::info!("Zone '{}': {} files skipped as too large", zone, too_large.len());
+        }
+
+        if cancel.is_cancelled() {
+            return Err(AppError::cancelled());
+        }
 
         // 3. Load known states from SQLite
-        let known_states = db::list_files_by_zone(db, zone)?;
+        let (known_dirs, mut known_states): (Vec<FileState>, Vec<FileState>) =
+            known_rows.into_iter().partition(FileState::is_dir);
+        known_states.retain(|s| !too_large.contains_key(&s.path));
         log::debug!("Zone '{}': {} known states in DB", zone, known_states.len());
 
-        // 4. Run 3-way diff (local and remote paths are relative to their bases)
+        // Folders present on both sides (or on neither) need no action, only a row
+        let local_dir_set: HashSet<&str> = local_dirs.iter().map(String::as_str).collect();
+        let remote_dir_set: HashSet<&str> = remote_dirs.iter().map(String::as_str).collect();
+        let both_dirs: Vec<&str> = local_dir_set.intersection(&remote_dir_set).copied().collect();
+        db::track_synced_dirs(db, zone, &both_dirs)?;
+        for state in &known_dirs {
+            let path = state.path.as_str();
+            if !local_dir_set.contains(path) && !remote_dir_set.contains(path) {
+                let _ = db::delete_file_state(db, path, zone);
+            }
+        }
+
+        // 4. Run 3-way diff (local and remote paths are relative to their bases);
+        // what it leaves out keeps folders holding it from being moved whole
+        let excluded = ExcludedPaths {
+            local: too_large
+                .iter()
+                .filter(|(_, (local_size, _))| local_size.is_some())
+                .map(|(path, _)| path.as_str())
+                .chain(local_hidden.iter().map(String::as_str))
+                .collect(),
+            remote: too_large
+                .iter()
+                .filter(|(_, (_, remote_size))| remote_size.is_some())
+                .map(|(path, _)| path.as_str())
+                .chain(remote_hidden.iter().map(String::as_str))
+                .collect(),
+        };
         let local_base_str = local_base.to_string_lossy().to_string();
-        let actions = compute_diff(
+        let mut actions = compute_diff(
             &local_files,
             &remote_files,
             &known_states,
+            &excluded,
             zone,
             &local_base_str,
             "", // remote paths are relative; WebDavTransfer prepends the base URL
         );
+        let dir_actions = compute_dir_diff(
+            &local_dirs,
+            &remote_dirs,
+            &known_dirs,
+            &actions,
+            zone,
+            &local_base_str,
+            "",
+        );
+        actions.extend(dir_actions);
+
+        // Files that failed recently wait out their backoff instead of failing again,
+        // unless either side changed since the failure
+        let now = chrono::Utc::now().timestamp();
+        let local_hashes: HashMap<&str, &str> = local_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.hash.as_str()))
+            .collect();
+        let remote_etags: HashMap<&str, Option<&str>> = remote_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.etag.as_deref()))
+            .collect();
+        let backing_off: HashSet<&str> = known_states
+            .iter()
+            .filter(|s| s.sync_status == "error" && s.next_retry_at.is_some_and(|at| at > now))
+            .filter(|s| {
+                let path = s.path.as_str();
+                local_hashes.get(path).copied() == s.local_hash.as_deref()
+                    && remote_etags.get(path).copied().flatten() == s.remote_etag.as_deref()
+            })
+            .map(|s| s.path.as_str())
+            .collect();
+        if !backing_off.is_empty() {
+            let before = actions.len();
+            actions.retain(|r| !backing_off.contains(r.path.as_str()));
+            log::info!(
+                "Zone '{}': {} failed files wait for their next retry",
+                zone,
+                before - actions.len()
+            );
+        }
 
         log::info!("Zone '{}': {} sync actions to perform", zone, actions.len());
 
         let total = actions.len();
-        let mut stats = SyncRunStats {
-            files_uploaded: 0,
-            files_downloaded: 0,
-            files_deleted: 0,
-            files_conflicted: 0,
-            bytes_transferred: 0,
-            error_message: None,
-        };
 
         // Set up trash manager
         let trash = LocalTrashManager::new(default_trash_dir(), db.clone());
 
-        // 5. Execute each action
-        for (idx, diff_result) in actions.iter().enumerate() {
-            let current = idx + 1;
-
-            let action_str = action_label(&diff_result.action);
-            let _ = app.emit(
-                "sync-file-progress",
-                SyncFileProgressPayload {
-                    path: diff_result.path.clone(),
-                    action: action_str.to_string(),
-                    current,
-                    total,
-                },
-            );
+        let ctx = ZoneContext {
+            db,
+            transfer: &transfer,
+            trash: &trash,
+            zone,
+            local_base,
+            conflict_policy: config.conflict_policy(zone),
+            remote_tree: Mutex::new(remote_tree),
+        };
 
-            let exec_result = self
-                .execute_action(
-                    app,
-                    db,
-                    &transfer,
-                    &trash,
-                    zone,
-                    local_base,
-                    &diff_result.action,
-                    &diff_result.path,
-                )
-                .await;
+        // Scan snapshots by path, used to record what each side looked like on conflict
+        let mut lookups = ScanLookups {
+            local: local_files.iter().map(|f| (f.path.as_str(), f)).collect(),
+            remote: remote_files.iter().map(|f| (f.path.as_str(), f)).collect(),
+            known: known_states.iter().map(|s| (s.path.as_str(), s)).collect(),
+            copy_sources: HashMap::new(),
+        };
+        lookups.copy_sources = find_copy_sources(&lookups, &known_states, &unscanned_rows);
 
-            match exec_result {
-                Ok(bytes) => match &diff_result.action {
-                    SyncAction::Upload { .. } => {
-                        stats.files_uploaded += 1;
-                        stats.bytes_transferred += bytes as i64;
-                    }
-                    SyncAction::Download { .. } => {
-                        stats.files_downloaded += 1;
-                        stats.bytes_transferred += bytes as i64;
-                    }
-                    SyncAction::DeleteLocal { .. } | SyncAction::DeleteRemote { .. } => {
-                        stats.files_deleted += 1;
-                    }
-                    SyncAction::Conflict { .. } => {
-                        stats.files_conflicted += 1;
-                    }
-                    SyncAction::Skip => {}
-                },
+        // 5. Execute actions, phase by phase on the upload/download slots (run_actions)
+        let slots = TransferSlots {
+            uploads: Semaphore::new(config.max_parallel_uploads.max(1)),
+            downloads: Semaphore::new(config.max_parallel_downloads.max(1)),
+            started: AtomicUsize::new(0),
+            total,
+            cancel: cancel.clone(),
+        };
+        let parallelism = config.max_parallel_uploads.max(1) + config.max_parallel_downloads.max(1);
+        let executor = ZoneExecutor {
+            engine: self,
+            app,
+            ctx: &ctx,
+            lookups: &lookups,
+        };
+
+        // Aggregate on this task only, once the transfers are done
+        for (diff_result, result) in run_actions(&actions, &slots, parallelism, &executor).await {
+            match result {
+                Ok(Some(bytes)) => count_done(&mut stats, &diff_result.action, bytes),
+                Ok(None) => {}
+                // Not a failure of the file: it is simply picked up by the next run
+                Err(e) if e.is_cancelled() => {}
                 Err(e) => {
                     log::warn!("Zone '{}': error on '{}': {}", zone, diff_result.path, e);
-                    let _ = record_file_error(db, &diff_result.path, zone, &e.to_string());
+                    // A folder action is simply retried; its row only tracks presence
+                    if !diff_result.action.is_directory() {
+                        let path = diff_result.path.as_str();
+                        let _ = record_file_error(
+                            db,
+                            path,
+                            zone,
+                            &e.to_string(),
+                            config.file_retry_max_minutes,
+                            lookups.local.get(path).copied(),
+                            lookups.remote.get(path).copied(),
+                        );
+                    }
                     self.log_activity(
                         &format!("sync_{}", zone),
                         &diff_result.path,
@@ -383,18 +838,149 @@ impl SyncEngine {
         Ok(stats)
     }
 
-    /// Execute a single sync action. Returns bytes transferred (0 for deletes/conflicts).
-    async fn execute_action(
+    /// Run one diff result: link identical never-synced files, snapshot conflicts,
+    /// then execute the action. Returns None when nothing was counted as done.
+    async fn run_action<R: Runtime>(
         &self,
-        app: &AppHandle,
-        db: &DbPool,
-        transfer: &WebDavTransfer,
-        trash: &LocalTrashManager,
-        zone: &str,
-        _local_base: &Path,
+        app: &AppHandle<R>,
+        ctx: &ZoneContext<'_>,
+        lookups: &ScanLookups<'_>,
+        diff_result: &DiffResult,
+    ) -> AppResult<Option<u64>> {
+        let path = diff_result.path.as_str();
+        let local_info = lookups.local.get(path).copied();
+        let remote_info = lookups.remote.get(path).copied();
+
+        // Both sides have the file but it was never synced (fresh install, wiped DB,
+        // existing folder): identical content just needs a `synced` row, not a conflict.
+        if let (
+            SyncAction::Conflict {
+                conflict_type: ConflictType::BothModified,
+                ..
+            },
+            Some(local),
+            Some(remote),
+        ) = (&diff_result.action, local_info, remote_info)
+        {
+            let never_synced = lookups
+                .known
+                .get(path)
+                .is_none_or(|k| k.last_synced_at.is_none());
+            if never_synced {
+                match self.link_if_identical(ctx, local, remote).await {
+                    Ok(true) => return Ok(None),
+                    Ok(false) => {}
+                    Err(e) => log::warn!("Zone '{}': cannot compare '{}': {}", ctx.zone, path, e),
+                }
+            }
+        }
+
+        // A new file whose content is already on the server is copied there
+        if let (SyncAction::Upload { local_path, .. }, Some(local), None) =
+            (&diff_result.action, local_info, lookups.known.get(path))
+        {
+            if let Some(source) = lookups.copy_sources.get(local.hash.as_str()) {
+                match self
+                    .copy_on_server(ctx, source, path, Path::new(local_path), local)
+                    .await
+                {
+                    Ok(()) => return Ok(Some(0)),
+                    Err(e) if e.is_cancelled() => return Err(e),
+                    Err(e) => log::warn!(
+                        "Zone '{}': server-side copy {} -> {} failed, uploading: {}",
+                        ctx.zone,
+                        source.path,
+                        path,
+                        e
+                    ),
+                }
+            }
+        }
+
+        if matches!(diff_result.action, SyncAction::Conflict { .. }) {
+            let _ = record_conflict_snapshot(ctx.db, path, ctx.zone, local_info, remote_info);
+        }
+
+        match self.execute_action(app, ctx, &diff_result.action, path).await {
+            Err(e) if e.is_precondition_failed() => {
+                log::warn!(
+                    "Zone '{}': '{}' changed on the server since the scan ({}), handling as a conflict",
+                    ctx.zone,
+                    path,
+                    e
+                );
+                self.precondition_conflict(app, ctx, diff_result, local_info)
+                    .await
+            }
+            result => result.map(Some),
+        }
+    }
+
+    /// An upload or delete was refused with 412: the server version is no longer the
+    /// one it was based on. Record what the server has now and run the file as a
+    /// fresh conflict, so neither version is lost. The planned action is not counted.
+    async fn precondition_conflict<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        ctx: &ZoneContext<'_>,
+        diff_result: &DiffResult,
+        local_info: Option<&LocalFileInfo>,
+    ) -> AppResult<Option<u64>> {
+        let path = diff_result.path.as_str();
+        let remote_path = match &diff_result.action {
+            SyncAction::Upload { remote_path, .. } | SyncAction::DeleteRemote { remote_path } => {
+                remote_path.trim_start_matches('/')
+            }
+            _ => {
+                return Err(AppError::sync(format!(
+                    "Remote version of {} changed during sync",
+                    path
+                )))
+            }
+        };
+
+        let remote = fetch_remote_entry(ctx.transfer, remote_path)
+            .await?
+            .map(|entry| remote_file_info(path.to_string(), entry));
+        let conflict_type = match (local_info, &remote) {
+            (Some(_), Some(_)) => ConflictType::BothModified,
+            (None, Some(_)) => ConflictType::DeletedLocallyModifiedRemotely,
+            (Some(_), None) => ConflictType::DeletedRemotelyModifiedLocally,
+            (None, None) => {
+                // Gone on both sides by now
+                db::delete_file_state(ctx.db, path, ctx.zone)?;
+                return Ok(None);
+            }
+        };
+
+        // A write that was retried after a lost response may have landed already
+        if let (Some(local), Some(remote)) = (local_info, &remote) {
+            if self.link_if_identical(ctx, local, remote).await? {
+                return Ok(None);
+            }
+        }
+
+        record_conflict_snapshot(ctx.db, path, ctx.zone, local_info, remote.as_ref())?;
+        let conflict = SyncAction::Conflict {
+            local_path: ctx.local_base.join(path).to_string_lossy().to_string(),
+            remote_path: remote_path.to_string(),
+            conflict_type,
+        };
+        self.execute_action(app, ctx, &conflict, path).await?;
+        Ok(None)
+    }
+
+    /// Execute a single sync action. Returns bytes transferred (0 for deletes and
+    /// conflicts left for the user).
+    async fn execute_action<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        ctx: &ZoneContext<'_>,
         action: &SyncAction,
         rel_path: &str,
     ) -> AppResult<u64> {
+        let (db, transfer, zone) = (ctx.db, ctx.transfer, ctx.zone);
+
         match action {
             SyncAction::Upload {
                 local_path,
@@ -404,34 +990,13 @@ impl SyncEngine {
                 let remote = remote_path.trim_start_matches('/');
 
                 log::info!("Upload: {} -> {}", local_path, remote);
-                let result = transfer.upload_file(local, remote).await?;
-
-                let new_etag = fetch_etag(transfer, remote).await.unwrap_or_default();
-
-                let metadata = std::fs::metadata(local)
-                    .map_err(|e| AppError::io(format!("Cannot stat {}: {}", local_path, e)))?;
-                let hash = hash_file(local)?;
-                let mtime = mtime_from_metadata(&metadata);
-
-                let state = FileState {
-                    path: rel_path.to_string(),
-                    sync_zone: zone.to_string(),
-                    local_hash: Some(hash.clone()),
-                    local_mtime: Some(mtime),
-                    local_size: Some(metadata.len() as i64),
-                    local_exists: true,
-                    remote_etag: non_empty_str(&new_etag),
-                    remote_mtime: None,
-                    remote_size: Some(result.bytes_sent as i64),
-                    remote_exists: true,
-                    sync_status: "synced".to_string(),
-                    last_synced_hash: Some(hash),
-                    last_synced_mtime: Some(mtime),
-                    last_synced_etag: non_empty_str(&new_etag),
-                    last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
-                    error_message: None,
-                    retry_count: 0,
-                };
+                let known = db::get_file_state(db, rel_path, zone)?;
+                let result = transfer
+                    .upload_file_if(local, remote, upload_precondition(known.as_ref()))
+                    .await?;
+
+                let version = RemoteVersion::uploaded(transfer, remote, &result).await;
+                let state = build_synced_state(rel_path, zone, local, version).await?;
                 db::upsert_file_state(db, &state)?;
 
                 self.log_activity("upload", rel_path, "success", None);
@@ -455,32 +1020,8 @@ impl SyncEngine {
                 log::info!("Download: {} -> {}", remote, local_path);
                 let result = transfer.download_file(remote, local).await?;
 
-                let metadata = std::fs::metadata(local)
-                    .map_err(|e| AppError::io(format!("Cannot stat {}: {}", local_path, e)))?;
-                let hash = hash_file(local)?;
-                let mtime = mtime_from_metadata(&metadata);
-
-                let new_etag = fetch_etag(transfer, remote).await.unwrap_or_default();
-
-                let state = FileState {
-                    path: rel_path.to_string(),
-                    sync_zone: zone.to_string(),
-                    local_hash: Some(hash.clone()),
-                    local_mtime: Some(mtime),
-                    local_size: Some(metadata.len() as i64),
-                    local_exists: true,
-                    remote_etag: non_empty_str(&new_etag),
-                    remote_mtime: None,
-                    remote_size: Some(result.bytes_received as i64),
-                    remote_exists: true,
-                    sync_status: "synced".to_string(),
-                    last_synced_hash: Some(hash),
-                    last_synced_mtime: Some(mtime),
-                    last_synced_etag: non_empty_str(&new_etag),
-                    last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
-                    error_message: None,
-                    retry_count: 0,
-                };
+                let version = RemoteVersion::downloaded(transfer, remote, &result).await;
+                let state = build_synced_state(rel_path, zone, local, version).await?;
                 db::upsert_file_state(db, &state)?;
 
                 self.log_activity("download", rel_path, "success", None);
@@ -491,7 +1032,7 @@ impl SyncEngine {
                 let local = Path::new(local_path);
                 if local.exists() {
                     log::info!("DeleteLocal (trash): {}", local_path);
-                    trash.trash_file(local, zone)?;
+                    ctx.trash.trash_file(local, zone)?;
                 }
                 let _ = db::delete_file_state(db, rel_path, zone);
                 self.log_activity("delete_local", rel_path, "success", None);
@@ -501,7 +1042,10 @@ impl SyncEngine {
             SyncAction::DeleteRemote { remote_path } => {
                 let remote = remote_path.trim_start_matches('/');
                 log::info!("DeleteRemote: {}", remote);
-                transfer.delete_remote(remote).await?;
+                let known = db::get_file_state(db, rel_path, zone)?;
+                let etag = known.as_ref().and_then(|k| k.last_synced_etag.as_deref());
+                transfer.delete_remote_if(remote, etag_precondition(etag)).await?;
+                ctx.remote_tree_lock().remove(remote);
                 let _ = db::delete_file_state(db, rel_path, zone);
                 self.log_activity("delete_remote", rel_path, "success", None);
                 Ok(0)
@@ -515,8 +1059,34 @@ impl SyncEngine {
                 // Compute debug string once — conflict_type will be moved into json! below.
                 let conflict_debug = format!("{:?}", conflict_type);
 
+                // Row written by record_conflict_snapshot just before this call
+                let existing = db::get_file_state(db, rel_path, zone)?;
+
+                if let Some(state) = &existing {
+                    if let Some(resolution) = auto_resolution(ctx.conflict_policy, state) {
+                        log::info!(
+                            "Conflict '{}' ({}): auto-resolving with {:?} (policy {:?})",
+                            rel_path,
+                            conflict_debug,
+                            resolution,
+                            ctx.conflict_policy
+                        );
+                        let bytes = self.apply_resolution(ctx, rel_path, state, resolution).await?;
+                        self.log_activity(
+                            "conflict_auto_resolved",
+                            rel_path,
+                            "success",
+                            Some(format!(
+                                "{}: {:?} (policy {:?})",
+                                conflict_debug, resolution, ctx.conflict_policy
+                            )),
+                        );
+                        return Ok(bytes);
+                    }
+                }
+
                 log::warn!(
-                    "Conflict '{}' ({}): auto-skipping (Phase 4 resolution pending)",
+                    "Conflict '{}' ({}): skipped until resolved via resolve_conflict",
                     rel_path,
                     conflict_debug
                 );
@@ -525,6 +1095,7 @@ impl SyncEngine {
                     "sync-conflict",
                     serde_json::json!({
                         "path": rel_path,
+                        "zone": zone,
                         "localPath": local_path,
                         "remotePath": remote_path,
                         "conflictType": conflict_type,
@@ -541,70 +1112,522 @@ impl SyncEngine {
                     .show();
 
                 // Record conflict state in DB
-                let existing = db::get_file_state(db, rel_path, zone)?;
-                let base = existing.unwrap_or(FileState {
-                    path: rel_path.to_string(),
-                    sync_zone: zone.to_string(),
-                    local_hash: None,
-                    local_mtime: None,
-                    local_size: None,
-                    local_exists: true,
-                    remote_etag: None,
-                    remote_mtime: None,
-                    remote_size: None,
-                    remote_exists: true,
-                    sync_status: "conflict".to_string(),
-                    last_synced_hash: None,
-                    last_synced_mtime: None,
-                    last_synced_etag: None,
-                    last_synced_at: None,
-                    error_message: None,
-                    retry_count: 0,
-                });
+                let base = existing.unwrap_or_else(|| new_conflict_state(rel_path, zone));
                 let updated = FileState {
                     sync_status: "conflict".to_string(),
                     error_message: Some(format!("Conflict: {}", conflict_debug)),
                     ..base
                 };
                 db::upsert_file_state(db, &updated)?;
+                db::set_conflict_type(db, rel_path, zone, &conflict_debug)?;
 
                 self.log_activity("conflict", rel_path, "skipped", Some(conflict_debug));
                 Ok(0)
             }
 
+            SyncAction::Move {
+                from,
+                source,
+                destination,
+                side,
+            } => {
+                match side {
+                    MoveSide::Remote => {
+                        let source = source.trim_start_matches('/');
+                        let destination = destination.trim_start_matches('/');
+                        log::info!("Move (remote): {} -> {}", source, destination);
+                        transfer.move_remote(source, destination).await?;
+                        ctx.moved_remote(source, destination);
+                    }
+                    MoveSide::Local => {
+                        log::info!("Move (local): {} -> {}", source, destination);
+                        move_local(Path::new(source), Path::new(destination))?;
+                    }
+                }
+                let moved = db::rename_file_states(db, zone, from, rel_path)?;
+                self.log_activity(
+                    "move",
+                    rel_path,
+                    "success",
+                    Some(format!("{} ({} files)", from, moved)),
+                );
+                Ok(0)
+            }
+
+            SyncAction::CreateLocalDir { local_path } => {
+                log::info!("CreateLocalDir: {}", local_path);
+                std::fs::create_dir_all(local_path)
+                    .map_err(|e| AppError::io(format!("Cannot create dir: {}", e)))?;
+                db::upsert_file_state(db, &synced_dir_state(rel_path, zone))?;
+                self.log_activity("create_local_dir", rel_path, "success", None);
+                Ok(0)
+            }
+
+            SyncAction::CreateRemoteDir { remote_path } => {
+                let remote = remote_path.trim_start_matches('/');
+                log::info!("CreateRemoteDir: {}", remote);
+                transfer.mkcol(remote).await?;
+                db::upsert_file_state(db, &synced_dir_state(rel_path, zone))?;
+                self.log_activity("create_remote_dir", rel_path, "success", None);
+                Ok(0)
+            }
+
+            SyncAction::DeleteLocalDir { local_path } => {
+                let local = Path::new(local_path);
+                if local.exists() {
+                    if !local_dir_is_empty(local) {
+                        log::info!("DeleteLocalDir: {} kept, not empty", local_path);
+                        return Ok(0);
+                    }
+                    log::info!("DeleteLocalDir: {}", local_path);
+                    std::fs::remove_dir_all(local).map_err(|e| {
+                        AppError::io(format!("Cannot remove dir {}: {}", local_path, e))
+                    })?;
+                }
+                db::delete_file_states_under(db, rel_path, zone)?;
+                self.log_activity("delete_local_dir", rel_path, "success", None);
+                Ok(0)
+            }
+
+            SyncAction::DeleteRemoteDir { remote_path } => {
+                let remote = remote_path.trim_start_matches('/');
+                if !ctx.remote_dir_is_empty(remote) {
+                    log::info!("DeleteRemoteDir: {} kept, not empty", remote);
+                    return Ok(0);
+                }
+                log::info!("DeleteRemoteDir: {}", remote);
+                transfer.delete_remote(remote).await?;
+                db::delete_file_states_under(db, rel_path, zone)?;
+                self.log_activity("delete_remote_dir", rel_path, "success", None);
+                Ok(0)
+            }
+
             SyncAction::Skip => Ok(0),
         }
     }
 
-    fn log_activity(&self, action: &str, file_path: &str, status: &str, details: Option<String>) {
-        let entry = ActivityEntry {
-            timestamp: chrono::Utc::now(),
-            action: action.to_string(),
-            file_path: file_path.to_string(),
-            status: status.to_string(),
-            details,
-        };
-
-        let mut log = self.activity_log_guard();
-        log.push(entry);
+    /// Create a new remote file with a WebDAV COPY of a synced file with the same
+    /// content, as long as the server still has the synced version of it, then
+    /// check the copy has the expected size.
+    async fn copy_on_server(
+        &self,
+        ctx: &ZoneContext<'_>,
+        source_state: &FileState,
+        rel_path: &str,
+        local: &Path,
+        local_info: &LocalFileInfo,
+    ) -> AppResult<()> {
+        let source = source_state.path.as_str();
+        log::info!("Copy (remote): {} -> {}", source, rel_path);
+        let synced = etag_precondition(source_state.last_synced_etag.as_deref());
+        ctx.transfer.copy_remote(source, rel_path, synced).await?;
 
-        // Keep only last 1000 entries
-        if log.len() > 1000 {
-            let excess = log.len() - 1000;
-            log.drain(0..excess);
+        let copied = fetch_remote_entry(ctx.transfer, rel_path).await?;
+        let size = copied.as_ref().and_then(|entry| entry.size);
+        if size != Some(local_info.size as u64) {
+            let _ = ctx.transfer.delete_remote(rel_path).await;
+            return Err(AppError::sync(format!(
+                "Copy of {} has size {:?}, expected {}",
+                source, size, local_info.size
+            )));
         }
-    }
 
-    /// Get the current sync status.
-    pub fn get_status(&self) -> SyncStatus {
-        self.status_guard().clone()
+        let version = RemoteVersion {
+            etag: copied.as_ref().and_then(|entry| entry.etag.clone()),
+            mtime: copied
+                .as_ref()
+                .and_then(|entry| entry.last_modified.as_deref())
+                .and_then(parse_http_date),
+            size: local_info.size as u64,
+        };
+        let state = build_synced_state(rel_path, ctx.zone, local, version).await?;
+        db::upsert_file_state(ctx.db, &state)?;
+
+        self.log_activity(
+            "upload",
+            rel_path,
+            "success",
+            Some(format!("server-side copy of {}", source)),
+        );
+        Ok(())
     }
 
-    /// Get recent activity entries.
-    pub fn get_activity(&self, limit: usize) -> Vec<ActivityEntry> {
-        let log = self.activity_log_guard();
-        let start = if log.len() > limit {
-            log.len() - limit
+    /// Resolve a file previously recorded as `conflict` with the user's chosen strategy.
+    ///
+    /// On success the `file_state` row is back to `synced`, so the next
+    /// `compute_diff` no longer reports the conflict.
+    pub async fn resolve_conflict(
+        &self,
+        config: &AppConfig,
+        token: &str,
+        zone: &str,
+        rel_path: &str,
+        resolution: ConflictResolution,
+    ) -> AppResult<()> {
+        if self.get_status() == SyncStatus::Syncing {
+            return Err(AppError::sync(
+                "Synchronizacja w toku — spróbuj ponownie po jej zakończeniu",
+            ));
+        }
+        if config.effective_bandwidth().paused {
+            return Err(AppError::sync(SCHEDULE_PAUSED));
+        }
+
+        let (local_base, webdav_url) = match zone {
+            "personal" => (&config.personal_sync_path, config.personal_webdav_url()),
+            "shared" => (&config.shared_sync_path, config.shared_webdav_url()),
+            _ => return Err(AppError::sync(format!("Nieznana strefa: {}", zone))),
+        };
+
+        let db = self.get_db()?;
+        let state = db::get_file_state(&db, rel_path, zone)?
+            .filter(|s| s.sync_status == "conflict")
+            .ok_or_else(|| AppError::sync(format!("Brak konfliktu dla pliku {}", rel_path)))?;
+
+        self.set_bandwidth_limits(config);
+        let transfer = WebDavTransfer::new(&webdav_url, token)
+            .with_db(db.clone())
+            .with_bandwidth_limiter(self.bandwidth.clone())
+            .with_retry_policy(retry_policy(config))
+            .with_chunked_uploads(
+                &config.uploads_webdav_url(),
+                config.chunked_upload_threshold_bytes,
+            );
+        let trash = LocalTrashManager::new(default_trash_dir(), db.clone());
+        let ctx = ZoneContext {
+            db: &db,
+            transfer: &transfer,
+            trash: &trash,
+            zone,
+            local_base,
+            conflict_policy: config.conflict_policy(zone),
+            remote_tree: Mutex::default(),
+        };
+
+        log::info!(
+            "Resolving conflict '{}' in zone '{}' with {:?}",
+            rel_path,
+            zone,
+            resolution
+        );
+        self.apply_resolution(&ctx, rel_path, &state, resolution).await?;
+
+        self.log_activity(
+            "conflict_resolved",
+            rel_path,
+            "success",
+            Some(format!("{:?}", resolution)),
+        );
+
+        if self.get_status() == SyncStatus::Conflict && db::count_conflicts(&db)? == 0 {
+            self.set_status(SyncStatus::Idle);
+        }
+        Ok(())
+    }
+
+    /// If the local and remote copies of a never-synced file have the same content,
+    /// record it as `synced` without transferring it. Compares against the server's
+    /// BLAKE3 checksum when available, otherwise downloads the remote copy to the
+    /// trash staging area and hashes it — up to `MAX_COMPARE_DOWNLOAD_BYTES`, larger
+    /// files stay a conflict. Returns true if the file was linked.
+    async fn link_if_identical(
+        &self,
+        ctx: &ZoneContext<'_>,
+        local: &LocalFileInfo,
+        remote: &RemoteFileInfo,
+    ) -> AppResult<bool> {
+        if remote.size != Some(local.size) {
+            return Ok(false);
+        }
+
+        let remote_hash = match remote.checksum.as_deref().and_then(transfer::blake3_from_checksums) {
+            Some(hash) => hash.to_lowercase(),
+            None if local.size > MAX_COMPARE_DOWNLOAD_BYTES => {
+                log::debug!(
+                    "Not downloading '{}' ({} bytes) to compare it, left to the user",
+                    local.path,
+                    local.size
+                );
+                return Ok(false);
+            }
+            None => {
+                let name = local.path.rsplit('/').next().unwrap_or(&local.path);
+                let staged = ctx.trash.staging_path(name)?;
+                let hashed = match ctx.transfer.download_file(&local.path, &staged).await {
+                    Ok(_) => hash_file_blocking(staged.clone()).await,
+                    Err(e) => Err(e),
+                };
+                let _ = std::fs::remove_file(&staged);
+                hashed?
+            }
+        };
+
+        if remote_hash != local.hash {
+            return Ok(false);
+        }
+
+        let state = FileState {
+            path: local.path.clone(),
+            sync_zone: ctx.zone.to_string(),
+            kind: "file".to_string(),
+            local_hash: Some(local.hash.clone()),
+            local_mtime: Some(local.mtime),
+            local_size: Some(local.size),
+            // Filled in by the next scan, which rehashes the file once
+            local_inode: None,
+            local_ctime: None,
+            local_exists: true,
+            remote_etag: remote.etag.clone(),
+            remote_mtime: remote.mtime,
+            remote_size: remote.size,
+            remote_exists: true,
+            sync_status: "synced".to_string(),
+            last_synced_hash: Some(local.hash.clone()),
+            last_synced_mtime: Some(local.mtime),
+            last_synced_etag: remote.etag.clone(),
+            last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
+            error_message: None,
+            retry_count: 0,
+            next_retry_at: None,
+        };
+        db::upsert_file_state(ctx.db, &state)?;
+
+        log::info!("Linked identical file '{}' in zone '{}'", local.path, ctx.zone);
+        self.log_activity("link_identical", &local.path, "success", None);
+        Ok(true)
+    }
+
+    /// Apply a conflict resolution to one file and mark it `synced` (or forget it
+    /// when the deletion wins). Whichever version is overwritten or removed ends up
+    /// in the local trash — the server version is downloaded there first.
+    /// Returns bytes transferred.
+    async fn apply_resolution(
+        &self,
+        ctx: &ZoneContext<'_>,
+        rel_path: &str,
+        state: &FileState,
+        resolution: ConflictResolution,
+    ) -> AppResult<u64> {
+        let (db, transfer, trash, zone) = (ctx.db, ctx.transfer, ctx.trash, ctx.zone);
+        let local = ctx.local_base.join(rel_path);
+        let local_exists = local.exists();
+        let remote_exists = state.remote_exists;
+
+        match resolution {
+            ConflictResolution::Local if local_exists => {
+                let mut bytes = 0;
+                let precondition = if remote_exists {
+                    bytes += trash_remote_version(ctx, rel_path).await?;
+                    etag_precondition(state.remote_etag.as_deref())
+                } else {
+                    WritePrecondition::Absent
+                };
+                let result = transfer
+                    .upload_file_if(&local, rel_path, precondition)
+                    .await?;
+                let version = RemoteVersion::uploaded(transfer, rel_path, &result).await;
+                let synced = build_synced_state(rel_path, zone, &local, version).await?;
+                db::upsert_file_state(db, &synced)?;
+                Ok(bytes + result.bytes_sent)
+            }
+            ConflictResolution::Local => {
+                // Local deletion wins over the remote modification.
+                let bytes = trash_remote_version(ctx, rel_path).await?;
+                transfer
+                    .delete_remote_if(rel_path, etag_precondition(state.remote_etag.as_deref()))
+                    .await?;
+                db::delete_file_state(db, rel_path, zone)?;
+                Ok(bytes)
+            }
+            ConflictResolution::Remote if remote_exists => {
+                if local_exists {
+                    trash.trash_file(&local, zone)?;
+                }
+                let result = transfer.download_file(rel_path, &local).await?;
+                let version = RemoteVersion::downloaded(transfer, rel_path, &result).await;
+                let synced = build_synced_state(rel_path, zone, &local, version).await?;
+                db::upsert_file_state(db, &synced)?;
+                Ok(result.bytes_received)
+            }
+            ConflictResolution::Remote => {
+                // Remote deletion wins over the local modification.
+                if local_exists {
+                    trash.trash_file(&local, zone)?;
+                }
+                db::delete_file_state(db, rel_path, zone)?;
+                Ok(0)
+            }
+            ConflictResolution::Both => {
+                let mut bytes = 0;
+                if local_exists && remote_exists {
+                    // The copy must be a new name on both sides: it is uploaded with
+                    // If-None-Match: *
+                    let parent = rel_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+                    let remote_names: HashSet<String> = transfer
+                        .propfind(parent)
+                        .await?
+                        .iter()
+                        .map(|entry| relative_remote_path(&entry.path))
+                        .collect();
+                    let copy_rel = conflicted_copy_path(rel_path, |path| {
+                        ctx.local_base.join(path).exists() || remote_names.contains(path)
+                    });
+                    let copy_local = ctx.local_base.join(&copy_rel);
+                    std::fs::rename(&local, &copy_local).map_err(|e| {
+                        AppError::io(format!(
+                            "Cannot rename {} -> {}: {}",
+                            local.display(),
+                            copy_local.display(),
+                            e
+                        ))
+                    })?;
+
+                    let uploaded = transfer
+                        .upload_file_if(&copy_local, &copy_rel, WritePrecondition::Absent)
+                        .await?;
+                    let copy_version = RemoteVersion::uploaded(transfer, &copy_rel, &uploaded).await;
+                    let copy_state =
+                        build_synced_state(&copy_rel, zone, &copy_local, copy_version).await?;
+                    db::upsert_file_state(db, &copy_state)?;
+                    self.log_activity("upload", &copy_rel, "success", None);
+                    bytes += uploaded.bytes_sent;
+                }
+
+                if remote_exists {
+                    let result = transfer.download_file(rel_path, &local).await?;
+                    let version = RemoteVersion::downloaded(transfer, rel_path, &result).await;
+                    let synced = build_synced_state(rel_path, zone, &local, version).await?;
+                    db::upsert_file_state(db, &synced)?;
+                    bytes += result.bytes_received;
+                } else {
+                    // Only the local side is left — restore it on the server.
+                    let result = transfer.upload_file(&local, rel_path).await?;
+                    let version = RemoteVersion::uploaded(transfer, rel_path, &result).await;
+                    let synced = build_synced_state(rel_path, zone, &local, version).await?;
+                    db::upsert_file_state(db, &synced)?;
+                    bytes += result.bytes_sent;
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Mark a file that exceeds the zone's size limit as `skipped`, keeping its
+    /// last-synced baseline so it syncs normally once the limit is raised.
+    /// Logs to the activity log only when the file is newly skipped.
+    fn record_too_large(
+        &self,
+        db: &DbPool,
+        zone: &str,
+        path: &str,
+        local_size: Option<i64>,
+        remote_size: Option<i64>,
+        max_file_size: u64,
+    ) -> AppResult<()> {
+        let existing = db::get_file_state(db, path, zone)?;
+        let newly_skipped = existing.as_ref().is_none_or(|s| s.sync_status != "skipped");
+
+        let mut state = existing.unwrap_or_else(|| FileState {
+            path: path.to_string(),
+            sync_zone: zone.to_string(),
+            kind: "file".to_string(),
+            local_hash: None,
+            local_mtime: None,
+            local_size: None,
+            local_inode: None,
+            local_ctime: None,
+            local_exists: false,
+            remote_etag: None,
+            remote_mtime: None,
+            remote_size: None,
+            remote_exists: false,
+            sync_status: String::new(),
+            last_synced_hash: None,
+            last_synced_mtime: None,
+            last_synced_etag: None,
+            last_synced_at: None,
+            error_message: None,
+            retry_count: 0,
+            next_retry_at: None,
+        });
+        let size = local_size.max(remote_size).unwrap_or(0);
+        let details = format!(
+            "skipped: too large ({} bytes, limit {} bytes)",
+            size, max_file_size
+        );
+
+        state.local_exists = local_size.is_some();
+        state.local_size = local_size;
+        state.remote_exists = remote_size.is_some();
+        state.remote_size = remote_size;
+        state.sync_status = "skipped".to_string();
+        state.error_message = Some(details.clone());
+        db::upsert_file_state(db, &state)?;
+
+        if newly_skipped {
+            log::warn!("Zone '{}': skipping {}: {}", zone, path, details);
+            self.log_activity("skipped", path, "skipped", Some(details));
+        }
+        Ok(())
+    }
+
+    /// Remove partial downloads that cannot be resumed (untracked, or not touched
+    /// for a week) from the given sync roots. Runs before the first sync of the
+    /// app run, so no download is resuming them yet.
+    async fn cleanup_partial_downloads(&self, roots: Vec<PathBuf>) -> AppResult<()> {
+        let db = self.get_db()?;
+        let resumable: HashSet<PathBuf> = db::prune_partial_downloads(&db, 7)?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        tokio::task::spawn_blocking(move || {
+            for root in roots.iter().filter(|p| p.is_dir()) {
+                let removed = remove_partial_downloads(root, &resumable);
+                if removed > 0 {
+                    log::info!("Removed {} partial download(s) from {}", removed, root.display());
+                }
+            }
+        })
+        .await
+        .map_err(|e| AppError::internal(format!("Partial download cleanup failed: {}", e)))
+    }
+
+    /// List files that are still waiting for conflict resolution.
+    pub fn list_conflicts(&self) -> AppResult<Vec<db::ConflictEntry>> {
+        let db = self.get_db()?;
+        db::list_conflicts(&db)
+    }
+
+    fn log_activity(&self, action: &str, file_path: &str, status: &str, details: Option<String>) {
+        let entry = ActivityEntry {
+            timestamp: chrono::Utc::now(),
+            action: action.to_string(),
+            file_path: file_path.to_string(),
+            status: status.to_string(),
+            details,
+        };
+
+        let mut log = self.activity_log_guard();
+        log.push(entry);
+
+        // Keep only last 1000 entries
+        if log.len() > 1000 {
+            let excess = log.len() - 1000;
+            log.drain(0..excess);
+        }
+    }
+
+    /// Get the current sync status.
+    pub fn get_status(&self) -> SyncStatus {
+        self.status_guard().clone()
+    }
+
+    /// Get recent activity entries.
+    pub fn get_activity(&self, limit: usize) -> Vec<ActivityEntry> {
+        let log = self.activity_log_guard();
+        let start = if log.len() > limit {
+            log.len() - limit
         } else {
             0
         };
@@ -623,12 +1646,29 @@ impl SyncEngine {
     pub fn pause(&self) {
         let current = self.get_status();
         // Only pause if idle or syncing (not if already paused or not configured)
-        if matches!(current, SyncStatus::Idle | SyncStatus::Syncing | SyncStatus::Error(_)) {
+        if matches!(
+            current,
+            SyncStatus::Idle | SyncStatus::Syncing | SyncStatus::Conflict | SyncStatus::Error(_)
+        ) {
             self.set_status(SyncStatus::Paused);
             log::info!("Sync paused by user");
         }
     }
 
+    /// Cancel the sync run in progress, also one paused meanwhile: transfers are
+    /// aborted, partial downloads removed and the run recorded as `cancelled`.
+    /// Returns false if nothing is running.
+    pub fn cancel(&self) -> bool {
+        match self.cancel_guard().as_ref() {
+            Some(cancel) => {
+                cancel.cancel();
+                log::info!("Sync cancelled by user");
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Resume sync — scheduler will resume normal operation.
     pub fn resume(&self) {
         if self.get_status() == SyncStatus::Paused {
@@ -637,11 +1677,56 @@ impl SyncEngine {
         }
     }
 
+    /// Make the next sync run rehash every local file instead of trusting the
+    /// stat cache.
+    pub fn request_full_verify(&self) {
+        self.full_verify_requested_guard()
+            .extend(["personal", "shared"]);
+    }
+
+    /// Whether this run should rehash everything in a zone: on request, or once
+    /// `full_verify_interval_hours` have passed since its last full verify (a zone
+    /// never verified is due at once).
+    fn full_verify_due(&self, db: &DbPool, config: &AppConfig, zone: &str) -> bool {
+        let due = self.full_verify_requested_guard().contains(zone) || {
+            let interval = config.full_verify_interval_hours as i64 * 3600;
+            config.full_verify_interval_hours > 0
+                && match db::get_last_full_verify(db, zone) {
+                    Ok(Some(verified_at)) => {
+                        chrono::Utc::now().timestamp() - verified_at >= interval
+                    }
+                    Ok(None) => true,
+                    Err(e) => {
+                        log::warn!("Cannot read last full verify of zone '{}': {}", zone, e);
+                        false
+                    }
+                }
+        };
+        if due {
+            log::info!("Full verify: rehashing all local files of zone '{}'", zone);
+        }
+        due
+    }
+
+    /// Record a full verify of `zone` that got through.
+    fn full_verify_done(&self, db: &DbPool, zone: &str) {
+        self.full_verify_requested_guard().remove(zone);
+        if let Err(e) = db::set_last_full_verify(db, zone, chrono::Utc::now().timestamp()) {
+            log::warn!("Cannot record full verify of zone '{}': {}", zone, e);
+        }
+    }
+
     /// Check if sync is paused.
     pub fn is_paused(&self) -> bool {
         self.get_status() == SyncStatus::Paused
     }
 
+    /// Whether a sync run is in progress, whatever the status shows (a run paused
+    /// meanwhile goes on until its transfers are done).
+    pub fn is_running(&self) -> bool {
+        self.cancel_guard().is_some()
+    }
+
     fn set_error_status(&self, error: String) -> AppError {
         self.set_status(SyncStatus::Error(error.clone()));
         AppError::sync(error)
@@ -657,6 +1742,18 @@ impl SyncEngine {
             .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 
+    fn cancel_guard(&self) -> MutexGuard<'_, Option<CancellationToken>> {
+        self.cancel
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn full_verify_requested_guard(&self) -> MutexGuard<'_, HashSet<&'static str>> {
+        self.full_verify_requested
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     fn activity_log_guard(&self) -> MutexGuard<'_, Vec<ActivityEntry>> {
         self.activity_log
             .lock()
@@ -666,31 +1763,270 @@ impl SyncEngine {
 
 // ==================== Local File Scanning ====================
 
-const MAX_FILE_SIZE: u64 = 500 * 1024 * 1024; // 500 MB
+/// Result of a local scan.
+struct LocalScan {
+    files: Vec<LocalFileInfo>,
+    /// Relative paths of all folders, including empty ones
+    dirs: Vec<String>,
+    /// Files above the size limit, as (relative path, size); these are not hashed
+    too_large: Vec<(String, i64)>,
+    /// Dotfiles and dot-folders: not synced, but still content of their folder
+    hidden: Vec<String>,
+    /// Synced files rehashed with an unchanged hash but a new stat tuple
+    refreshed_stats: Vec<(String, db::LocalStat)>,
+}
+
+/// A regular file found by the local walk, not hashed yet.
+struct WalkedFile {
+    rel_path: String,
+    path: PathBuf,
+    stat: db::LocalStat,
+}
+
+/// Everything the local walk found, before hashing.
+struct LocalWalk {
+    files: Vec<WalkedFile>,
+    dirs: Vec<String>,
+    too_large: Vec<(String, i64)>,
+    hidden: Vec<String>,
+}
 
 /// Recursively scan local directory, hashing files with blake3.
-/// Skips dotfiles and files > 500MB.
-fn scan_local_files(base: &Path) -> AppResult<Vec<LocalFileInfo>> {
-    let mut files = Vec::new();
+/// Skips dotfiles; files above `max_file_size` (0 = unlimited) are reported separately.
+/// Files whose stat tuple matches their `stat_cache` row reuse the stored hash;
+/// without a cache (full verify) every file is hashed. The walk and the hashing
+/// run on the blocking thread pool, so the async runtime is never stalled.
+async fn scan_local_files(
+    base: &Path,
+    max_file_size: u64,
+    stat_cache: Option<&HashMap<&str, &FileState>>,
+) -> AppResult<LocalScan> {
+    let root = base.to_path_buf();
+    let walk = tokio::task::spawn_blocking(move || walk_local_tree(&root, max_file_size))
+        .await
+        .map_err(|e| AppError::internal(format!("Local scan task failed: {}", e)))?;
+    Ok(hash_local_walk(base, walk, stat_cache).await)
+}
+
+/// Synced file rows by path, for `scan_local_files` to skip unchanged files.
+fn file_stat_cache(known_rows: &[FileState]) -> HashMap<&str, &FileState> {
+    known_rows
+        .iter()
+        .filter(|s| !s.is_dir())
+        .map(|s| (s.path.as_str(), s))
+        .collect()
+}
+
+/// Hash the files found by a walk, reusing stored hashes of unchanged files.
+/// Files that can't be read are left out (and so retried by the next run).
+async fn hash_local_walk(
+    base: &Path,
+    walk: LocalWalk,
+    stat_cache: Option<&HashMap<&str, &FileState>>,
+) -> LocalScan {
+    let mut scan = LocalScan {
+        files: Vec::with_capacity(walk.files.len()),
+        dirs: walk.dirs,
+        too_large: walk.too_large,
+        hidden: walk.hidden,
+        refreshed_stats: Vec::new(),
+    };
+
+    // Unchanged files keep their stored hash; the rest is hashed, a few at a time
+    let mut to_hash = Vec::new();
+    for file in walk.files {
+        let cached = stat_cache.and_then(|cache| cache.get(file.rel_path.as_str()).copied());
+        match cached.and_then(|state| state.cached_hash(&file.stat)) {
+            Some(hash) => scan.files.push(LocalFileInfo {
+                hash: hash.to_string(),
+                path: file.rel_path,
+                mtime: file.stat.mtime,
+                size: file.stat.size,
+            }),
+            None => {
+                let previous_hash = cached.and_then(|state| state.local_hash.clone());
+                to_hash.push((file, previous_hash));
+            }
+        }
+    }
+    let hashed = to_hash.len();
+
+    let results: Vec<_> = stream::iter(to_hash)
+        .map(|(file, previous_hash)| async move {
+            let hash = hash_file_blocking(file.path.clone()).await;
+            (file, previous_hash, hash)
+        })
+        .buffer_unordered(MAX_CONCURRENT_HASHERS)
+        .collect()
+        .await;
+
+    for (file, previous_hash, hash) in results {
+        let hash = match hash {
+            Ok(h) => h,
+            Err(e) => {
+                log::warn!("Cannot hash {}: {}", file.path.display(), e);
+                continue;
+            }
+        };
+        if previous_hash.as_deref() == Some(hash.as_str()) {
+            scan.refreshed_stats.push((file.rel_path.clone(), file.stat.clone()));
+        }
+        scan.files.push(LocalFileInfo {
+            path: file.rel_path,
+            hash,
+            mtime: file.stat.mtime,
+            size: file.stat.size,
+        });
+    }
+
+    log::debug!(
+        "Local scan of {}: {} files, {} hashed",
+        base.display(),
+        scan.files.len(),
+        hashed
+    );
+    scan
+}
+
+/// Scan only the given paths of a zone, on both sides. Returns None when one of
+/// them is (or was) a folder: what changed under it is unknown, so the caller
+/// falls back to a full scan.
+async fn scan_touched_paths(
+    transfer: &WebDavTransfer,
+    local_base: &Path,
+    paths: &[String],
+    known_rows: &[FileState],
+    max_file_size: u64,
+) -> AppResult<Option<(LocalScan, RemoteScan)>> {
+    let known_dirs: HashSet<&str> = known_rows
+        .iter()
+        .filter(|s| s.is_dir())
+        .map(|s| s.path.as_str())
+        .collect();
+    if paths.iter().any(|p| known_dirs.contains(p.as_str())) {
+        return Ok(None);
+    }
+
+    // Dotfiles (in-flight downloads among them) are not synced on either side
+    let paths: Vec<String> = paths
+        .iter()
+        .filter(|p| !p.split('/').any(|c| c.starts_with('.')))
+        .cloned()
+        .collect();
+
+    let root = local_base.to_path_buf();
+    let owned_paths = paths.clone();
+    let walk = tokio::task::spawn_blocking(move || {
+        stat_local_paths(&root, &owned_paths, max_file_size)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Local scan task failed: {}", e)))?;
+    let Some(walk) = walk else {
+        return Ok(None);
+    };
+
+    let Some(remote_files) = scan_remote_paths(transfer, &paths).await else {
+        return Ok(None);
+    };
+
+    let stat_cache = file_stat_cache(known_rows);
+    let local_scan = hash_local_walk(local_base, walk, Some(&stat_cache)).await;
+    let remote_scan = RemoteScan {
+        files: remote_files,
+        dirs: Vec::new(),
+        hidden: Vec::new(),
+    };
+    Ok(Some((local_scan, remote_scan)))
+}
+
+/// Stat the given paths like `walk_local_tree` would; missing ones are left out.
+/// Returns None if one of them is a folder. Blocking.
+fn stat_local_paths(base: &Path, paths: &[String], max_file_size: u64) -> Option<LocalWalk> {
+    let mut walk = LocalWalk {
+        files: Vec::new(),
+        dirs: Vec::new(),
+        too_large: Vec::new(),
+        hidden: Vec::new(),
+    };
+
+    for rel_path in paths {
+        let path = base.join(rel_path);
+        let metadata = match std::fs::metadata(&path) {
+            Ok(m) => m,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                log::warn!("Cannot stat {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if metadata.is_dir() {
+            // Links to folders are skipped, as by the full walk
+            if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) {
+                continue;
+            }
+            return None;
+        }
+
+        if max_file_size > 0 && metadata.len() > max_file_size {
+            walk.too_large.push((rel_path.clone(), metadata.len() as i64));
+            continue;
+        }
+
+        walk.files.push(WalkedFile {
+            rel_path: rel_path.clone(),
+            path,
+            stat: local_stat(&metadata),
+        });
+    }
+
+    Some(walk)
+}
+
+/// Walk the local tree: list folders, stat files, and set aside files above the
+/// size limit and dotfiles. Blocking.
+fn walk_local_tree(base: &Path, max_file_size: u64) -> LocalWalk {
+    let mut walk = LocalWalk {
+        files: Vec::new(),
+        dirs: Vec::new(),
+        too_large: Vec::new(),
+        hidden: Vec::new(),
+    };
 
     if !base.exists() {
-        return Ok(files);
+        return walk;
     }
 
-    for entry in walkdir::WalkDir::new(base)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
+    let mut entries = walkdir::WalkDir::new(base).follow_links(false).into_iter();
+    while let Some(entry) = entries.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
         let path = entry.path();
 
-        // Skip directories
-        if path.is_dir() {
+        // Dotfiles (any path component starting with '.') are only recorded, and
+        // dot-folders not descended into; in-flight downloads are skipped
+        if has_dotfile_component(path, base) {
+            if entry.file_type().is_dir() {
+                entries.skip_current_dir();
+            }
+            if !is_partial_download(path) {
+                if let Ok(rel) = path.strip_prefix(base) {
+                    walk.hidden.push(rel.to_string_lossy().replace('\\', "/"));
+                }
+            }
             continue;
         }
 
-        // Skip dotfiles (any path component starting with '.')
-        if has_dotfile_component(path, base) {
+        // Folders are only listed (links to folders are skipped)
+        if path.is_dir() {
+            if entry.file_type().is_dir() {
+                if let Ok(rel) = path.strip_prefix(base) {
+                    let rel_path = rel.to_string_lossy().replace('\\', "/");
+                    if !rel_path.is_empty() {
+                        walk.dirs.push(rel_path);
+                    }
+                }
+            }
             continue;
         }
 
@@ -702,12 +2038,6 @@ fn scan_local_files(base: &Path) -> AppResult<Vec<LocalFileInfo>> {
             }
         };
 
-        // Skip files > 500MB
-        if metadata.len() > MAX_FILE_SIZE {
-            log::warn!("Skipping large file (>500MB): {}", path.display());
-            continue;
-        }
-
         // Compute relative path
         let rel_path = match path.strip_prefix(base) {
             Ok(p) => p.to_string_lossy().replace('\\', "/"),
@@ -718,25 +2048,19 @@ fn scan_local_files(base: &Path) -> AppResult<Vec<LocalFileInfo>> {
             continue;
         }
 
-        let hash = match hash_file(path) {
-            Ok(h) => h,
-            Err(e) => {
-                log::warn!("Cannot hash {}: {}", path.display(), e);
-                continue;
-            }
-        };
-
-        let mtime = mtime_from_metadata(&metadata);
+        if max_file_size > 0 && metadata.len() > max_file_size {
+            walk.too_large.push((rel_path, metadata.len() as i64));
+            continue;
+        }
 
-        files.push(LocalFileInfo {
-            path: rel_path,
-            hash,
-            mtime,
-            size: metadata.len() as i64,
+        walk.files.push(WalkedFile {
+            rel_path,
+            path: path.to_path_buf(),
+            stat: local_stat(&metadata),
         });
     }
 
-    Ok(files)
+    walk
 }
 
 /// Returns true if any component of `path` (relative to `base`) starts with '.'.
@@ -753,16 +2077,79 @@ fn has_dotfile_component(path: &Path, base: &Path) -> bool {
     false
 }
 
+fn is_partial_download(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| transfer::is_partial_file(&name.to_string_lossy()))
+}
+
+/// Remove partial download files under `base` left behind by a crash or kill
+/// mid-download, except those whose target is in `resumable`. Returns how many were removed.
+fn remove_partial_downloads(base: &Path, resumable: &HashSet<PathBuf>) -> usize {
+    let mut removed = 0;
+    for entry in walkdir::WalkDir::new(base)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !is_partial_download(path) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let target_name = name
+            .trim_start_matches('.')
+            .trim_end_matches(transfer::PARTIAL_SUFFIX);
+        if resumable.contains(&path.with_file_name(target_name)) {
+            continue;
+        }
+        match std::fs::remove_file(path) {
+            Ok(()) => removed += 1,
+            Err(e) => log::warn!("Cannot remove partial download {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
 /// Compute blake3 hash of a file, returned as hex string.
 fn hash_file(path: &Path) -> AppResult<String> {
-    let bytes = std::fs::read(path).map_err(|e| {
+    use std::io::Read;
+
+    let read_error = |e: std::io::Error| {
         AppError::io(format!(
             "Cannot read file for hashing {}: {}",
             path.display(),
             e
         ))
-    })?;
-    Ok(blake3::hash(&bytes).to_hex().to_string())
+    };
+
+    // Streamed rather than memory-mapped: a file truncated while mapped would
+    // crash the process with SIGBUS, and files in the sync folders change anytime
+    let mut file = std::fs::File::open(path).map_err(read_error)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; HASH_BUFFER_SIZE];
+    loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_error(e)),
+        };
+        if n >= HASH_RAYON_MIN_BYTES {
+            hasher.update_rayon(&buf[..n]);
+        } else {
+            hasher.update(&buf[..n]);
+        }
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hash a file on the blocking thread pool, at most `MAX_CONCURRENT_HASHERS`
+/// files at a time across the whole app.
+async fn hash_file_blocking(path: PathBuf) -> AppResult<String> {
+    let _slot = HASH_SLOTS.acquire().await.expect("semaphore is never closed");
+    tokio::task::spawn_blocking(move || hash_file(&path))
+        .await
+        .map_err(|e| AppError::internal(format!("Hashing task failed: {}", e)))?
 }
 
 /// Extract modification time as unix timestamp from file metadata.
@@ -775,30 +2162,565 @@ fn mtime_from_metadata(metadata: &std::fs::Metadata) -> i64 {
         .unwrap_or(0)
 }
 
-/// Convert an empty string to None.
-fn non_empty_str(s: &str) -> Option<String> {
-    if s.is_empty() {
-        None
-    } else {
-        Some(s.to_string())
+/// Stat tuple for the stat cache. The ctime (in ns) changes on every write, even
+/// one that keeps size and mtime, and the inode changes when a file is replaced.
+fn local_stat(metadata: &std::fs::Metadata) -> db::LocalStat {
+    #[cfg(unix)]
+    let (inode, ctime) = {
+        use std::os::unix::fs::MetadataExt;
+        (
+            Some(metadata.ino() as i64),
+            Some(metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec()),
+        )
+    };
+    #[cfg(not(unix))]
+    let (inode, ctime) = (None, None);
+
+    db::LocalStat {
+        size: metadata.len() as i64,
+        mtime: mtime_from_metadata(metadata),
+        inode,
+        ctime,
+    }
+}
+
+/// True if the folder holds nothing but (empty) subfolders — hidden files count
+/// as content, so they are never removed along with the folder.
+fn local_dir_is_empty(dir: &Path) -> bool {
+    walkdir::WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .all(|entry| entry.is_ok_and(|e| e.file_type().is_dir()))
+}
+
+impl ZoneContext<'_> {
+    fn remote_tree_lock(&self) -> MutexGuard<'_, BTreeSet<String>> {
+        self.remote_tree
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// True if the remote folder holds nothing but (empty) subfolders by now.
+    fn remote_dir_is_empty(&self, dir: &str) -> bool {
+        let prefix = format!("{}/", dir.trim_matches('/'));
+        !self
+            .remote_tree_lock()
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|path| path.starts_with(&prefix))
+    }
+
+    /// Record a remote file or folder moved from `source` to `destination`.
+    fn moved_remote(&self, source: &str, destination: &str) {
+        let mut tree = self.remote_tree_lock();
+        let prefix = format!("{}/", source);
+        let moved: Vec<String> = tree
+            .range(source.to_string()..)
+            .take_while(|path| path.as_str() == source || path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for path in moved {
+            tree.remove(&path);
+            tree.insert(format!("{}{}", destination, &path[source.len()..]));
+        }
+    }
+}
+
+/// `synced` row for a folder that now exists on both sides.
+fn synced_dir_state(path: &str, zone: &str) -> FileState {
+    FileState {
+        path: path.to_string(),
+        sync_zone: zone.to_string(),
+        kind: "dir".to_string(),
+        local_hash: None,
+        local_mtime: None,
+        local_size: None,
+        local_inode: None,
+        local_ctime: None,
+        local_exists: true,
+        remote_etag: None,
+        remote_mtime: None,
+        remote_size: None,
+        remote_exists: true,
+        sync_status: "synced".to_string(),
+        last_synced_hash: None,
+        last_synced_mtime: None,
+        last_synced_etag: None,
+        last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
+        error_message: None,
+        retry_count: 0,
+        next_retry_at: None,
+    }
+}
+
+/// Rename a local file or folder into place for a move made on the server.
+/// An existing destination is never replaced.
+fn move_local(source: &Path, destination: &Path) -> AppResult<()> {
+    if destination.exists() {
+        return Err(AppError::sync(format!(
+            "Cannot move {}: {} already exists",
+            source.display(),
+            destination.display()
+        )));
+    }
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(format!("Cannot create dir: {}", e)))?;
+    }
+    std::fs::rename(source, destination).map_err(|e| {
+        AppError::io(format!(
+            "Cannot move {} to {}: {}",
+            source.display(),
+            destination.display(),
+            e
+        ))
+    })
+}
+
+/// Run the actions of one zone on the upload/download slots. Moves go first, so
+/// folders they land in are not created by an upload before them; then transfers
+/// run in parallel, then conflicts, then deletions, so nothing is removed while a
+/// transfer it may depend on is still running. Folders are removed last, once the
+/// files in them are gone. Remote folders are created at the start of each phase.
+async fn run_actions<'a>(
+    actions: &'a [DiffResult],
+    slots: &TransferSlots,
+    parallelism: usize,
+    executor: &impl ActionExecutor,
+) -> Vec<(&'a DiffResult, AppResult<Option<u64>>)> {
+    let phases: [fn(&SyncAction) -> bool; 5] = [
+        |a| matches!(a, SyncAction::Move { .. }),
+        |a| {
+            matches!(
+                a,
+                SyncAction::Upload { .. }
+                    | SyncAction::Download { .. }
+                    | SyncAction::CreateLocalDir { .. }
+                    | SyncAction::CreateRemoteDir { .. }
+            )
+        },
+        |a| matches!(a, SyncAction::Conflict { .. }),
+        |a| {
+            matches!(
+                a,
+                SyncAction::DeleteLocal { .. } | SyncAction::DeleteRemote { .. }
+            )
+        },
+        |a| {
+            matches!(
+                a,
+                SyncAction::DeleteLocalDir { .. } | SyncAction::DeleteRemoteDir { .. }
+            )
+        },
+    ];
+
+    let mut outcomes = Vec::with_capacity(actions.len());
+    for in_phase in phases {
+        let phase: Vec<&DiffResult> = actions.iter().filter(|r| in_phase(&r.action)).collect();
+        executor.create_remote_parents(&phase).await;
+        let tasks: Vec<_> = actions
+            .iter()
+            .filter(|r| in_phase(&r.action))
+            .map(|r| run_in_slot(executor, slots, r))
+            .collect();
+        let done: Vec<_> = stream::iter(tasks)
+            .buffer_unordered(parallelism)
+            .collect()
+            .await;
+        outcomes.extend(done);
+    }
+    outcomes
+}
+
+/// Wait for a free upload/download slot, report progress, then run the action.
+async fn run_in_slot<'a>(
+    executor: &impl ActionExecutor,
+    slots: &TransferSlots,
+    diff_result: &'a DiffResult,
+) -> (&'a DiffResult, AppResult<Option<u64>>) {
+    let semaphore = match diff_result.action {
+        SyncAction::Upload { .. }
+        | SyncAction::DeleteRemote { .. }
+        | SyncAction::CreateRemoteDir { .. }
+        | SyncAction::DeleteRemoteDir { .. }
+        | SyncAction::Move {
+            side: MoveSide::Remote,
+            ..
+        } => &slots.uploads,
+        _ => &slots.downloads,
+    };
+    let _permit = semaphore
+        .acquire()
+        .await
+        .expect("semaphore is never closed");
+    if slots.cancel.is_cancelled() {
+        return (diff_result, Err(AppError::cancelled()));
+    }
+
+    let current = slots.started.fetch_add(1, Ordering::Relaxed) + 1;
+    executor.started(diff_result, current, slots.total);
+    (diff_result, executor.execute(diff_result).await)
+}
+
+/// Count a finished action in the statistics of the run.
+fn count_done(stats: &mut SyncRunStats, action: &SyncAction, bytes: u64) {
+    match action {
+        SyncAction::Upload { .. } => {
+            stats.files_uploaded += 1;
+            stats.bytes_transferred += bytes as i64;
+        }
+        SyncAction::Download { .. } => {
+            stats.files_downloaded += 1;
+            stats.bytes_transferred += bytes as i64;
+        }
+        SyncAction::DeleteLocal { .. } | SyncAction::DeleteRemote { .. } => {
+            stats.files_deleted += 1;
+        }
+        SyncAction::Conflict { .. } => {
+            stats.files_conflicted += 1;
+            stats.bytes_transferred += bytes as i64;
+        }
+        SyncAction::Move { .. }
+        | SyncAction::CreateLocalDir { .. }
+        | SyncAction::CreateRemoteDir { .. }
+        | SyncAction::DeleteLocalDir { .. }
+        | SyncAction::DeleteRemoteDir { .. }
+        | SyncAction::Skip => {}
+    }
+}
+
+/// The remote parent folders of all uploads and remote moves, shallowest first,
+/// to be created before the parallel transfers start.
+fn remote_parents<'a>(actions: &[&'a DiffResult]) -> Vec<&'a str> {
+    let mut parents: Vec<&str> = actions
+        .iter()
+        .filter_map(|r| match &r.action {
+            SyncAction::Upload {
+                remote_path: path, ..
+            }
+            | SyncAction::CreateRemoteDir { remote_path: path }
+            | SyncAction::Move {
+                destination: path,
+                side: MoveSide::Remote,
+                ..
+            } => path
+                .trim_start_matches('/')
+                .rsplit_once('/')
+                .map(|(parent, _)| parent),
+            _ => None,
+        })
+        .filter(|parent| !parent.is_empty())
+        .collect();
+    parents.sort_by_key(|p| (p.matches('/').count(), *p));
+    parents.dedup();
+    parents
+}
+
+/// Build a `synced` FileState from the local file as it is on disk right now.
+async fn build_synced_state(
+    rel_path: &str,
+    zone: &str,
+    local: &Path,
+    remote: RemoteVersion,
+) -> AppResult<FileState> {
+    let metadata = std::fs::metadata(local)
+        .map_err(|e| AppError::io(format!("Cannot stat {}: {}", local.display(), e)))?;
+    let hash = hash_file_blocking(local.to_path_buf()).await?;
+    let stat = local_stat(&metadata);
+    let mtime = stat.mtime;
+
+    Ok(FileState {
+        path: rel_path.to_string(),
+        sync_zone: zone.to_string(),
+        kind: "file".to_string(),
+        local_hash: Some(hash.clone()),
+        local_mtime: Some(mtime),
+        local_size: Some(stat.size),
+        local_inode: stat.inode,
+        local_ctime: stat.ctime,
+        local_exists: true,
+        remote_etag: remote.etag.clone(),
+        remote_mtime: remote.mtime,
+        remote_size: Some(remote.size as i64),
+        remote_exists: true,
+        sync_status: "synced".to_string(),
+        last_synced_hash: Some(hash),
+        last_synced_mtime: Some(mtime),
+        last_synced_etag: remote.etag,
+        last_synced_at: Some(chrono::Utc::now().to_rfc3339()),
+        error_message: None,
+        retry_count: 0,
+        next_retry_at: None,
+    })
+}
+
+/// Build a "conflicted copy" path next to the original:
+/// `dir/report.pdf` → `dir/report (conflict HOST 2026-04-09 1530).pdf`.
+/// `taken` tells whether a path is already in use.
+fn conflicted_copy_path(rel_path: &str, taken: impl Fn(&str) -> bool) -> String {
+    let host = tauri_plugin_os::hostname();
+    let date = chrono::Local::now().format("%Y-%m-%d %H%M");
+    tagged_copy_path(rel_path, &format!("conflict {} {}", host, date), taken)
+}
+
+/// `dir/report.pdf` → `dir/report (TAG).pdf`, then `dir/report (TAG 2).pdf`
+/// and so on while the name is taken.
+fn tagged_copy_path(rel_path: &str, tag: &str, taken: impl Fn(&str) -> bool) -> String {
+    let (dir, name) = match rel_path.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, rel_path),
+    };
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+
+    let mut counter = 1;
+    loop {
+        let copy_name = if counter == 1 {
+            format!("{} ({}){}", stem, tag, ext)
+        } else {
+            format!("{} ({} {}){}", stem, tag, counter, ext)
+        };
+        let copy_path = match dir {
+            Some(dir) => format!("{}/{}", dir, copy_name),
+            None => copy_name,
+        };
+        if !taken(&copy_path) {
+            return copy_path;
+        }
+        counter += 1;
     }
 }
 
 // ==================== Remote File Scanning ====================
 
+/// Result of a remote scan.
+struct RemoteScan {
+    files: Vec<RemoteFileInfo>,
+    /// Relative paths of all folders, including empty ones
+    dirs: Vec<String>,
+    /// Dotfiles and dot-folders: not synced, but still content of their folder
+    hidden: Vec<String>,
+}
+
+/// Rounds of a truncated sync-collection result followed within one scan.
+const MAX_SYNC_COLLECTION_ROUNDS: usize = 20;
+
+/// List the remote tree. Servers supporting the sync-collection REPORT only send
+/// what changed since the last run, which is applied to the snapshot kept in
+/// SQLite; others (or a failed REPORT) get the PROPFIND walk, which skips folders
+/// whose change tag is the same as last time unless `verify` is set.
+async fn scan_remote(
+    transfer: &WebDavTransfer,
+    db: &DbPool,
+    zone: &str,
+    webdav_url: &str,
+    verify: bool,
+) -> AppResult<RemoteScan> {
+    match update_remote_snapshot(transfer, db, zone, webdav_url, verify).await {
+        Ok(true) => {
+            let entries = db::list_remote_entries(db, zone)?;
+            log::debug!("Zone '{}': remote listing from sync-collection snapshot", zone);
+            return Ok(remote_scan_from_snapshot(entries));
+        }
+        Ok(false) => {}
+        Err(e) => log::warn!("Zone '{}': sync-collection failed: {}", zone, e),
+    }
+
+    let cache = if verify {
+        None
+    } else {
+        Some(RemoteTreeCache {
+            dir_tags: db::list_remote_dir_tags(db, zone)?.into_iter().collect(),
+            entries: db::list_remote_entries(db, zone)?
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+        })
+    };
+    let walk = scan_remote_files(transfer, cache.as_ref()).await?;
+    log::debug!(
+        "Zone '{}': remote walk listed {} folders, reused {}",
+        zone,
+        walk.listed,
+        walk.reused
+    );
+    // The tags of folders above one that failed to list would vouch for missing entries
+    let dir_tags = if walk.incomplete { &[][..] } else { &walk.dir_tags[..] };
+    if let Err(e) = db::save_remote_walk(db, zone, &walk.entries, dir_tags) {
+        log::warn!("Zone '{}': cannot store remote listing: {}", zone, e);
+    }
+    Ok(remote_scan_from_snapshot(walk.entries))
+}
+
+/// Bring the remote snapshot of a zone up to date with sync-collection. Returns
+/// false when it could not be (the caller then walks the tree instead). A server
+/// known not to support it is asked again only on `verify` runs.
+async fn update_remote_snapshot(
+    transfer: &WebDavTransfer,
+    db: &DbPool,
+    zone: &str,
+    webdav_url: &str,
+    verify: bool,
+) -> AppResult<bool> {
+    let mut token = db::get_sync_token(db, zone, webdav_url)?;
+    if token.as_deref() == Some("") {
+        if !verify {
+            return Ok(false);
+        }
+        token = None;
+    }
+
+    for _ in 0..MAX_SYNC_COLLECTION_ROUNDS {
+        let changes = match transfer.sync_collection(token.as_deref()).await? {
+            transfer::SyncCollection::Changes(changes) => changes,
+            transfer::SyncCollection::TokenInvalid if token.is_some() => {
+                log::info!("Zone '{}': sync-token no longer valid, listing everything", zone);
+                token = None;
+                continue;
+            }
+            transfer::SyncCollection::TokenInvalid | transfer::SyncCollection::Unsupported => {
+                db::mark_sync_collection_unsupported(db, zone, webdav_url)?;
+                return Ok(false);
+            }
+        };
+
+        let changed: Vec<db::RemoteEntryRow> = changes
+            .changed
+            .into_iter()
+            .filter_map(|entry| {
+                let path = relative_remote_path(&entry.path);
+                (!path.is_empty()).then(|| remote_entry_row(path, entry))
+            })
+            .collect();
+        let removed: Vec<String> = changes
+            .removed
+            .iter()
+            .map(|href| relative_remote_path(href))
+            .filter(|path| !path.is_empty())
+            .collect();
+        // Without a token the changes are a complete listing
+        db::apply_remote_changes(
+            db,
+            zone,
+            webdav_url,
+            token.is_none(),
+            &changed,
+            &removed,
+            &changes.sync_token,
+        )?;
+        log::debug!(
+            "Zone '{}': sync-collection: {} changed, {} removed",
+            zone,
+            changed.len(),
+            removed.len()
+        );
+
+        if !changes.truncated {
+            return Ok(true);
+        }
+        token = Some(changes.sync_token);
+    }
+
+    // Still truncated: the snapshot is incomplete for now, later runs continue it
+    Ok(false)
+}
+
+/// Remote scan result from the snapshot, skipping dotfiles like the walk does.
+fn remote_scan_from_snapshot(entries: Vec<db::RemoteEntryRow>) -> RemoteScan {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut hidden = Vec::new();
+    for entry in entries {
+        if entry.path.split('/').any(|c| c.starts_with('.')) {
+            hidden.push(entry.path);
+            continue;
+        }
+        if entry.is_dir {
+            dirs.push(entry.path);
+        } else {
+            files.push(RemoteFileInfo {
+                path: entry.path,
+                etag: entry.etag,
+                mtime: entry.last_modified.as_deref().and_then(parse_http_date),
+                size: entry.size,
+                checksum: entry.checksum,
+            });
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+    RemoteScan {
+        files,
+        dirs,
+        hidden,
+    }
+}
+
+/// The remote tree as of the last walk.
+struct RemoteTreeCache {
+    /// Change tag of each folder ("" = the root)
+    dir_tags: HashMap<String, String>,
+    /// Every file and folder, ordered by path so a subtree is one range
+    entries: BTreeMap<String, db::RemoteEntryRow>,
+}
+
+impl RemoteTreeCache {
+    /// Whether the folder `dir` still has the change tag it had last time.
+    fn is_unchanged(&self, dir: &str, tag: Option<&str>) -> bool {
+        tag.is_some() && self.dir_tags.get(dir).map(String::as_str) == tag
+    }
+
+    /// Copy the cached entries and folder tags below `dir` (and the tag of `dir`) into `walk`.
+    fn reuse_subtree(&self, dir: &str, walk: &mut RemoteWalk) {
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        walk.entries.extend(
+            self.entries
+                .range(prefix.clone()..)
+                .take_while(|(path, _)| path.starts_with(&prefix))
+                .map(|(_, entry)| entry.clone()),
+        );
+        walk.dir_tags.extend(
+            self.dir_tags
+                .iter()
+                .filter(|(path, _)| path.as_str() == dir || path.starts_with(&prefix))
+                .map(|(path, tag)| (path.clone(), tag.clone())),
+        );
+        walk.reused += 1;
+    }
+}
+
+/// Result of a remote walk.
+#[derive(Default)]
+struct RemoteWalk {
+    /// Every file and folder found (the insides of dot-folders left out)
+    entries: Vec<db::RemoteEntryRow>,
+    /// Change tags of the folders that were listed completely, or reused
+    dir_tags: Vec<(String, String)>,
+    /// Folders listed with PROPFIND / taken from the cache
+    listed: usize,
+    reused: usize,
+    /// Some folder could not be listed
+    incomplete: bool,
+}
+
 /// Recursively scan remote WebDAV directory via PROPFIND.
-/// Returns flat list of RemoteFileInfo for files only.
+/// Returns a flat list of files and folders plus the change tags of the folders.
+/// A folder whose change tag matches `cache` is not descended into: its cached
+/// contents are reused, so an unchanged tree takes a single PROPFIND.
 ///
 /// Stack holds *relative* paths (e.g. "folder/sub"), not full hrefs. The server
 /// returns hrefs like "/dav/personal/folder/sub/"; we convert to relative via
 /// `relative_remote_path` before pushing to avoid URL-duplication bug (building
 /// `base_url + "/" + absolute_href` → double-prefix 404 loop).
-async fn scan_remote_files(transfer: &WebDavTransfer) -> AppResult<Vec<RemoteFileInfo>> {
-    use std::collections::HashSet;
-
+async fn scan_remote_files(
+    transfer: &WebDavTransfer,
+    cache: Option<&RemoteTreeCache>,
+) -> AppResult<RemoteWalk> {
     let mut stack: Vec<String> = vec![String::new()]; // "" = root
     let mut visited: HashSet<String> = HashSet::new();
-    let mut files = Vec::new();
+    let mut walk = RemoteWalk::default();
 
     while let Some(dir) = stack.pop() {
         // Guard against cycles (e.g. server returns self-referential hrefs).
@@ -806,46 +2728,122 @@ async fn scan_remote_files(transfer: &WebDavTransfer) -> AppResult<Vec<RemoteFil
             continue;
         }
 
-        let entries = match transfer.propfind(&dir).await {
-            Ok(e) => e,
+        let listing = match transfer.propfind_dir(&dir).await {
+            Ok(l) => l,
             Err(e) => {
                 log::warn!("PROPFIND failed for '{}': {}", dir, e);
+                walk.incomplete = true;
                 continue;
             }
         };
+        walk.listed += 1;
 
-        for entry in entries {
+        let tag = listing
+            .collection
+            .as_ref()
+            .and_then(|c| c.collection_tag())
+            .map(str::to_string);
+        if let Some(cache) = cache {
+            if cache.is_unchanged(&dir, tag.as_deref()) {
+                cache.reuse_subtree(&dir, &mut walk);
+                continue;
+            }
+        }
+        if let Some(tag) = tag {
+            walk.dir_tags.push((dir.clone(), tag));
+        }
+
+        for entry in listing.children {
             let rel_path = relative_remote_path(&entry.path);
 
-            // Skip the directory itself (PROPFIND Depth:1 includes the queried resource).
-            if rel_path == dir {
+            // Skip the directory itself (in case its href did not match the request).
+            if rel_path == dir || rel_path.is_empty() {
                 continue;
             }
 
-            // Skip dotfiles (anywhere in the path).
+            // Dotfiles are not synced; they are only recorded, and dot-folders
+            // are not descended into.
             if rel_path.split('/').any(|c| c.starts_with('.')) {
+                walk.entries.push(remote_entry_row(rel_path, entry));
                 continue;
             }
 
             if entry.is_directory {
-                if !rel_path.is_empty() {
-                    stack.push(rel_path);
+                let unchanged =
+                    cache.is_some_and(|c| c.is_unchanged(&rel_path, entry.collection_tag()));
+                match cache {
+                    Some(cache) if unchanged => cache.reuse_subtree(&rel_path, &mut walk),
+                    _ => stack.push(rel_path.clone()),
                 }
-            } else if !rel_path.is_empty() {
-                files.push(RemoteFileInfo {
-                    path: rel_path,
-                    etag: entry.etag,
-                    mtime: entry
-                        .last_modified
-                        .as_deref()
-                        .and_then(parse_http_date),
-                    size: entry.size.map(|s| s as i64),
-                });
             }
+            walk.entries.push(remote_entry_row(rel_path, entry));
+        }
+    }
+
+    Ok(walk)
+}
+
+/// Snapshot row for a remote entry found at `rel_path`.
+fn remote_entry_row(rel_path: String, entry: transfer::RemoteEntry) -> db::RemoteEntryRow {
+    db::RemoteEntryRow {
+        path: rel_path,
+        is_dir: entry.is_directory,
+        etag: entry.etag,
+        size: entry.size.map(|s| s as i64),
+        last_modified: entry.last_modified,
+        checksum: entry.checksum,
+    }
+}
+
+/// Look up the given remote paths, listing each parent folder once. Returns None
+/// if one of them is a folder or a listing fails (the full scan copes with both).
+async fn scan_remote_paths(
+    transfer: &WebDavTransfer,
+    paths: &[String],
+) -> Option<Vec<RemoteFileInfo>> {
+    let wanted: HashSet<&str> = paths.iter().map(String::as_str).collect();
+    let parents: HashSet<String> = paths
+        .iter()
+        .map(|p| {
+            Path::new(p)
+                .parent()
+                .map(|parent| parent.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for parent in parents {
+        let entries = match transfer.propfind(&parent).await {
+            Ok(e) => e,
+            Err(e) => {
+                log::warn!("PROPFIND failed for '{}': {}", parent, e);
+                return None;
+            }
+        };
+        for entry in entries {
+            let rel_path = relative_remote_path(&entry.path);
+            if rel_path == parent || !wanted.contains(rel_path.as_str()) {
+                continue;
+            }
+            if entry.is_directory {
+                return None;
+            }
+            files.push(remote_file_info(rel_path, entry));
         }
     }
+    Some(files)
+}
 
-    Ok(files)
+/// Diff input for a remote file listed by PROPFIND.
+fn remote_file_info(rel_path: String, entry: transfer::RemoteEntry) -> RemoteFileInfo {
+    RemoteFileInfo {
+        path: rel_path,
+        etag: entry.etag,
+        mtime: entry.last_modified.as_deref().and_then(parse_http_date),
+        size: entry.size.map(|s| s as i64),
+        checksum: entry.checksum,
+    }
 }
 
 /// Decode a WebDAV href to a plain path string (no URL encoding).
@@ -905,39 +2903,148 @@ fn parse_http_date(s: &str) -> Option<i64> {
         .map(|dt| dt.timestamp())
 }
 
-/// Fetch the etag for a remote file by PROPFIND on its parent directory.
-async fn fetch_etag(transfer: &WebDavTransfer, remote_path: &str) -> AppResult<String> {
+/// Remote side of a file right after a transfer, as recorded in its `synced` row.
+struct RemoteVersion {
+    etag: Option<String>,
+    mtime: Option<i64>,
+    size: u64,
+}
+
+impl RemoteVersion {
+    /// The version an upload stored, as reported by the PUT response.
+    async fn uploaded(
+        transfer: &WebDavTransfer,
+        remote_path: &str,
+        result: &transfer::UploadResult,
+    ) -> Self {
+        let version = Self {
+            etag: result.etag.clone(),
+            mtime: None,
+            size: result.bytes_sent,
+        };
+        version.or_propfind(transfer, remote_path).await
+    }
+
+    /// The version a download received, from the GET response headers.
+    async fn downloaded(
+        transfer: &WebDavTransfer,
+        remote_path: &str,
+        result: &transfer::DownloadResult,
+    ) -> Self {
+        let version = Self {
+            etag: result.etag.clone(),
+            mtime: result.last_modified.as_deref().and_then(parse_http_date),
+            size: result.content_length.unwrap_or(result.bytes_received),
+        };
+        version.or_propfind(transfer, remote_path).await
+    }
+
+    /// Without an etag header, ask with a PROPFIND on the parent directory. That may
+    /// already see a newer version, so it is only the fallback.
+    async fn or_propfind(mut self, transfer: &WebDavTransfer, remote_path: &str) -> Self {
+        if self.etag.is_some() {
+            return self;
+        }
+        match fetch_remote_entry(transfer, remote_path).await {
+            Ok(Some(entry)) => {
+                self.etag = entry.etag;
+                self.mtime = self
+                    .mtime
+                    .or_else(|| entry.last_modified.as_deref().and_then(parse_http_date));
+            }
+            Ok(None) => log::warn!("{} is missing on the server right after a transfer", remote_path),
+            Err(e) => log::warn!("Cannot read the etag of {}: {}", remote_path, e),
+        }
+        self
+    }
+}
+
+/// Look up a single remote file by listing its parent folder.
+async fn fetch_remote_entry(
+    transfer: &WebDavTransfer,
+    remote_path: &str,
+) -> AppResult<Option<transfer::RemoteEntry>> {
     let parent = Path::new(remote_path)
         .parent()
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_default();
 
-    let entries = transfer.propfind(&parent).await.unwrap_or_default();
+    let entries = transfer.propfind(&parent).await?;
 
     let file_name = Path::new(remote_path)
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_default();
 
-    for entry in entries {
-        if entry.name == file_name {
-            return Ok(entry.etag.unwrap_or_default());
+    Ok(entries
+        .into_iter()
+        .find(|entry| !entry.is_directory && entry.name == file_name))
+}
+
+/// Map content hashes to synced files whose server copy still holds that content,
+/// so they can serve as the source of a server-side copy. A scanned file must be
+/// unchanged on both sides right now. One a targeted run did not scan (`unscanned`)
+/// is left to the server to check, by the `If-Match` of the COPY, so it needs a
+/// strong etag. Empty files are left out: a PUT of nothing is cheaper than a COPY.
+fn find_copy_sources<'a>(
+    lookups: &ScanLookups<'a>,
+    known_states: &'a [FileState],
+    unscanned: &'a [FileState],
+) -> HashMap<&'a str, &'a FileState> {
+    let scanned = known_states.iter().filter(|state| {
+        let path = state.path.as_str();
+        let local_unchanged = lookups
+            .local
+            .get(path)
+            .is_some_and(|l| state.last_synced_hash.as_deref() == Some(l.hash.as_str()));
+        let remote_unchanged = lookups
+            .remote
+            .get(path)
+            .is_some_and(|r| r.etag.is_some() && r.etag == state.last_synced_etag);
+        local_unchanged && remote_unchanged
+    });
+    let unscanned = unscanned.iter().filter(|state| {
+        state
+            .last_synced_etag
+            .as_deref()
+            .is_some_and(|etag| !etag.is_empty() && !etag.starts_with("W/"))
+    });
+
+    let mut sources = HashMap::new();
+    for state in scanned.chain(unscanned) {
+        if state.sync_status != "synced" || state.local_size.is_none_or(|size| size <= 0) {
+            continue;
+        }
+        if let Some(hash) = state.last_synced_hash.as_deref() {
+            sources.entry(hash).or_insert(state);
         }
     }
-
-    Ok(String::new())
+    sources
 }
 
 // ==================== DB Helpers ====================
 
-/// Record a per-file error in the database, incrementing retry_count.
-fn record_file_error(db: &DbPool, path: &str, zone: &str, error: &str) -> AppResult<()> {
+/// Record a per-file error in the database, incrementing retry_count and setting
+/// when the file is tried next: `FILE_RETRY_BASE_SECS` after the first failure,
+/// doubling up to `max_minutes`.
+fn record_file_error(
+    db: &DbPool,
+    path: &str,
+    zone: &str,
+    error: &str,
+    max_minutes: u64,
+    local: Option<&LocalFileInfo>,
+    remote: Option<&RemoteFileInfo>,
+) -> AppResult<()> {
     let existing = db::get_file_state(db, path, zone)?.unwrap_or(FileState {
         path: path.to_string(),
         sync_zone: zone.to_string(),
+        kind: "file".to_string(),
         local_hash: None,
         local_mtime: None,
         local_size: None,
+        local_inode: None,
+        local_ctime: None,
         local_exists: false,
         remote_etag: None,
         remote_mtime: None,
@@ -950,18 +3057,161 @@ fn record_file_error(db: &DbPool, path: &str, zone: &str, error: &str) -> AppRes
         last_synced_at: None,
         error_message: None,
         retry_count: 0,
+        next_retry_at: None,
     });
 
+    let retry_count = existing.retry_count + 1;
+    let backoff = file_retry_backoff(retry_count, max_minutes);
+    // Both sides as they were when the file failed: a change to either ends the backoff
     let updated = FileState {
+        local_hash: local.map(|l| l.hash.clone()),
+        local_mtime: local.map(|l| l.mtime),
+        local_size: local.map(|l| l.size),
+        local_inode: None,
+        local_ctime: None,
+        local_exists: local.is_some(),
+        remote_etag: remote.and_then(|r| r.etag.clone()),
+        remote_mtime: remote.and_then(|r| r.mtime),
+        remote_size: remote.and_then(|r| r.size),
+        remote_exists: remote.is_some(),
         sync_status: "error".to_string(),
         error_message: Some(error.to_string()),
-        retry_count: existing.retry_count + 1,
+        retry_count,
+        next_retry_at: Some(chrono::Utc::now().timestamp() + backoff as i64),
         ..existing
     };
 
     db::upsert_file_state(db, &updated)
 }
 
+/// Seconds until a file that has failed `retry_count` times is tried again.
+fn file_retry_backoff(retry_count: i32, max_minutes: u64) -> u64 {
+    FILE_RETRY_BASE_SECS
+        .saturating_mul(1 << (retry_count - 1).clamp(0, 20))
+        .min(max_minutes.saturating_mul(60))
+}
+
+/// Store the local and remote state seen during the scan on a conflicted row.
+/// The `last_synced_*` columns stay untouched so the conflict is still detected.
+fn record_conflict_snapshot(
+    db: &DbPool,
+    path: &str,
+    zone: &str,
+    local: Option<&LocalFileInfo>,
+    remote: Option<&RemoteFileInfo>,
+) -> AppResult<()> {
+    let existing =
+        db::get_file_state(db, path, zone)?.unwrap_or_else(|| new_conflict_state(path, zone));
+
+    let updated = FileState {
+        local_hash: local.map(|l| l.hash.clone()),
+        local_mtime: local.map(|l| l.mtime),
+        local_size: local.map(|l| l.size),
+        // The snapshot has no inode/ctime, so the stat cache must not trust it
+        local_inode: None,
+        local_ctime: None,
+        local_exists: local.is_some(),
+        remote_etag: remote.and_then(|r| r.etag.clone()),
+        remote_mtime: remote.and_then(|r| r.mtime),
+        remote_size: remote.and_then(|r| r.size),
+        remote_exists: remote.is_some(),
+        ..existing
+    };
+
+    db::upsert_file_state(db, &updated)
+}
+
+/// Condition for writing over the version recorded in `known`: a file that was never
+/// synced must still be absent on the server, a synced one must still have the etag
+/// it was synced at.
+fn upload_precondition(known: Option<&FileState>) -> WritePrecondition<'_> {
+    match known {
+        Some(state) if state.remote_exists && state.last_synced_at.is_some() => {
+            etag_precondition(state.last_synced_etag.as_deref())
+        }
+        _ => WritePrecondition::Absent,
+    }
+}
+
+/// `If-Match` on a known etag; without one there is nothing to compare against.
+fn etag_precondition(etag: Option<&str>) -> WritePrecondition<'_> {
+    match etag {
+        Some(etag) if !etag.is_empty() => WritePrecondition::Etag(etag),
+        _ => WritePrecondition::None,
+    }
+}
+
+/// Request retries as configured.
+fn retry_policy(config: &AppConfig) -> transfer::RetryPolicy {
+    transfer::RetryPolicy {
+        max_attempts: config.retry_max_attempts.max(1),
+        max_delay: Duration::from_secs(config.retry_max_delay_secs.max(1)),
+        ..Default::default()
+    }
+}
+
+/// Fresh `conflict` row for a file that has never been synced.
+fn new_conflict_state(path: &str, zone: &str) -> FileState {
+    FileState {
+        path: path.to_string(),
+        sync_zone: zone.to_string(),
+        kind: "file".to_string(),
+        local_hash: None,
+        local_mtime: None,
+        local_size: None,
+        local_inode: None,
+        local_ctime: None,
+        local_exists: true,
+        remote_etag: None,
+        remote_mtime: None,
+        remote_size: None,
+        remote_exists: true,
+        sync_status: "conflict".to_string(),
+        last_synced_hash: None,
+        last_synced_mtime: None,
+        last_synced_etag: None,
+        last_synced_at: None,
+        error_message: None,
+        retry_count: 0,
+        next_retry_at: None,
+    }
+}
+
+// ==================== Conflict Policy ====================
+
+/// Pick the resolution the zone's conflict policy asks for, based on the
+/// snapshot recorded on the conflicted row. `None` means "ask the user".
+fn auto_resolution(policy: ConflictPolicy, state: &FileState) -> Option<ConflictResolution> {
+    match policy {
+        ConflictPolicy::Ask => None,
+        ConflictPolicy::KeepBoth => Some(ConflictResolution::Both),
+        ConflictPolicy::LocalWins => Some(ConflictResolution::Local),
+        ConflictPolicy::RemoteWins => Some(ConflictResolution::Remote),
+        ConflictPolicy::NewestWins => Some(match (state.local_exists, state.remote_exists) {
+            // A modification is always newer than the delete on the other side
+            (false, _) => ConflictResolution::Remote,
+            (_, false) => ConflictResolution::Local,
+            _ => match (state.local_mtime, state.remote_mtime) {
+                (Some(local), Some(remote)) if local > remote => ConflictResolution::Local,
+                (Some(local), Some(remote)) if remote > local => ConflictResolution::Remote,
+                // Same or unknown mtime — don't guess, keep both
+                _ => ConflictResolution::Both,
+            },
+        }),
+    }
+}
+
+/// Download the server version of a file into the local trash before it is
+/// overwritten or deleted on the server. Returns bytes downloaded.
+async fn trash_remote_version(ctx: &ZoneContext<'_>, rel_path: &str) -> AppResult<u64> {
+    let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    let staged = ctx.trash.staging_path(name)?;
+    let result = ctx.transfer.download_file(rel_path, &staged).await?;
+    ctx.trash
+        .trash_file_as(&staged, &ctx.local_base.join(rel_path), ctx.zone)?;
+    Ok(result.bytes_received)
+}
+
 // ==================== Misc Helpers ====================
 
 /// Default trash directory for this application.
@@ -995,6 +3245,1181 @@ fn action_label(action: &SyncAction) -> &'static str {
         SyncAction::DeleteLocal { .. } => "delete_local",
         SyncAction::DeleteRemote { .. } => "delete_remote",
         SyncAction::Conflict { .. } => "conflict",
+        SyncAction::Move { .. } => "move",
+        SyncAction::CreateLocalDir { .. } => "create_local_dir",
+        SyncAction::CreateRemoteDir { .. } => "create_remote_dir",
+        SyncAction::DeleteLocalDir { .. } => "delete_local_dir",
+        SyncAction::DeleteRemoteDir { .. } => "delete_remote_dir",
         SyncAction::Skip => "skip",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, FakeDav};
+
+    fn make_conflict(local: Option<i64>, remote: Option<i64>) -> FileState {
+        FileState {
+            local_exists: local.is_some(),
+            local_mtime: local,
+            remote_exists: remote.is_some(),
+            remote_mtime: remote,
+            ..new_conflict_state("doc.txt", "personal")
+        }
+    }
+
+    #[test]
+    fn ask_policy_leaves_conflict_to_user() {
+        let state = make_conflict(Some(2000), Some(1000));
+        assert_eq!(auto_resolution(ConflictPolicy::Ask, &state), None);
+    }
+
+    #[test]
+    fn fixed_policies_ignore_mtimes() {
+        let state = make_conflict(Some(1000), Some(2000));
+        assert_eq!(
+            auto_resolution(ConflictPolicy::LocalWins, &state),
+            Some(ConflictResolution::Local)
+        );
+        let state = make_conflict(Some(2000), Some(1000));
+        assert_eq!(
+            auto_resolution(ConflictPolicy::RemoteWins, &state),
+            Some(ConflictResolution::Remote)
+        );
+        assert_eq!(
+            auto_resolution(ConflictPolicy::KeepBoth, &state),
+            Some(ConflictResolution::Both)
+        );
+    }
+
+    #[test]
+    fn newest_wins_picks_newer_side() {
+        assert_eq!(
+            auto_resolution(
+                ConflictPolicy::NewestWins,
+                &make_conflict(Some(2000), Some(1000))
+            ),
+            Some(ConflictResolution::Local)
+        );
+        assert_eq!(
+            auto_resolution(
+                ConflictPolicy::NewestWins,
+                &make_conflict(Some(1000), Some(2000))
+            ),
+            Some(ConflictResolution::Remote)
+        );
+    }
+
+    #[test]
+    fn newest_wins_keeps_both_on_equal_or_unknown_mtime() {
+        let equal = make_conflict(Some(1000), Some(1000));
+        assert_eq!(
+            auto_resolution(ConflictPolicy::NewestWins, &equal),
+            Some(ConflictResolution::Both)
+        );
+        let mut unknown = make_conflict(Some(1000), Some(1000));
+        unknown.remote_mtime = None;
+        assert_eq!(
+            auto_resolution(ConflictPolicy::NewestWins, &unknown),
+            Some(ConflictResolution::Both)
+        );
+    }
+
+    #[test]
+    fn newest_wins_prefers_modification_over_deletion() {
+        // Deleted locally, modified on the server, even with an older mtime
+        assert_eq!(
+            auto_resolution(ConflictPolicy::NewestWins, &make_conflict(None, Some(1000))),
+            Some(ConflictResolution::Remote)
+        );
+        assert_eq!(
+            auto_resolution(ConflictPolicy::NewestWins, &make_conflict(Some(1000), None)),
+            Some(ConflictResolution::Local)
+        );
+    }
+
+    fn make_synced(path: &str, hash: &str, etag: &str) -> FileState {
+        FileState {
+            local_hash: Some(hash.to_string()),
+            local_size: Some(100),
+            sync_status: "synced".to_string(),
+            last_synced_hash: Some(hash.to_string()),
+            last_synced_etag: Some(etag.to_string()),
+            last_synced_at: Some("2026-01-01T00:00:00Z".to_string()),
+            ..new_conflict_state(path, "personal")
+        }
+    }
+
+    fn lookups<'a>(local: &'a [LocalFileInfo], remote: &'a [RemoteFileInfo]) -> ScanLookups<'a> {
+        ScanLookups {
+            local: local.iter().map(|f| (f.path.as_str(), f)).collect(),
+            remote: remote.iter().map(|f| (f.path.as_str(), f)).collect(),
+            known: HashMap::new(),
+            copy_sources: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn unscanned_synced_file_is_copy_source() {
+        // A watcher run scanned only the new file; the synced original was not touched
+        let local = vec![LocalFileInfo {
+            path: "copy.pdf".to_string(),
+            hash: "h1".to_string(),
+            mtime: 1000,
+            size: 100,
+        }];
+        let unscanned = vec![
+            make_synced("orig.pdf", "h1", "e1"),
+            make_synced("weak.pdf", "h2", "W/\"e2\""),
+        ];
+        let sources = find_copy_sources(&lookups(&local, &[]), &[], &unscanned);
+        assert_eq!(sources.get("h1").map(|s| s.path.as_str()), Some("orig.pdf"));
+        // A weak etag cannot be checked with If-Match
+        assert!(!sources.contains_key("h2"));
+    }
+
+    #[test]
+    fn scanned_file_changed_on_server_is_no_copy_source() {
+        let known = vec![make_synced("orig.pdf", "h1", "e1")];
+        let local = vec![LocalFileInfo {
+            path: "orig.pdf".to_string(),
+            hash: "h1".to_string(),
+            mtime: 1000,
+            size: 100,
+        }];
+        let remote = vec![RemoteFileInfo {
+            path: "orig.pdf".to_string(),
+            etag: Some("e2".to_string()),
+            mtime: Some(1000),
+            size: Some(100),
+            checksum: None,
+        }];
+        assert!(find_copy_sources(&lookups(&local, &remote), &known, &[]).is_empty());
+
+        let remote = vec![RemoteFileInfo {
+            etag: Some("e1".to_string()),
+            ..remote[0].clone()
+        }];
+        let sources = find_copy_sources(&lookups(&local, &remote), &known, &[]);
+        assert_eq!(sources.get("h1").map(|s| s.path.as_str()), Some("orig.pdf"));
+    }
+
+    #[test]
+    fn file_retry_backoff_doubles_up_to_max() {
+        assert_eq!(file_retry_backoff(1, 60), 60);
+        assert_eq!(file_retry_backoff(2, 60), 120);
+        assert_eq!(file_retry_backoff(3, 60), 240);
+        assert_eq!(file_retry_backoff(6, 60), 1920);
+        assert_eq!(file_retry_backoff(7, 60), 3600);
+        assert_eq!(file_retry_backoff(50, 60), 3600);
+        // 0 = try again on every sync
+        assert_eq!(file_retry_backoff(3, 0), 0);
+    }
+
+    #[test]
+    fn record_file_error_schedules_next_retry() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::migrate(&conn).unwrap();
+        let db: DbPool = Arc::new(Mutex::new(conn));
+
+        let before = chrono::Utc::now().timestamp();
+        record_file_error(&db, "doc.txt", "personal", "HTTP 500", 60, None, None).unwrap();
+        record_file_error(&db, "doc.txt", "personal", "HTTP 503", 60, None, None).unwrap();
+        let state = db::get_file_state(&db, "doc.txt", "personal")
+            .unwrap()
+            .unwrap();
+        let after = chrono::Utc::now().timestamp();
+
+        assert_eq!(state.sync_status, "error");
+        assert_eq!(state.retry_count, 2);
+        assert_eq!(state.error_message.as_deref(), Some("HTTP 503"));
+        let next = state.next_retry_at.unwrap();
+        assert!(next >= before + 120 && next <= after + 120, "{}", next);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn schedule_window_starting_mid_transfer_throttles_it() {
+        use crate::config::BandwidthRule;
+
+        // 512 KB/s upload on weekdays from 8:00; the transfer started before that
+        let config = AppConfig {
+            bandwidth_schedule: vec![BandwidthRule {
+                days: vec![1, 2, 3, 4, 5],
+                start_minute: 8 * 60,
+                end_minute: 17 * 60,
+                upload_kbps: 512,
+                download_kbps: 0,
+                pause_sync: false,
+            }],
+            ..AppConfig::default()
+        };
+        let at = |time: &str| {
+            chrono::NaiveDateTime::parse_from_str(&format!("2026-10-12 {}", time), "%Y-%m-%d %H:%M")
+                .unwrap()
+        };
+        let clock = Mutex::new(at("07:59"));
+        let engine = SyncEngine::new();
+        let follow = engine.follow_bandwidth_schedule(Duration::from_millis(10), || {
+            config.bandwidth_at(*clock.lock().unwrap())
+        });
+
+        let transfer = async {
+            let upload = &engine.bandwidth.upload;
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            for _ in 0..10 {
+                upload.take(1024 * 1024).await; // unlimited before 8:00
+            }
+
+            *clock.lock().unwrap() = at("08:00");
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            // 1 MB at 512 KB/s leaves two seconds of debt for the next piece
+            upload.take(1024 * 1024).await;
+            let next = tokio::time::timeout(Duration::from_millis(300), upload.take(1)).await;
+            assert!(next.is_err(), "the transfer was not throttled at 8:00");
+        };
+        tokio::time::timeout(Duration::from_secs(2), async {
+            tokio::select! {
+                _ = follow => unreachable!(),
+                _ = transfer => {}
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_window_starting_mid_transfer_stops_it_and_cancels_the_run() {
+        use crate::config::BandwidthRule;
+
+        // Nothing goes through from 22:00; the limits of the rule do not count
+        let config = AppConfig {
+            bandwidth_schedule: vec![BandwidthRule {
+                days: vec![1, 2, 3, 4, 5, 6, 7],
+                start_minute: 22 * 60,
+                end_minute: 6 * 60,
+                upload_kbps: 0,
+                download_kbps: 0,
+                pause_sync: true,
+            }],
+            ..AppConfig::default()
+        };
+        let at = |time: &str| {
+            chrono::NaiveDateTime::parse_from_str(&format!("2026-10-12 {}", time), "%Y-%m-%d %H:%M")
+                .unwrap()
+        };
+        let clock = Mutex::new(at("21:59"));
+        let engine = SyncEngine::new();
+        let run = CancellationToken::new();
+        *engine.cancel_guard() = Some(run.clone());
+        let follow = engine.follow_bandwidth_schedule(Duration::from_millis(10), || {
+            config.bandwidth_at(*clock.lock().unwrap())
+        });
+
+        let transfer = async {
+            let download = &engine.bandwidth.download;
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            download.take(1024 * 1024).await;
+            assert!(!run.is_cancelled());
+
+            *clock.lock().unwrap() = at("22:00");
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            assert!(run.is_cancelled(), "the run goes on in the pause window");
+            let next = tokio::time::timeout(Duration::from_secs(60), download.take(1)).await;
+            assert!(next.is_err(), "data went through in the pause window");
+
+            // Back to the default limits (unlimited) at 6:00
+            *clock.lock().unwrap() = at("06:00") + chrono::Duration::days(1);
+            download.take(1).await;
+        };
+        tokio::time::timeout(Duration::from_secs(120), async {
+            tokio::select! {
+                _ = follow => unreachable!(),
+                _ = transfer => {}
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn copy_path_keeps_directory_and_extension() {
+        assert_eq!(
+            tagged_copy_path("dir/report.pdf", "conflict HOST", |_| false),
+            "dir/report (conflict HOST).pdf"
+        );
+        assert_eq!(
+            tagged_copy_path("archive.tar.gz", "conflict HOST", |_| false),
+            "archive.tar (conflict HOST).gz"
+        );
+    }
+
+    #[test]
+    fn copy_path_without_extension() {
+        assert_eq!(
+            tagged_copy_path("dir/Makefile", "conflict HOST", |_| false),
+            "dir/Makefile (conflict HOST)"
+        );
+    }
+
+    #[test]
+    fn copy_path_of_dotfile_keeps_whole_name() {
+        assert_eq!(
+            tagged_copy_path("dir/.env", "conflict HOST", |_| false),
+            "dir/.env (conflict HOST)"
+        );
+    }
+
+    #[test]
+    fn copy_path_skips_taken_names() {
+        let taken = ["a/doc (conflict HOST).txt", "a/doc (conflict HOST 2).txt"];
+        assert_eq!(
+            tagged_copy_path("a/doc.txt", "conflict HOST", |path| taken.contains(&path)),
+            "a/doc (conflict HOST 3).txt"
+        );
+    }
+
+    /// Records what the worker pool does instead of transferring anything.
+    #[derive(Default)]
+    struct FakeExecutor {
+        events: Mutex<Vec<String>>,
+        /// Uploads and downloads running now, and the most seen at once
+        running: Mutex<[usize; 4]>,
+        progress: Mutex<Vec<usize>>,
+        /// Cancelled as the first action starts
+        cancel: Option<CancellationToken>,
+    }
+
+    impl FakeExecutor {
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn count_running(&self, action: &SyncAction, delta: isize) {
+            let slot = match action {
+                SyncAction::Upload { .. } => 0,
+                SyncAction::Download { .. } => 2,
+                _ => return,
+            };
+            let mut running = self.running.lock().unwrap();
+            running[slot] = running[slot].checked_add_signed(delta).unwrap();
+            running[slot + 1] = running[slot + 1].max(running[slot]);
+        }
+    }
+
+    impl ActionExecutor for FakeExecutor {
+        async fn create_remote_parents(&self, actions: &[&DiffResult]) {
+            for parent in remote_parents(actions) {
+                self.record(format!("mkcol {}", parent));
+            }
+        }
+
+        fn started(&self, _diff_result: &DiffResult, current: usize, _total: usize) {
+            self.progress.lock().unwrap().push(current);
+        }
+
+        async fn execute(&self, diff_result: &DiffResult) -> AppResult<Option<u64>> {
+            if let Some(cancel) = &self.cancel {
+                cancel.cancel();
+            }
+            self.record(format!("start {}", diff_result.path));
+            self.count_running(&diff_result.action, 1);
+            // Uneven durations, so the actions of a phase finish out of order
+            let millis = 5 + (diff_result.path.len() as u64 * 7) % 20;
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            self.count_running(&diff_result.action, -1);
+            self.record(format!("end {}", diff_result.path));
+
+            if diff_result.path.contains("fail") {
+                return Err(AppError::sync("HTTP 500"));
+            }
+            Ok(Some(match diff_result.action {
+                SyncAction::Upload { .. } => 100,
+                SyncAction::Download { .. } => 10,
+                _ => 0,
+            }))
+        }
+    }
+
+    fn slots(uploads: usize, downloads: usize, total: usize) -> TransferSlots {
+        TransferSlots {
+            uploads: Semaphore::new(uploads),
+            downloads: Semaphore::new(downloads),
+            started: AtomicUsize::new(0),
+            total,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    fn action(path: &str, action: SyncAction) -> DiffResult {
+        DiffResult {
+            path: path.to_string(),
+            action,
+        }
+    }
+
+    fn upload(path: &str) -> DiffResult {
+        action(
+            path,
+            SyncAction::Upload {
+                local_path: format!("/local/{}", path),
+                remote_path: format!("/{}", path),
+            },
+        )
+    }
+
+    fn download(path: &str) -> DiffResult {
+        action(
+            path,
+            SyncAction::Download {
+                remote_path: format!("/{}", path),
+                local_path: format!("/local/{}", path),
+            },
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pool_runs_phases_in_order() {
+        let actions = vec![
+            action(
+                "olddir",
+                SyncAction::DeleteRemoteDir {
+                    remote_path: "/olddir".into(),
+                },
+            ),
+            action(
+                "old.txt",
+                SyncAction::DeleteRemote {
+                    remote_path: "/old.txt".into(),
+                },
+            ),
+            action(
+                "gone.txt",
+                SyncAction::DeleteLocal {
+                    local_path: "/local/gone.txt".into(),
+                },
+            ),
+            action(
+                "c.txt",
+                SyncAction::Conflict {
+                    local_path: "/local/c.txt".into(),
+                    remote_path: "/c.txt".into(),
+                    conflict_type: ConflictType::BothModified,
+                },
+            ),
+            upload("b/new/x.txt"),
+            action(
+                "b/new",
+                SyncAction::CreateRemoteDir {
+                    remote_path: "/b/new".into(),
+                },
+            ),
+            download("d.txt"),
+            action(
+                "b/a.txt",
+                SyncAction::Move {
+                    from: "a.txt".into(),
+                    source: "a.txt".into(),
+                    destination: "b/a.txt".into(),
+                    side: MoveSide::Remote,
+                },
+            ),
+        ];
+        let executor = FakeExecutor::default();
+        let outcomes = run_actions(&actions, &slots(4, 4, actions.len()), 8, &executor).await;
+        assert_eq!(outcomes.len(), actions.len());
+
+        let events = executor.events();
+        let at = |event: &str| {
+            events
+                .iter()
+                .position(|e| e == event)
+                .unwrap_or_else(|| panic!("no '{}' in {:?}", event, events))
+        };
+        let in_order = |first: &[&str], then: &[&str]| {
+            for a in first {
+                for b in then {
+                    assert!(at(a) < at(b), "'{}' is not before '{}': {:?}", a, b, events);
+                }
+            }
+        };
+
+        // Folders exist before anything is moved or uploaded into them
+        in_order(&["mkcol b"], &["start b/a.txt"]);
+        in_order(&["mkcol b/new"], &["start b/new/x.txt", "start b/new"]);
+        // Moves, then transfers, then conflicts, then deletions, then folder deletions
+        let transfers = ["start b/new/x.txt", "start b/new", "start d.txt"];
+        in_order(&["end b/a.txt"], &transfers);
+        in_order(
+            &["end b/new/x.txt", "end b/new", "end d.txt"],
+            &["start c.txt"],
+        );
+        in_order(&["end c.txt"], &["start old.txt", "start gone.txt"]);
+        in_order(&["end old.txt", "end gone.txt"], &["start olddir"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pool_keeps_to_the_slots_and_counts_every_action() {
+        let mut actions = Vec::new();
+        for i in 0..12 {
+            let name = if i == 3 {
+                "fail".to_string()
+            } else {
+                i.to_string()
+            };
+            actions.push(upload(&format!("up/{}.bin", name)));
+            actions.push(download(&format!("down/{}.bin", name)));
+        }
+        let executor = FakeExecutor::default();
+        let outcomes = run_actions(&actions, &slots(2, 3, actions.len()), 5, &executor).await;
+
+        let [uploads, most_uploads, downloads, most_downloads] = *executor.running.lock().unwrap();
+        assert_eq!((uploads, downloads), (0, 0));
+        assert!(most_uploads == 2, "{} uploads at once", most_uploads);
+        assert!(
+            (2..=3).contains(&most_downloads),
+            "{} downloads at once",
+            most_downloads
+        );
+        let mut progress = executor.progress.lock().unwrap().clone();
+        progress.sort_unstable();
+        assert_eq!(progress, (1..=24).collect::<Vec<_>>());
+
+        let mut stats = SyncRunStats {
+            files_uploaded: 0,
+            files_downloaded: 0,
+            files_deleted: 0,
+            files_conflicted: 0,
+            bytes_transferred: 0,
+            error_message: None,
+        };
+        let mut failed = Vec::new();
+        for (diff_result, result) in outcomes {
+            match result {
+                Ok(Some(bytes)) => count_done(&mut stats, &diff_result.action, bytes),
+                Ok(None) => {}
+                Err(_) => failed.push(diff_result.path.as_str()),
+            }
+        }
+        failed.sort_unstable();
+        assert_eq!(failed, ["down/fail.bin", "up/fail.bin"]);
+        assert_eq!(stats.files_uploaded, 11);
+        assert_eq!(stats.files_downloaded, 11);
+        assert_eq!(stats.bytes_transferred, 11 * 100 + 11 * 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_pool_starts_no_further_actions() {
+        let actions: Vec<_> = (0..5).map(|i| upload(&format!("{}.bin", i))).collect();
+        let slots = slots(1, 1, actions.len());
+        let executor = FakeExecutor {
+            cancel: Some(slots.cancel.clone()),
+            ..FakeExecutor::default()
+        };
+        let outcomes = run_actions(&actions, &slots, 2, &executor).await;
+
+        let started: Vec<_> = executor
+            .events()
+            .into_iter()
+            .filter(|e| e.starts_with("start"))
+            .collect();
+        assert_eq!(started.len(), 1, "{:?}", started);
+        let cancelled = outcomes
+            .iter()
+            .filter(|(_, result)| result.as_ref().is_err_and(|e| e.is_cancelled()))
+            .count();
+        assert_eq!(cancelled, 4);
+        assert_eq!(slots.started.load(Ordering::Relaxed), 1);
+    }
+
+    fn memory_db() -> DbPool {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::migrate(&conn).unwrap();
+        Arc::new(Mutex::new(conn))
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("veloryn-sync-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn mock_app() -> tauri::App<tauri::test::MockRuntime> {
+        tauri::test::mock_builder()
+            .plugin(tauri_plugin_notification::init())
+            .build(tauri::test::mock_context(tauri::test::noop_assets()))
+            .unwrap()
+    }
+
+    /// An engine with an in-memory DB, syncing `dir`/personal with the fake server.
+    fn test_engine(dav: &FakeDav, dir: &Path) -> (SyncEngine, AppConfig) {
+        let engine = SyncEngine::new();
+        *engine.db.lock().unwrap() = Some(memory_db());
+        let config = AppConfig {
+            server_url: dav.server_url.clone(),
+            user_login: "u".to_string(),
+            personal_sync_path: dir.join("personal"),
+            shared_sync_path: dir.join("shared"),
+            ..AppConfig::default()
+        };
+        (engine, config)
+    }
+
+    fn synced_row(engine: &SyncEngine, path: &str) -> Option<FileState> {
+        let db = engine.get_db().unwrap();
+        db::get_file_state(&db, path, "personal")
+            .unwrap()
+            .filter(|state| state.sync_status == "synced")
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_paused_run_records_it_as_cancelled() {
+        let dav = FakeDav::start().await;
+        dav.tree().put("big.bin", &[7; 64 * 1024]);
+        let dir = test_dir("cancel");
+        let (engine, mut config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("mine.txt"), b"mine").unwrap();
+        // 8 KB/s down: mine.txt is uploaded at once, big.bin takes seconds
+        config.max_download_kbps = 8;
+        let app = mock_app();
+
+        let run = engine.sync_all(app.handle(), &config, "token", "manual", &SyncScope::Full);
+        let user = async {
+            while synced_row(&engine, "mine.txt").is_none()
+                || !dav.requests().iter().any(|r| r == "GET big.bin")
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            engine.pause();
+            assert!(engine.is_running());
+            assert!(engine.cancel(), "a paused run in progress can be cancelled");
+        };
+        let (result, ()) = tokio::join!(run, user);
+        result.unwrap();
+
+        let db = engine.get_db().unwrap();
+        let last_run = || {
+            let runs = db::list_sync_runs(&db, 10).unwrap();
+            runs.into_iter().max_by_key(|run| run.id).unwrap()
+        };
+        let run = last_run();
+        assert_eq!(run.status, "cancelled");
+        assert_eq!((run.files_uploaded, run.files_downloaded), (1, 0));
+        assert_eq!(engine.get_status(), SyncStatus::Paused);
+        assert!(!engine.is_running());
+        assert!(!engine.cancel());
+
+        // What finished is synced; the cancelled download left neither a row nor a file
+        assert!(synced_row(&engine, "mine.txt").is_some());
+        assert!(db::get_file_state(&db, "big.bin", "personal")
+            .unwrap()
+            .is_none());
+        let mut local: Vec<_> = std::fs::read_dir(&config.personal_sync_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        local.sort();
+        assert_eq!(local, ["mine.txt"]);
+
+        // The next run picks it up
+        engine.resume();
+        config.max_download_kbps = 0;
+        engine
+            .sync_all(app.handle(), &config, "token", "manual", &SyncScope::Full)
+            .await
+            .unwrap();
+        assert_eq!(last_run().status, "success");
+        assert!(synced_row(&engine, "big.bin").is_some());
+        assert_eq!(
+            std::fs::read(config.personal_sync_path.join("big.bin")).unwrap(),
+            [7; 64 * 1024]
+        );
+    }
+
+    /// Synced row of a file hashed with `hash` while it had `stat`.
+    fn hashed_row(path: &str, hash: &str, stat: &db::LocalStat) -> FileState {
+        FileState {
+            kind: "file".to_string(),
+            local_hash: Some(hash.to_string()),
+            local_size: Some(stat.size),
+            local_mtime: Some(stat.mtime),
+            local_inode: stat.inode,
+            local_ctime: stat.ctime,
+            ..synced_dir_state(path, "personal")
+        }
+    }
+
+    #[test]
+    fn cached_hash_needs_the_whole_stat_tuple() {
+        let stat = db::LocalStat {
+            size: 4,
+            mtime: 1_700_000_000,
+            inode: Some(42),
+            ctime: Some(1_700_000_000_123_456_789),
+        };
+        let row = hashed_row("a.txt", "hash", &stat);
+        assert_eq!(row.cached_hash(&stat), Some("hash"));
+
+        // Rewritten in place with the same size and mtime
+        let rewritten = db::LocalStat {
+            ctime: Some(1_700_000_001_000_000_000),
+            ..stat.clone()
+        };
+        assert_eq!(row.cached_hash(&rewritten), None);
+        // Replaced by another file
+        let replaced = db::LocalStat {
+            inode: Some(43),
+            ..stat.clone()
+        };
+        assert_eq!(row.cached_hash(&replaced), None);
+        let resized = db::LocalStat { size: 5, ..stat };
+        assert_eq!(row.cached_hash(&resized), None);
+    }
+
+    #[tokio::test]
+    async fn scan_rehashes_changed_stats_and_refreshes_unchanged_content() {
+        let dir = test_dir("stat-cache");
+        for name in ["same.txt", "touched.txt", "replaced.txt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        let stat = |name: &str| local_stat(&std::fs::metadata(dir.join(name)).unwrap());
+        let hash = |name: &str| hash_file(&dir.join(name)).unwrap();
+
+        // Unchanged: the stored hash is reused, even a wrong one
+        let same = hashed_row("same.txt", "stored", &stat("same.txt"));
+        // Same content, but the ctime moved on since it was hashed
+        let touched_stat = db::LocalStat {
+            ctime: stat("touched.txt").ctime.map(|ctime| ctime - 1),
+            ..stat("touched.txt")
+        };
+        let touched = hashed_row("touched.txt", &hash("touched.txt"), &touched_stat);
+        // Another file under the same name
+        let replaced_stat = db::LocalStat {
+            inode: stat("replaced.txt").inode.map(|inode| inode + 1),
+            ..stat("replaced.txt")
+        };
+        let replaced = hashed_row("replaced.txt", "old", &replaced_stat);
+
+        let db = memory_db();
+        let rows = vec![same, touched, replaced];
+        for row in &rows {
+            db::upsert_file_state(&db, row).unwrap();
+        }
+        let cache = file_stat_cache(&rows);
+        let scan = scan_local_files(&dir, 0, Some(&cache)).await.unwrap();
+
+        let hashes: HashMap<&str, &str> = scan
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.hash.as_str()))
+            .collect();
+        assert_eq!(hashes["same.txt"], "stored");
+        assert_eq!(hashes["touched.txt"], hash("touched.txt"));
+        assert_eq!(hashes["replaced.txt"], hash("replaced.txt"));
+
+        // Only the rehash that found the same content updates the stored stats
+        let refreshed: Vec<&str> = scan
+            .refreshed_stats
+            .iter()
+            .map(|(p, _)| p.as_str())
+            .collect();
+        assert_eq!(refreshed, ["touched.txt"]);
+        db::refresh_local_stats(&db, "personal", &scan.refreshed_stats).unwrap();
+        let stored = db::get_file_state(&db, "touched.txt", "personal")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.cached_hash(&stat("touched.txt")),
+            Some(hash("touched.txt").as_str())
+        );
+    }
+
+    #[test]
+    fn full_verify_is_due_per_zone_from_the_stored_time() {
+        let engine = SyncEngine::new();
+        let db = memory_db();
+        let config = AppConfig {
+            full_verify_interval_hours: 24,
+            ..AppConfig::default()
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        // Never verified, e.g. right after an upgrade
+        assert!(engine.full_verify_due(&db, &config, "personal"));
+        engine.full_verify_done(&db, "personal");
+        assert!(!engine.full_verify_due(&db, &config, "personal"));
+        assert!(engine.full_verify_due(&db, &config, "shared"));
+
+        db::set_last_full_verify(&db, "personal", now - 25 * 3600).unwrap();
+        assert!(engine.full_verify_due(&db, &config, "personal"));
+        db::set_last_full_verify(&db, "personal", now - 23 * 3600).unwrap();
+        db::set_last_full_verify(&db, "shared", now - 23 * 3600).unwrap();
+
+        // A requested verify lasts until it got through the zone
+        engine.request_full_verify();
+        engine.full_verify_done(&db, "shared");
+        assert!(engine.full_verify_due(&db, &config, "personal"));
+        assert!(!engine.full_verify_due(&db, &config, "shared"));
+    }
+
+    #[tokio::test]
+    async fn never_synced_pair_is_linked_when_identical() {
+        let dav = FakeDav::start().await;
+        dav.tree().put("same.txt", b"same content");
+        dav.tree().put("other.txt", b"their change");
+        let dir = test_dir("link");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("same.txt"), b"same content").unwrap();
+        std::fs::write(config.personal_sync_path.join("other.txt"), b"local change").unwrap();
+        let app = mock_app();
+
+        engine
+            .sync_all(app.handle(), &config, "token", "manual", &SyncScope::Full)
+            .await
+            .unwrap();
+
+        let same = synced_row(&engine, "same.txt").expect("identical copies are linked");
+        assert_eq!(same.remote_etag.as_deref(), dav.tree().etag("same.txt"));
+        let db = engine.get_db().unwrap();
+        let other = db::get_file_state(&db, "other.txt", "personal")
+            .unwrap()
+            .unwrap();
+        assert_eq!(other.sync_status, "conflict");
+        // Compared by download, nothing uploaded
+        let requests = dav.requests();
+        assert!(requests.contains(&"GET same.txt".to_string()));
+        assert!(!requests.iter().any(|r| r.starts_with("PUT ")));
+        assert_eq!(dav.tree().file("other.txt"), Some(&b"their change"[..]));
+    }
+
+    #[tokio::test]
+    async fn large_never_synced_pair_is_not_downloaded_to_compare() {
+        let dav = FakeDav::start().await;
+        let content = vec![1; MAX_COMPARE_DOWNLOAD_BYTES as usize + 1];
+        dav.tree().put("big.bin", &content);
+        let dir = test_dir("link-large");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("big.bin"), &content).unwrap();
+        let app = mock_app();
+
+        engine
+            .sync_all(app.handle(), &config, "token", "manual", &SyncScope::Full)
+            .await
+            .unwrap();
+
+        let db = engine.get_db().unwrap();
+        let state = db::get_file_state(&db, "big.bin", "personal")
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.sync_status, "conflict");
+        assert!(!dav.requests().iter().any(|r| r == "GET big.bin"));
+    }
+
+    async fn sync_once(engine: &SyncEngine, config: &AppConfig) -> AppResult<()> {
+        sync_from(engine, config, "manual").await
+    }
+
+    async fn sync_from(engine: &SyncEngine, config: &AppConfig, source: &str) -> AppResult<()> {
+        let app = mock_app();
+        engine
+            .sync_all(app.handle(), config, "token", source, &SyncScope::Full)
+            .await
+    }
+
+    fn file_status(engine: &SyncEngine, path: &str) -> Option<String> {
+        let db = engine.get_db().unwrap();
+        db::get_file_state(&db, path, "personal")
+            .unwrap()
+            .map(|state| state.sync_status)
+    }
+
+    /// The request `method` made for `path` in the zone, the last one if several.
+    fn last_request(dav: &FakeDav, method: &str, path: &str) -> test_server::Request {
+        dav.raw_requests()
+            .into_iter()
+            .rev()
+            .find(|r| r.method == method && r.zone_path() == Some(path))
+            .unwrap_or_else(|| panic!("no {} {}", method, path))
+    }
+
+    #[tokio::test]
+    async fn new_file_is_uploaded_only_where_the_server_has_none() {
+        let dav = FakeDav::start().await;
+        let dir = test_dir("if-none-match");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("fresh.txt"), b"mine").unwrap();
+        std::fs::write(config.personal_sync_path.join("raced.txt"), b"mine").unwrap();
+        // Someone else creates raced.txt on the server while this run scans
+        dav.hook(|request, tree| {
+            if request.method == "PUT" && request.zone_path() == Some("raced.txt") {
+                tree.put("raced.txt", b"theirs");
+            }
+            None
+        });
+
+        sync_once(&engine, &config).await.unwrap();
+
+        let fresh = last_request(&dav, "PUT", "fresh.txt");
+        assert_eq!(fresh.header("If-None-Match"), Some("*"));
+        assert_eq!(file_status(&engine, "fresh.txt").as_deref(), Some("synced"));
+        assert_eq!(
+            file_status(&engine, "raced.txt").as_deref(),
+            Some("conflict")
+        );
+        assert_eq!(dav.tree().file("raced.txt"), Some(&b"theirs"[..]));
+    }
+
+    #[tokio::test]
+    async fn remote_change_during_upload_or_delete_becomes_a_conflict() {
+        let dav = FakeDav::start().await;
+        dav.tree().put("edit.txt", b"v1");
+        dav.tree().put("gone.txt", b"v1");
+        let dir = test_dir("if-match");
+        let (engine, config) = test_engine(&dav, &dir);
+        sync_once(&engine, &config).await.unwrap();
+        let synced_etag = dav.tree().etag("edit.txt").unwrap().to_string();
+
+        std::fs::write(config.personal_sync_path.join("edit.txt"), b"mine, v2").unwrap();
+        std::fs::remove_file(config.personal_sync_path.join("gone.txt")).unwrap();
+        // Both files change on the server just before the client's write lands
+        dav.hook(|request, tree| {
+            match (request.method.as_str(), request.zone_path()) {
+                ("PUT", Some("edit.txt")) => {
+                    tree.put("edit.txt", b"theirs, v2");
+                }
+                ("DELETE", Some("gone.txt")) => {
+                    tree.put("gone.txt", b"theirs, v2");
+                }
+                _ => {}
+            }
+            None
+        });
+
+        sync_once(&engine, &config).await.unwrap();
+
+        let put = last_request(&dav, "PUT", "edit.txt");
+        assert_eq!(
+            put.header("If-Match"),
+            Some(format!("\"{}\"", synced_etag).as_str())
+        );
+        assert!(last_request(&dav, "DELETE", "gone.txt")
+            .header("If-Match")
+            .is_some());
+        assert_eq!(
+            file_status(&engine, "edit.txt").as_deref(),
+            Some("conflict")
+        );
+        assert_eq!(
+            file_status(&engine, "gone.txt").as_deref(),
+            Some("conflict")
+        );
+        assert_eq!(dav.tree().file("edit.txt"), Some(&b"theirs, v2"[..]));
+        assert_eq!(dav.tree().file("gone.txt"), Some(&b"theirs, v2"[..]));
+    }
+
+    #[tokio::test]
+    async fn failed_file_is_retried_early_once_changed_or_asked_for() {
+        let dav = FakeDav::start().await;
+        let dir = test_dir("backoff");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        let local = config.personal_sync_path.join("refused.txt");
+        std::fs::write(&local, b"v1").unwrap();
+        dav.hook(|request, _| {
+            (request.method == "PUT" && request.zone_path() == Some("refused.txt"))
+                .then(|| test_server::response("403 Forbidden", &[], b""))
+        });
+        let puts = |dav: &FakeDav| {
+            dav.requests()
+                .iter()
+                .filter(|r| r.as_str() == "PUT refused.txt")
+                .count()
+        };
+
+        let _ = sync_from(&engine, &config, "interval").await;
+        assert_eq!(
+            file_status(&engine, "refused.txt").as_deref(),
+            Some("error")
+        );
+        assert_eq!(puts(&dav), 1);
+
+        // Nothing changed: the file waits out its backoff
+        let _ = sync_from(&engine, &config, "interval").await;
+        assert_eq!(puts(&dav), 1);
+
+        // A new local version is worth trying at once
+        std::fs::write(&local, b"v2").unwrap();
+        let _ = sync_from(&engine, &config, "interval").await;
+        assert_eq!(puts(&dav), 2);
+        let _ = sync_from(&engine, &config, "interval").await;
+        assert_eq!(puts(&dav), 2);
+
+        // So is a run the user asked for
+        let _ = sync_from(&engine, &config, "manual").await;
+        assert_eq!(puts(&dav), 3);
+    }
+
+    #[tokio::test]
+    async fn remote_folder_is_deleted_only_when_the_scan_left_nothing_in_it() {
+        let dav = FakeDav::start().await;
+        for dir in ["gone", "kept"] {
+            dav.tree().mkdir(dir);
+            dav.tree().put(&format!("{}/a.txt", dir), b"a");
+        }
+        dav.tree().put("kept/.keep", b"");
+        let dir = test_dir("remote-dir-delete");
+        let (engine, config) = test_engine(&dav, &dir);
+        sync_once(&engine, &config).await.unwrap();
+
+        for dir in ["gone", "kept"] {
+            std::fs::remove_dir_all(config.personal_sync_path.join(dir)).unwrap();
+        }
+        sync_once(&engine, &config).await.unwrap();
+
+        let requests: Vec<String> = dav
+            .raw_requests()
+            .iter()
+            .filter_map(|r| Some(format!("{} {}", r.method, r.zone_path()?)))
+            .collect();
+        let first_delete = requests
+            .iter()
+            .position(|r| r.starts_with("DELETE "))
+            .expect("a delete");
+        let after = &requests[first_delete..];
+        assert!(after.iter().any(|r| r == "DELETE gone"));
+        assert!(!after.iter().any(|r| r == "DELETE kept"));
+        // Emptiness comes from the scan, not from listing the folders again
+        assert!(!after.iter().any(|r| r.starts_with("PROPFIND ")));
+        assert_eq!(dav.tree().file("gone/a.txt"), None);
+        assert_eq!(dav.tree().file("kept/a.txt"), None);
+        assert_eq!(dav.tree().file("kept/.keep"), Some(&b""[..]));
+    }
+
+    #[tokio::test]
+    async fn renamed_folder_holding_a_remote_dotfile_moves_file_by_file() {
+        let dav = FakeDav::start().await;
+        dav.tree().mkdir("old");
+        dav.tree().put("old/a.txt", b"a");
+        dav.tree().put("old/b.txt", b"b");
+        dav.tree().put("old/.keep", b"");
+        let dir = test_dir("dir-move-hidden");
+        let (engine, config) = test_engine(&dav, &dir);
+        sync_once(&engine, &config).await.unwrap();
+
+        let base = &config.personal_sync_path;
+        std::fs::rename(base.join("old"), base.join("new")).unwrap();
+        sync_once(&engine, &config).await.unwrap();
+
+        assert!(!dav.requests().contains(&"MOVE old".to_string()));
+        assert_eq!(dav.tree().file("new/a.txt"), Some(&b"a"[..]));
+        assert_eq!(dav.tree().file("new/b.txt"), Some(&b"b"[..]));
+        assert_eq!(dav.tree().file("old/.keep"), Some(&b""[..]));
+    }
+
+    #[tokio::test]
+    async fn conflict_copy_name_taken_on_the_server_is_skipped() {
+        let dav = FakeDav::start().await;
+        dav.tree().mkdir("a");
+        dav.tree().put("a/doc.txt", b"v1");
+        let dir = test_dir("copy-name");
+        let (engine, config) = test_engine(&dav, &dir);
+        sync_once(&engine, &config).await.unwrap();
+
+        std::fs::write(config.personal_sync_path.join("a/doc.txt"), b"mine").unwrap();
+        dav.tree().put("a/doc.txt", b"theirs");
+        sync_once(&engine, &config).await.unwrap();
+        // Since the run found the conflict, the first copy name came up on the server
+        let taken = conflicted_copy_path("a/doc.txt", |_| false);
+        dav.tree().put(&taken, b"someone else's");
+        engine
+            .resolve_conflict(
+                &config,
+                "token",
+                "personal",
+                "a/doc.txt",
+                ConflictResolution::Both,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(dav.tree().file(&taken), Some(&b"someone else's"[..]));
+        let copy = conflicted_copy_path("a/doc.txt", |path| path == taken);
+        assert_eq!(dav.tree().file(&copy), Some(&b"mine"[..]));
+        assert_eq!(synced_row(&engine, &copy).map(|row| row.path), Some(copy));
+        assert_eq!(dav.tree().file("a/doc.txt"), Some(&b"theirs"[..]));
+    }
+
+    /// PROPFINDs in the zone after its first transfer, i.e. not made by the scan.
+    fn propfinds_after_transfers(dav: &FakeDav) -> usize {
+        let requests: Vec<_> = dav
+            .raw_requests()
+            .into_iter()
+            .filter(|r| r.zone_path().is_some())
+            .collect();
+        let first_transfer = requests
+            .iter()
+            .position(|r| r.method == "GET" || r.method == "PUT")
+            .expect("a transfer");
+        requests[first_transfer..]
+            .iter()
+            .filter(|r| r.method == "PROPFIND")
+            .count()
+    }
+
+    #[tokio::test]
+    async fn transfer_etags_come_from_the_response_headers() {
+        let dav = FakeDav::start().await;
+        dav.tree().put("down.txt", b"theirs");
+        let dir = test_dir("etag-headers");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("up.txt"), b"mine").unwrap();
+
+        sync_once(&engine, &config).await.unwrap();
+
+        for path in ["down.txt", "up.txt"] {
+            let state = synced_row(&engine, path).unwrap();
+            assert_eq!(
+                state.remote_etag.as_deref(),
+                dav.tree().etag(path),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(propfinds_after_transfers(&dav), 0);
+    }
+
+    #[tokio::test]
+    async fn transfer_etags_fall_back_to_a_propfind() {
+        let dav = FakeDav::start().await;
+        dav.tree().etag_headers = false;
+        dav.tree().put("down.txt", b"theirs");
+        let dir = test_dir("etag-propfind");
+        let (engine, config) = test_engine(&dav, &dir);
+        std::fs::create_dir_all(&config.personal_sync_path).unwrap();
+        std::fs::write(config.personal_sync_path.join("up.txt"), b"mine").unwrap();
+
+        sync_once(&engine, &config).await.unwrap();
+
+        for path in ["down.txt", "up.txt"] {
+            let state = synced_row(&engine, path).unwrap();
+            assert_eq!(
+                state.remote_etag.as_deref(),
+                dav.tree().etag(path),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(propfinds_after_transfers(&dav), 2);
+    }
+}
@@ -0,0 +1,505 @@
+//! In-memory WebDAV server on a local port, for tests of the transfer client and
+//! the sync engine. It keeps a tree of files with etags and answers PROPFIND, GET,
+//! PUT, DELETE, MKCOL, MOVE and COPY, honouring If-Match and If-None-Match, and
+//! assembles Nextcloud-style chunked uploads on a MOVE of `<upload dir>/.file`.
+//! sync-collection is not supported (REPORT answers 501).
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Server path of the zone the helpers work in.
+pub const ZONE_ROOT: &str = "dav/personal";
+/// Server path of the chunked uploads collection of the test user.
+pub const UPLOADS_ROOT: &str = "dav/uploads/u";
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Percent-decoded, without the leading slash, e.g. "dav/personal/a.txt"
+    pub path: String,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The path relative to the zone root, if it is below it.
+    pub fn zone_path(&self) -> Option<&str> {
+        self.path
+            .strip_prefix(ZONE_ROOT)
+            .map(|rest| rest.trim_start_matches('/'))
+    }
+}
+
+/// Files and folders on the server, by path without the leading slash.
+#[derive(Default)]
+pub struct Tree {
+    /// None for a folder
+    entries: BTreeMap<String, (Option<Vec<u8>>, String)>,
+    etags: u32,
+    /// Send ETag and OC-ETag headers on PUT, GET and MOVE responses
+    pub etag_headers: bool,
+}
+
+impl Tree {
+    fn next_etag(&mut self) -> String {
+        self.etags += 1;
+        format!("etag-{}", self.etags)
+    }
+
+    /// Create or replace a file in the zone, with a new etag.
+    pub fn put(&mut self, path: &str, body: &[u8]) -> String {
+        self.put_at(&zone(path), body.to_vec())
+    }
+
+    fn put_at(&mut self, path: &str, body: Vec<u8>) -> String {
+        let etag = self.next_etag();
+        self.entries
+            .insert(path.to_string(), (Some(body), etag.clone()));
+        etag
+    }
+
+    /// Create a folder in the zone.
+    pub fn mkdir(&mut self, path: &str) {
+        let etag = self.next_etag();
+        self.entries.insert(zone(path), (None, etag));
+    }
+
+    /// Content of a file in the zone.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.entries.get(&zone(path))?.0.as_deref()
+    }
+
+    /// Etag of a file or folder in the zone.
+    pub fn etag(&self, path: &str) -> Option<&str> {
+        self.entries.get(&zone(path)).map(|(_, etag)| etag.as_str())
+    }
+
+    /// Paths of everything below `dir`, which is left in place.
+    fn below(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{}/", dir);
+        self.entries
+            .keys()
+            .filter(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    fn remove_tree(&mut self, path: &str) {
+        for child in self.below(path) {
+            self.entries.remove(&child);
+        }
+        self.entries.remove(path);
+    }
+}
+
+fn zone(path: &str) -> String {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        ZONE_ROOT.to_string()
+    } else {
+        format!("{}/{}", ZONE_ROOT, path)
+    }
+}
+
+/// Answers a request before the server does; None lets the server handle it.
+type Hook = Box<dyn FnMut(&Request, &mut Tree) -> Option<Vec<u8>> + Send>;
+
+struct Shared {
+    tree: Tree,
+    requests: Vec<Request>,
+    hook: Option<Hook>,
+}
+
+/// A running fake WebDAV server; stops when dropped.
+pub struct FakeDav {
+    /// Server URL the zone and uploads paths are below
+    pub server_url: String,
+    /// Base URL of the zone
+    pub url: String,
+    /// Base URL of the chunked uploads collection
+    pub uploads_url: String,
+    shared: Arc<Mutex<Shared>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for FakeDav {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl FakeDav {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        let mut tree = Tree {
+            etag_headers: true,
+            ..Tree::default()
+        };
+        for dir in ["dav", ZONE_ROOT, "dav/shared", "dav/uploads", UPLOADS_ROOT] {
+            let etag = tree.next_etag();
+            tree.entries.insert(dir.to_string(), (None, etag));
+        }
+        let shared = Arc::new(Mutex::new(Shared {
+            tree,
+            requests: Vec::new(),
+            hook: None,
+        }));
+
+        let server = {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                loop {
+                    let Ok((socket, _)) = listener.accept().await else {
+                        return;
+                    };
+                    tokio::spawn(serve_connection(socket, shared.clone()));
+                }
+            })
+        };
+        Self {
+            url: format!("{}/{}", origin, ZONE_ROOT),
+            server_url: origin.clone(),
+            uploads_url: format!("{}/{}", origin, UPLOADS_ROOT),
+            shared,
+            server,
+        }
+    }
+
+    fn shared(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Look at or change the files on the server.
+    pub fn tree(&self) -> impl std::ops::DerefMut<Target = Tree> + '_ {
+        struct TreeGuard<'a>(MutexGuard<'a, Shared>);
+        impl std::ops::Deref for TreeGuard<'_> {
+            type Target = Tree;
+            fn deref(&self) -> &Tree {
+                &self.0.tree
+            }
+        }
+        impl std::ops::DerefMut for TreeGuard<'_> {
+            fn deref_mut(&mut self) -> &mut Tree {
+                &mut self.0.tree
+            }
+        }
+        TreeGuard(self.shared())
+    }
+
+    /// Answer requests with `hook` first, e.g. to fail some or change files
+    /// while the client is busy.
+    pub fn hook(&self, hook: impl FnMut(&Request, &mut Tree) -> Option<Vec<u8>> + Send + 'static) {
+        self.shared().hook = Some(Box::new(hook));
+    }
+
+    /// Every request so far as "METHOD path", zone paths relative to the zone.
+    pub fn requests(&self) -> Vec<String> {
+        self.shared()
+            .requests
+            .iter()
+            .map(|r| format!("{} {}", r.method, r.zone_path().unwrap_or(&r.path)))
+            .collect()
+    }
+
+    /// The requests so far, as received.
+    pub fn raw_requests(&self) -> Vec<Request> {
+        self.shared().requests.clone()
+    }
+}
+
+/// A response with a body and Content-Length.
+pub fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let mut response = head.into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+async fn serve_connection(mut socket: TcpStream, shared: Arc<Mutex<Shared>>) {
+    let Some(request) = read_request(&mut socket).await else {
+        return;
+    };
+    let reply = {
+        let mut shared = shared.lock().unwrap_or_else(|p| p.into_inner());
+        shared.requests.push(request.clone());
+        let Shared { tree, hook, .. } = &mut *shared;
+        match hook.as_mut().and_then(|hook| hook(&request, tree)) {
+            Some(reply) => reply,
+            None => handle(&request, tree),
+        }
+    };
+    let _ = socket.write_all(&reply).await;
+    let _ = socket.shutdown().await;
+}
+
+async fn read_request(socket: &mut TcpStream) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 64 * 1024];
+    let head_end = loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = socket.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut request = Request {
+        method,
+        path: url_path(target),
+        headers,
+        body: data[head_end..].to_vec(),
+    };
+
+    let length: usize = request
+        .header("Content-Length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while request.body.len() < length {
+        let n = socket.read(&mut buf).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        request.body.extend_from_slice(&buf[..n]);
+    }
+    Some(request)
+}
+
+/// Decoded path of a request target or URL, without the leading slash.
+fn url_path(target: &str) -> String {
+    let path = match target.find("://") {
+        Some(pos) => target[pos + 3..]
+            .find('/')
+            .map_or("/", |i| &target[pos + 3 + i..]),
+        None => target,
+    };
+    let path = path.split('?').next().unwrap_or(path);
+    urlencoding::decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+        .trim_matches('/')
+        .to_string()
+}
+
+/// Whether the If-Match / If-None-Match headers of `request` hold for the entry at
+/// `path`.
+fn preconditions_hold(request: &Request, tree: &Tree, path: &str) -> bool {
+    let current = tree.entries.get(path).map(|(_, etag)| etag.as_str());
+    if let Some(expected) = request.header("If-Match") {
+        if current != Some(expected.trim_matches('"')) {
+            return false;
+        }
+    }
+    if request.header("If-None-Match") == Some("*") && current.is_some() {
+        return false;
+    }
+    true
+}
+
+fn etag_headers(tree: &Tree, etag: &str) -> Vec<(&'static str, String)> {
+    if tree.etag_headers {
+        vec![
+            ("ETag", format!("\"{}\"", etag)),
+            ("OC-ETag", format!("\"{}\"", etag)),
+        ]
+    } else {
+        Vec::new()
+    }
+}
+
+fn reply(status: &str, headers: &[(&'static str, String)], body: &[u8]) -> Vec<u8> {
+    let headers: Vec<(&str, &str)> = headers.iter().map(|(n, v)| (*n, v.as_str())).collect();
+    response(status, &headers, body)
+}
+
+fn handle(request: &Request, tree: &mut Tree) -> Vec<u8> {
+    let path = request.path.clone();
+    match request.method.as_str() {
+        "PROPFIND" => match tree.entries.get(&path) {
+            Some(_) => {
+                let xml = multistatus(tree, &path);
+                response(
+                    "207 Multi-Status",
+                    &[("Content-Type", "application/xml")],
+                    xml.as_bytes(),
+                )
+            }
+            None => response("404 Not Found", &[], b""),
+        },
+        "GET" | "HEAD" => match tree.entries.get(&path) {
+            Some((Some(body), etag)) => {
+                let headers = etag_headers(tree, etag);
+                let body = if request.method == "GET" {
+                    body.as_slice()
+                } else {
+                    b""
+                };
+                reply("200 OK", &headers, body)
+            }
+            _ => response("404 Not Found", &[], b""),
+        },
+        "PUT" => {
+            if !preconditions_hold(request, tree, &path) {
+                return response("412 Precondition Failed", &[], b"");
+            }
+            let existed = tree.entries.contains_key(&path);
+            let etag = tree.put_at(&path, request.body.clone());
+            let status = if existed {
+                "204 No Content"
+            } else {
+                "201 Created"
+            };
+            reply(status, &etag_headers(tree, &etag), b"")
+        }
+        "DELETE" => {
+            if !tree.entries.contains_key(&path) {
+                return response("404 Not Found", &[], b"");
+            }
+            if !preconditions_hold(request, tree, &path) {
+                return response("412 Precondition Failed", &[], b"");
+            }
+            tree.remove_tree(&path);
+            response("204 No Content", &[], b"")
+        }
+        "MKCOL" => {
+            if tree.entries.contains_key(&path) {
+                return response("405 Method Not Allowed", &[], b"");
+            }
+            let etag = tree.next_etag();
+            tree.entries.insert(path, (None, etag));
+            response("201 Created", &[], b"")
+        }
+        "MOVE" | "COPY" => {
+            let Some(destination) = request.header("Destination").map(url_path) else {
+                return response("400 Bad Request", &[], b"");
+            };
+            if let Some(upload_dir) = path.strip_suffix("/.file") {
+                return assemble(request, tree, upload_dir, &destination);
+            }
+            if !tree.entries.contains_key(&path) {
+                return response("404 Not Found", &[], b"");
+            }
+            if !preconditions_hold(request, tree, &path) {
+                return response("412 Precondition Failed", &[], b"");
+            }
+            let existed = tree.entries.contains_key(&destination);
+            if existed && request.header("Overwrite") == Some("F") {
+                return response("412 Precondition Failed", &[], b"");
+            }
+            tree.remove_tree(&destination);
+            let mut moved = vec![path.clone()];
+            moved.extend(tree.below(&path));
+            for from in moved {
+                let to = format!("{}{}", destination, &from[path.len()..]);
+                let entry = if request.method == "MOVE" {
+                    tree.entries.remove(&from).unwrap()
+                } else {
+                    let (body, _) = tree.entries[&from].clone();
+                    (body, tree.next_etag())
+                };
+                tree.entries.insert(to, entry);
+            }
+            let etag = tree.entries[&destination].1.clone();
+            let status = if existed {
+                "204 No Content"
+            } else {
+                "201 Created"
+            };
+            reply(status, &etag_headers(tree, &etag), b"")
+        }
+        "REPORT" => response("501 Not Implemented", &[], b""),
+        _ => response("405 Method Not Allowed", &[], b""),
+    }
+}
+
+/// MOVE of `<upload dir>/.file`: the chunks, in name order, become the destination.
+fn assemble(request: &Request, tree: &mut Tree, upload_dir: &str, destination: &str) -> Vec<u8> {
+    if !tree.entries.contains_key(upload_dir) {
+        return response("404 Not Found", &[], b"");
+    }
+    if !preconditions_hold(request, tree, destination) {
+        return response("412 Precondition Failed", &[], b"");
+    }
+    let mut body = Vec::new();
+    for chunk in tree.below(upload_dir) {
+        body.extend_from_slice(tree.entries[&chunk].0.as_deref().unwrap_or_default());
+    }
+    tree.remove_tree(upload_dir);
+    let existed = tree.entries.contains_key(destination);
+    let etag = tree.put_at(destination, body);
+    let status = if existed {
+        "204 No Content"
+    } else {
+        "201 Created"
+    };
+    reply(status, &etag_headers(tree, &etag), b"")
+}
+
+/// Depth 1 PROPFIND answer for `path`: the entry itself and its direct children.
+fn multistatus(tree: &Tree, path: &str) -> String {
+    let mut members = vec![path.to_string()];
+    members.extend(
+        tree.below(path)
+            .into_iter()
+            .filter(|child| !child[path.len() + 1..].contains('/')),
+    );
+
+    let mut xml =
+        String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+    for member in members {
+        let (body, etag) = &tree.entries[&member];
+        let href: Vec<String> = member
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect();
+        let props = match body {
+            Some(body) => format!(
+                "<D:getcontentlength>{}</D:getcontentlength><D:resourcetype/>",
+                body.len()
+            ),
+            None => "<D:resourcetype><D:collection/></D:resourcetype>".to_string(),
+        };
+        xml.push_str(&format!(
+            "<D:response><D:href>/{}{}</D:href><D:propstat><D:prop>{}\
+             <D:getetag>\"{}\"</D:getetag>\
+             <D:getlastmodified>Mon, 12 Oct 2026 08:00:00 GMT</D:getlastmodified>\
+             </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+            href.join("/"),
+            if body.is_none() { "/" } else { "" },
+            props,
+            etag
+        ));
+    }
+    xml.push_str("</D:multistatus>");
+    xml
+}
@@ -1,10 +1,26 @@
 // Some fields/methods are part of the public API surface but not yet consumed.
 #![allow(dead_code)]
 
+use crate::bandwidth::BandwidthLimiter;
+use crate::db::{self, DbPool};
 use crate::error::{AppError, AppResult};
+use crate::multistatus;
 use reqwest::Client;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Suffix of the hidden file a download is written to before being renamed into place.
+pub const PARTIAL_SUFFIX: &str = ".veloryn-part";
+
+/// Size of one chunk of a chunked upload (Nextcloud requires at least 5 MB).
+const CHUNK_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Upload bodies are sent in pieces of this size, each waiting for the bandwidth limit.
+const UPLOAD_PIECE_SIZE: usize = 64 * 1024;
 
 /// WebDAV file transfer operations.
 /// All methods use Bearer token authentication.
@@ -12,22 +28,97 @@ pub struct WebDavTransfer {
     client: Client,
     base_url: String,
     token: String,
-    /// Upload speed limit in bytes/sec (0 = unlimited)
-    upload_limit_bps: u64,
-    /// Download speed limit in bytes/sec (0 = unlimited)
-    download_limit_bps: u64,
+    /// Upload/download limits shared with other transfers (None = unlimited)
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+    /// Where partial downloads are tracked so they can resume after a restart
+    db: Option<DbPool>,
+    /// Chunked upload settings (None = always a single PUT)
+    chunking: Option<ChunkedUploadConfig>,
+    /// Set once the server rejects chunked uploads, so further files go straight to PUT
+    chunking_unsupported: AtomicBool,
+    /// Remote directories known to exist, so parallel uploads skip redundant MKCOLs
+    known_dirs: Mutex<HashSet<String>>,
+    /// Cancels in-flight uploads and downloads
+    cancel: Option<CancellationToken>,
+    /// How failed requests are retried
+    retry: RetryPolicy,
+}
+
+/// Outcome of a `sync-collection` REPORT (RFC 6578).
+#[derive(Debug)]
+pub enum SyncCollection {
+    /// What changed since the token sent (everything, for an initial sync).
+    Changes(SyncCollectionChanges),
+    /// The server no longer accepts the token; start over with an initial sync.
+    TokenInvalid,
+    /// The server does not support the REPORT on this collection.
+    Unsupported,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncCollectionChanges {
+    /// Members added or modified, files and folders alike
+    pub changed: Vec<RemoteEntry>,
+    /// Hrefs of removed members (a removed folder may stand for all of its contents)
+    pub removed: Vec<String>,
+    /// Token to send next time
+    pub sync_token: String,
+    /// The server sent only part of the changes; ask again with `sync_token`
+    pub truncated: bool,
+}
+
+/// Nextcloud-style chunked uploads (chunking v2): chunks are PUT into a collection
+/// under `uploads_url`, then assembled with a MOVE onto the target.
+struct ChunkedUploadConfig {
+    uploads_url: String,
+    threshold_bytes: u64,
+    chunk_size: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct UploadResult {
     pub remote_path: String,
     pub bytes_sent: u64,
+    /// Etag of the stored file from the response headers, if the server sent one.
+    pub etag: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct DownloadResult {
     pub local_path: String,
     pub bytes_received: u64,
+    /// Etag of the downloaded version from the response headers, if any.
+    pub etag: Option<String>,
+    /// `Last-Modified` of the downloaded version, as sent.
+    pub last_modified: Option<String>,
+    /// Full size of the remote file as the server announced it.
+    pub content_length: Option<u64>,
+}
+
+/// Condition a write is sent with, so that it cannot overwrite or delete a remote
+/// version this client has not seen. A failed condition comes back as
+/// `AppError::PreconditionFailed` (HTTP 412).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePrecondition<'a> {
+    /// Unconditional write.
+    None,
+    /// The remote file must still have this etag (`If-Match`).
+    Etag(&'a str),
+    /// The remote file must not exist yet (`If-None-Match: *`).
+    Absent,
+}
+
+impl WritePrecondition<'_> {
+    /// Header name and value to send, if any.
+    fn header(self) -> Option<(&'static str, String)> {
+        match self {
+            Self::None => None,
+            // If-Match compares strongly, a weak etag would never match
+            Self::Etag(etag) if etag.starts_with("W/") => None,
+            Self::Etag(etag) => Some(("If-Match", format!("\"{}\"", etag.trim_matches('"')))),
+            Self::Absent => Some(("If-None-Match", "*".to_string())),
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -38,23 +129,73 @@ pub struct RemoteEntry {
     pub size: Option<u64>,
     pub etag: Option<String>,
     pub last_modified: Option<String>,
+    /// Raw `oc:checksums` value, e.g. "SHA1:… BLAKE3:…" (when the server provides it)
+    pub checksum: Option<String>,
+    /// `getctag` of a collection: changes whenever anything inside it does
+    pub ctag: Option<String>,
+    pub content_type: Option<String>,
+    /// Server-side file id (`oc:fileid`), stable across renames
+    pub file_id: Option<String>,
+    /// Share permissions (`oc:permissions`), e.g. "RGDNVW"
+    pub permissions: Option<String>,
+}
+
+impl RemoteEntry {
+    /// Tag that changes whenever anything inside this collection changes: the
+    /// ctag, or the etag on Nextcloud/ownCloud (told apart by their `oc:`
+    /// properties), where it is recursive. Elsewhere, e.g. Apache mod_dav, a
+    /// collection's etag only covers its direct members, so there is no tag.
+    pub fn collection_tag(&self) -> Option<&str> {
+        let recursive_etag = self.file_id.is_some() || self.permissions.is_some();
+        self.ctag
+            .as_deref()
+            .or(self.etag.as_deref().filter(|_| recursive_etag))
+    }
+}
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts per request, the first one included
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles for each one after it
+    pub base_delay: Duration,
+    /// Upper bound for the delay, and for a `Retry-After` the server asks for
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay after failed attempt number `attempt`: the exponential step, capped,
+    /// then a random point in its upper half.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let step = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        step / 2 + (step / 2).mul_f64(jitter())
+    }
+}
+
+/// A collection listed with PROPFIND Depth: 1.
+#[derive(Debug, Clone, Default)]
+pub struct DirListing {
+    /// The collection itself
+    pub collection: Option<RemoteEntry>,
+    pub children: Vec<RemoteEntry>,
 }
 
 impl WebDavTransfer {
     /// Create a new transfer client.
     /// base_url: e.g. "https://dev.veloryn.pl/backend/dav/personal"
     pub fn new(base_url: &str, token: &str) -> Self {
-        Self::new_with_limits(base_url, token, 0, 0)
-    }
-
-    /// Create a new transfer client with bandwidth limits.
-    /// Limits are in KB/s (0 = unlimited).
-    pub fn new_with_limits(
-        base_url: &str,
-        token: &str,
-        upload_limit_kbps: u64,
-        download_limit_kbps: u64,
-    ) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(300))
             .build()
@@ -63,20 +204,65 @@ impl WebDavTransfer {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             token: token.to_string(),
-            upload_limit_bps: upload_limit_kbps * 1024,
-            download_limit_bps: download_limit_kbps * 1024,
+            bandwidth: None,
+            db: None,
+            chunking: None,
+            chunking_unsupported: AtomicBool::new(false),
+            known_dirs: Mutex::new(HashSet::new()),
+            cancel: None,
+            retry: RetryPolicy::default(),
         }
     }
 
-    /// Apply rate limiting by sleeping proportionally to data transferred.
-    async fn rate_limit(&self, bytes: u64, limit_bps: u64) {
-        if limit_bps == 0 || bytes == 0 {
-            return;
+    /// Persist partial downloads in the sync DB, so an interrupted download
+    /// continues with a Range request on the next sync instead of starting over.
+    pub fn with_db(mut self, db: DbPool) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Throttle uploads and downloads to the limits of `bandwidth`, as the data is
+    /// sent and received. Transfers sharing one limiter share its rate.
+    pub fn with_bandwidth_limiter(mut self, bandwidth: Arc<BandwidthLimiter>) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    /// Retry failed requests as `policy` says instead of the default.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Abort uploads and downloads as soon as `cancel` is triggered.
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Run `fut` unless the transfer is cancelled first, in which case it is dropped
+    /// (closing its connection) and `AppError::Cancelled` is returned.
+    async fn cancellable<T>(&self, fut: impl std::future::Future<Output = AppResult<T>>) -> AppResult<T> {
+        match &self.cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.cancelled() => Err(AppError::cancelled()),
+                result = fut => result,
+            },
+            None => fut.await,
         }
-        let expected_duration_ms = (bytes as f64 / limit_bps as f64 * 1000.0) as u64;
-        if expected_duration_ms > 0 {
-            tokio::time::sleep(Duration::from_millis(expected_duration_ms)).await;
+    }
+
+    /// Upload files larger than `threshold_bytes` in chunks via the `uploads_url`
+    /// collection (0 = never). With a DB attached, chunk progress survives restarts.
+    pub fn with_chunked_uploads(mut self, uploads_url: &str, threshold_bytes: u64) -> Self {
+        if threshold_bytes > 0 {
+            self.chunking = Some(ChunkedUploadConfig {
+                uploads_url: uploads_url.trim_end_matches('/').to_string(),
+                threshold_bytes,
+                chunk_size: CHUNK_SIZE,
+            });
         }
+        self
     }
 
     fn build_url(&self, remote_path: &str) -> String {
@@ -95,11 +281,19 @@ impl WebDavTransfer {
         local_path: &Path,
         remote_path: &str,
     ) -> AppResult<UploadResult> {
-        let bytes = tokio::fs::read(local_path)
+        self.upload_file_if(local_path, remote_path, WritePrecondition::None)
             .await
-            .map_err(|e| AppError::io(format!("Failed to read file {}: {}", local_path.display(), e)))?;
-        let bytes_len = bytes.len() as u64;
+    }
 
+    /// Upload a local file only if the remote matches `precondition`. A remote that
+    /// changed meanwhile makes the server answer 412, returned as
+    /// `AppError::PreconditionFailed`.
+    pub async fn upload_file_if(
+        &self,
+        local_path: &Path,
+        remote_path: &str,
+        precondition: WritePrecondition<'_>,
+    ) -> AppResult<UploadResult> {
         // Ensure parent directory (and all ancestors) exist on remote.
         // WebDAV MKCOL is not recursive, so we create each segment in turn.
         if let Some((parent, _)) = remote_path.trim_start_matches('/').rsplit_once('/') {
@@ -108,49 +302,63 @@ impl WebDavTransfer {
             }
         }
 
+        let chunked = self.upload_chunked(local_path, remote_path, precondition);
+        if let Some(result) = self.cancellable(chunked).await? {
+            return Ok(result);
+        }
+
         let url = self.build_url(remote_path);
-        let result = retry_request(3, |attempt| {
+        let put = retry_request(self.retry, |attempt| {
             let url = url.clone();
             let auth = self.auth_header();
-            let bytes = bytes.clone();
             let client = self.client.clone();
+            let condition = precondition.header();
+            let bandwidth = self.bandwidth.clone();
             async move {
                 log::debug!("upload_file attempt {}: PUT {}", attempt, url);
-                let resp = client
+                // Re-open on every attempt: a streamed body is consumed by the failed request
+                let (body, len) = open_upload_body(local_path, bandwidth).await?;
+                let mut req = client
                     .put(&url)
                     .header("Authorization", &auth)
                     .header("Content-Type", "application/octet-stream")
-                    .body(bytes)
+                    .header("Content-Length", len);
+                if let Some((name, value)) = &condition {
+                    req = req.header(*name, value);
+                }
+                let resp = req
+                    .body(body)
                     .send()
                     .await
-                    .map_err(|e| AppError::network(format!("Network error uploading {}: {}", url, e)))?;
+                    .map_err(|e| RetryableError::transport(format!("Network error uploading {}", url), e))?;
 
                 let status = resp.status();
                 if status.is_success() || status.as_u16() == 201 || status.as_u16() == 204 {
-                    Ok(())
+                    Ok((len, response_etag(&resp)))
+                } else if status.as_u16() == 412 {
+                    Err(AppError::precondition_failed(format!("HTTP {}: {}", status, url)).into())
                 } else {
-                    Err(RetryableError {
-                        error: AppError::network(format!("HTTP {}: {}", status, url)),
-                        retryable: status.as_u16() >= 500,
-                    })
+                    Err(RetryableError::status(&resp, &url))
                 }
             }
-        })
-        .await;
+        });
+        let result = self.cancellable(put).await;
 
-        // Apply bandwidth throttling after successful transfer
-        if result.is_ok() {
-            self.rate_limit(bytes_len, self.upload_limit_bps).await;
-        }
-
-        result.map(|_| UploadResult {
+        result.map(|(bytes_sent, etag)| UploadResult {
             remote_path: remote_path.to_string(),
-            bytes_sent: bytes_len,
+            bytes_sent,
+            etag,
         })
     }
 
     /// Download a file from WebDAV to local path.
-    /// Uses HTTP GET, streams response to file.
+    /// Uses HTTP GET and streams the response into a hidden partial file next to the
+    /// target, which is verified, fsynced and only then renamed over `local_path`.
+    /// An interrupted download never leaves a truncated file at the real path.
+    ///
+    /// The partial file is kept together with the etag of the version it holds, and
+    /// retries (or the next sync) continue it with `Range` + `If-Range`. A server that
+    /// answers 200 instead of 206, e.g. because the etag changed, restarts it.
     pub async fn download_file(
         &self,
         remote_path: &str,
@@ -163,95 +371,526 @@ impl WebDavTransfer {
                 .map_err(|e| AppError::io(format!("Failed to create directory {}: {}", parent.display(), e)))?;
         }
 
+        let partial = partial_path(local_path);
+        let key = local_path.to_string_lossy().to_string();
+        // Etag of the remote version the partial file holds (None = start from scratch)
+        let resume_etag = Mutex::new(self.stored_partial_etag(&key, &partial));
+
         let url = self.build_url(remote_path);
-        let bytes = retry_request(3, |attempt| {
+        let get = retry_request(self.retry, |attempt| {
+            let partial = partial.clone();
             let url = url.clone();
             let auth = self.auth_header();
             let client = self.client.clone();
+            let (key, resume_etag) = (&key, &resume_etag);
             async move {
-                log::debug!("download_file attempt {}: GET {}", attempt, url);
-                let resp = client
-                    .get(&url)
-                    .header("Authorization", &auth)
+                let resume = lock_etag(resume_etag).clone();
+                let offset = match resume {
+                    Some(_) => tokio::fs::metadata(&partial).await.map(|m| m.len()).unwrap_or(0),
+                    None => 0,
+                };
+
+                let mut req = client.get(&url).header("Authorization", &auth);
+                match &resume {
+                    Some(etag) if offset > 0 => {
+                        log::debug!(
+                            "download_file attempt {}: GET {} from byte {}",
+                            attempt,
+                            url,
+                            offset
+                        );
+                        req = req
+                            .header("Range", format!("bytes={}-", offset))
+                            .header("If-Range", format!("\"{}\"", etag));
+                    }
+                    _ => log::debug!("download_file attempt {}: GET {}", attempt, url),
+                }
+                let resp = req
                     .send()
                     .await
-                    .map_err(|e| AppError::network(format!("Network error downloading {}: {}", url, e)))?;
+                    .map_err(|e| RetryableError::transport(format!("Network error downloading {}", url), e))?;
 
                 let status = resp.status();
+                let resumed = status.as_u16() == 206;
+                if status.as_u16() == 416 || (resumed && content_range_start(&resp) != Some(offset)) {
+                    // The partial file does not fit the remote one: start over
+                    *lock_etag(resume_etag) = None;
+                    return Err(RetryableError::transient(AppError::network(format!(
+                        "HTTP {} resuming {}",
+                        status, url
+                    ))));
+                }
                 if !status.is_success() {
-                    return Err(RetryableError {
-                        error: AppError::network(format!("HTTP {}: {}", status, url)),
-                        retryable: status.as_u16() >= 500,
-                    });
+                    return Err(RetryableError::status(&resp, &url));
                 }
 
-                let bytes = resp
-                    .bytes()
-                    .await
-                    .map_err(|e| AppError::network(format!("Failed to read response body: {}", e)))?;
-                Ok(bytes)
+                if !resumed {
+                    // Full body: the partial file now holds this version
+                    let etag = strong_etag(&resp);
+                    self.remember_partial(key, etag.as_deref());
+                    *lock_etag(resume_etag) = etag;
+                }
+
+                let version = DownloadedVersion {
+                    // A 206 passed If-Range, so it is the version the partial file holds
+                    etag: response_etag(&resp).or_else(|| lock_etag(resume_etag).clone()),
+                    last_modified: header_str(&resp, "Last-Modified").map(str::to_string),
+                    content_length: if resumed {
+                        content_range_total(&resp)
+                    } else {
+                        resp.content_length()
+                    },
+                };
+                let offset = if resumed { offset } else { 0 };
+                let len =
+                    write_response_body(resp, &partial, offset, self.bandwidth.as_deref()).await?;
+                Ok((len, version))
             }
-        })
-        .await?;
+        });
+        let downloaded = self.cancellable(get).await;
 
-        let bytes_len = bytes.len() as u64;
-        tokio::fs::write(local_path, &bytes)
-            .await
-            .map_err(|e| AppError::io(format!("Failed to write file {}: {}", local_path.display(), e)))?;
+        let (bytes_len, version) = match downloaded {
+            Ok(downloaded) => downloaded,
+            Err(e) => {
+                // Keep what we have if it can be resumed later, otherwise drop it
+                if e.is_cancelled() || self.db.is_none() || lock_etag(&resume_etag).is_none() {
+                    let _ = tokio::fs::remove_file(&partial).await;
+                    self.remember_partial(&key, None);
+                }
+                return Err(e);
+            }
+        };
 
-        // Apply bandwidth throttling after successful transfer
-        self.rate_limit(bytes_len, self.download_limit_bps).await;
+        self.remember_partial(&key, None);
+        if let Err(e) = tokio::fs::rename(&partial, local_path).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(AppError::io(format!(
+                "Failed to move {} into place: {}",
+                local_path.display(),
+                e
+            )));
+        }
 
         Ok(DownloadResult {
             local_path: local_path.to_string_lossy().to_string(),
             bytes_received: bytes_len,
+            etag: version.etag,
+            last_modified: version.last_modified,
+            content_length: version.content_length,
+        })
+    }
+
+    /// Etag recorded for an existing partial download of `key`, if it can be resumed.
+    fn stored_partial_etag(&self, key: &str, partial: &Path) -> Option<String> {
+        let db = self.db.as_ref()?;
+        let etag = db::get_partial_download(db, key).ok().flatten()?;
+        if partial.is_file() {
+            Some(etag)
+        } else {
+            let _ = db::delete_partial_download(db, key);
+            None
+        }
+    }
+
+    /// Record (or with `None`, forget) which remote version a partial download holds.
+    fn remember_partial(&self, key: &str, etag: Option<&str>) {
+        let Some(db) = &self.db else { return };
+        let result = match etag {
+            Some(etag) => db::save_partial_download(db, key, etag),
+            None => db::delete_partial_download(db, key),
+        };
+        if let Err(e) = result {
+            log::warn!("Cannot update partial download state for {}: {}", key, e);
+        }
+    }
+
+    /// Upload a large file in chunks, resuming a previously interrupted upload of the
+    /// same file version. Returns None when chunking does not apply (disabled, file
+    /// below the threshold, or the server does not support it) and a plain PUT is needed.
+    async fn upload_chunked(
+        &self,
+        local_path: &Path,
+        remote_path: &str,
+        precondition: WritePrecondition<'_>,
+    ) -> AppResult<Option<UploadResult>> {
+        let Some(chunking) = &self.chunking else { return Ok(None) };
+        if self.chunking_unsupported.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let metadata = tokio::fs::metadata(local_path)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to stat file {}: {}", local_path.display(), e)))?;
+        let size = metadata.len();
+        if size <= chunking.threshold_bytes {
+            return Ok(None);
+        }
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let key = local_path.to_string_lossy().to_string();
+        let remote_url = self.build_url(remote_path);
+        let stored = self
+            .db
+            .as_ref()
+            .and_then(|db| db::get_chunked_upload(db, &key).ok().flatten());
+
+        let mut upload = match stored {
+            Some(upload)
+                if upload.remote_url == remote_url
+                    && upload.file_size == size as i64
+                    && upload.file_mtime == mtime
+                    && upload.chunk_size == chunking.chunk_size as i64 =>
+            {
+                log::info!(
+                    "Resuming chunked upload of {} at chunk {}",
+                    local_path.display(),
+                    upload.chunks_done + 1
+                );
+                upload
+            }
+            stale => {
+                // The file changed since the interrupted upload: its chunks are useless
+                if let Some(old) = stale {
+                    self.delete_upload_dir(&old.upload_url).await;
+                }
+                let upload_url = format!("{}/{}", chunking.uploads_url, new_transfer_id(&key));
+                if !self.create_upload_dir(&upload_url, &remote_url).await? {
+                    log::info!("Server does not support chunked uploads, using plain PUT");
+                    self.chunking_unsupported.store(true, Ordering::Relaxed);
+                    self.forget_chunked_upload(&key);
+                    return Ok(None);
+                }
+                let upload = db::ChunkedUpload {
+                    local_path: key.clone(),
+                    remote_url: remote_url.clone(),
+                    upload_url,
+                    file_size: size as i64,
+                    file_mtime: mtime,
+                    chunk_size: chunking.chunk_size as i64,
+                    chunks_done: 0,
+                };
+                if let Some(db) = &self.db {
+                    if let Err(e) = db::save_chunked_upload(db, &upload) {
+                        log::warn!("Cannot persist chunked upload of {}: {}", key, e);
+                    }
+                }
+                upload
+            }
+        };
+
+        // Only what goes over the wire now, not the chunks of an earlier attempt
+        let mut bytes_sent = 0;
+        let chunk_size = chunking.chunk_size;
+        let total_chunks = size.div_ceil(chunk_size);
+        for index in upload.chunks_done as u64..total_chunks {
+            let offset = index * chunk_size;
+            let len = chunk_size.min(size - offset);
+            let chunk = read_chunk(local_path, offset, len).await?;
+            let chunk_url = format!("{}/{:05}", upload.upload_url, index + 1);
+
+            retry_request(self.retry, |attempt| {
+                let chunk_url = chunk_url.clone();
+                let remote_url = remote_url.clone();
+                let auth = self.auth_header();
+                let client = self.client.clone();
+                let body = upload_body(std::io::Cursor::new(chunk.clone()), self.bandwidth.clone());
+                let key = &key;
+                async move {
+                    log::debug!("upload chunk attempt {}: PUT {}", attempt, chunk_url);
+                    let resp = client
+                        .put(&chunk_url)
+                        .header("Authorization", &auth)
+                        .header("Destination", &remote_url)
+                        .header("OC-Total-Length", size)
+                        .header("Content-Length", len)
+                        .body(body)
+                        .send()
+                        .await
+                        .map_err(|e| RetryableError::transport(format!("Network error uploading {}", chunk_url), e))?;
+
+                    let status = resp.status();
+                    if status.is_success() {
+                        return Ok(());
+                    }
+                    if status.as_u16() == 404 {
+                        // Upload collection expired on the server: start over next time
+                        self.forget_chunked_upload(key);
+                    }
+                    Err(RetryableError::status(&resp, &chunk_url))
+                }
+            })
+            .await?;
+
+            bytes_sent += len;
+            upload.chunks_done = index as i64 + 1;
+            if let Some(db) = &self.db {
+                let _ = db::set_chunked_upload_progress(db, &key, upload.chunks_done);
+            }
+        }
+
+        // Assemble the chunks onto the target
+        let assemble_url = format!("{}/.file", upload.upload_url);
+        let refused = AtomicBool::new(false);
+        let assembled = retry_request(self.retry, |attempt| {
+            let assemble_url = assemble_url.clone();
+            let remote_url = remote_url.clone();
+            let auth = self.auth_header();
+            let client = self.client.clone();
+            let condition = precondition.header();
+            let refused = &refused;
+            async move {
+                log::debug!("upload assemble attempt {}: MOVE {}", attempt, assemble_url);
+                let mut req = client
+                    .request(reqwest::Method::from_bytes(b"MOVE").unwrap(), &assemble_url)
+                    .header("Authorization", &auth)
+                    .header("Destination", &remote_url)
+                    .header("Overwrite", "T")
+                    .header("OC-Total-Length", size);
+                // Checked by the server against the destination file
+                if let Some((name, value)) = &condition {
+                    req = req.header(*name, value);
+                }
+                let resp = req
+                    .send()
+                    .await
+                    .map_err(|e| RetryableError::transport(format!("Network error in MOVE {}", assemble_url), e))?;
+
+                let status = resp.status();
+                if status.is_success() {
+                    return Ok(response_etag(&resp));
+                }
+                let error = if status.as_u16() == 412 {
+                    AppError::precondition_failed(format!("HTTP {}: {}", status, remote_url)).into()
+                } else {
+                    RetryableError::status(&resp, &remote_url)
+                };
+                if !error.retryable {
+                    refused.store(true, Ordering::Relaxed);
+                }
+                Err(error)
+            }
+        })
+        .await;
+
+        match &assembled {
+            Ok(_) => self.forget_chunked_upload(&key),
+            // The chunks cannot be reused once assembly was refused (e.g. 412)
+            Err(_) if refused.load(Ordering::Relaxed) => {
+                self.delete_upload_dir(&upload.upload_url).await;
+                self.forget_chunked_upload(&key);
+            }
+            // Server or network trouble: the next attempt only repeats the MOVE
+            Err(_) => {}
+        }
+        let etag = assembled?;
+
+        Ok(Some(UploadResult {
+            remote_path: remote_path.to_string(),
+            bytes_sent,
+            etag,
+        }))
+    }
+
+    /// Create the upload collection for a chunked upload. Returns false if the
+    /// server has no chunked upload support (404, 405 or 501); any other refusal
+    /// is an error.
+    async fn create_upload_dir(&self, upload_url: &str, remote_url: &str) -> AppResult<bool> {
+        retry_request(self.retry, |attempt| {
+            let upload_url = upload_url.to_string();
+            let auth = self.auth_header();
+            let client = self.client.clone();
+            async move {
+                log::debug!("create upload dir attempt {}: MKCOL {}", attempt, upload_url);
+                let resp = client
+                    .request(reqwest::Method::from_bytes(b"MKCOL").unwrap(), &upload_url)
+                    .header("Authorization", &auth)
+                    .header("Destination", remote_url)
+                    .send()
+                    .await
+                    .map_err(|e| RetryableError::transport(format!("Network error in MKCOL {}", upload_url), e))?;
+
+                match resp.status().as_u16() {
+                    201 => Ok(true),
+                    404 | 405 | 501 => Ok(false),
+                    _ => Err(RetryableError::status(&resp, &upload_url)),
+                }
+            }
         })
+        .await
+    }
+
+    /// Best-effort removal of an abandoned upload collection.
+    async fn delete_upload_dir(&self, upload_url: &str) {
+        let result = self
+            .client
+            .delete(upload_url)
+            .header("Authorization", self.auth_header())
+            .send()
+            .await;
+        if let Err(e) = result {
+            log::debug!("Cannot delete upload dir {}: {}", upload_url, e);
+        }
+    }
+
+    fn forget_chunked_upload(&self, key: &str) {
+        if let Some(db) = &self.db {
+            if let Err(e) = db::delete_chunked_upload(db, key) {
+                log::warn!("Cannot clear chunked upload state for {}: {}", key, e);
+            }
+        }
     }
 
     /// Delete a remote file/directory via WebDAV DELETE.
     pub async fn delete_remote(&self, remote_path: &str) -> AppResult<()> {
+        self.delete_remote_if(remote_path, WritePrecondition::None)
+            .await
+    }
+
+    /// Delete a remote file only if it matches `precondition`, so a version changed
+    /// on the server meanwhile is not lost (412, `AppError::PreconditionFailed`).
+    pub async fn delete_remote_if(
+        &self,
+        remote_path: &str,
+        precondition: WritePrecondition<'_>,
+    ) -> AppResult<()> {
         let url = self.build_url(remote_path);
-        retry_request(3, |attempt| {
+        retry_request(self.retry, |attempt| {
             let url = url.clone();
             let auth = self.auth_header();
             let client = self.client.clone();
+            let condition = precondition.header();
             async move {
                 log::debug!("delete_remote attempt {}: DELETE {}", attempt, url);
-                let resp = client
-                    .delete(&url)
-                    .header("Authorization", &auth)
+                let mut req = client.delete(&url).header("Authorization", &auth);
+                if let Some((name, value)) = &condition {
+                    req = req.header(*name, value);
+                }
+                let resp = req
                     .send()
                     .await
-                    .map_err(|e| AppError::network(format!("Network error deleting {}: {}", url, e)))?;
+                    .map_err(|e| RetryableError::transport(format!("Network error deleting {}", url), e))?;
 
                 let status = resp.status();
                 match status.as_u16() {
                     200 | 204 | 404 => Ok(()),
-                    _ => Err(RetryableError {
-                        error: AppError::network(format!("HTTP {}: {}", status, url)),
-                        retryable: status.as_u16() >= 500,
-                    }),
+                    412 => Err(AppError::precondition_failed(format!("HTTP {}: {}", status, url)).into()),
+                    _ => Err(RetryableError::status(&resp, &url)),
                 }
             }
         })
         .await
     }
 
+    /// Copy a file on the server via WebDAV COPY, without sending its content.
+    /// `source` is checked against the file copied from, so only a known version
+    /// is copied. Never overwrites: an existing destination fails with 412.
+    pub async fn copy_remote(
+        &self,
+        from_path: &str,
+        to_path: &str,
+        source: WritePrecondition<'_>,
+    ) -> AppResult<()> {
+        let url = self.build_url(from_path);
+        let destination = self.build_url(to_path);
+        retry_request(self.retry, |attempt| {
+            let url = url.clone();
+            let destination = destination.clone();
+            let auth = self.auth_header();
+            let client = self.client.clone();
+            let condition = source.header();
+            async move {
+                log::debug!("copy_remote attempt {}: COPY {} -> {}", attempt, url, destination);
+                let mut req = client
+                    .request(reqwest::Method::from_bytes(b"COPY").unwrap(), &url)
+                    .header("Authorization", &auth)
+                    .header("Destination", &destination)
+                    .header("Overwrite", "F");
+                if let Some((name, value)) = &condition {
+                    req = req.header(*name, value);
+                }
+                let resp = req
+                    .send()
+                    .await
+                    .map_err(|e| RetryableError::transport(format!("Network error in COPY {}", url), e))?;
+
+                let status = resp.status();
+                match status.as_u16() {
+                    201 | 204 => Ok(()),
+                    _ => Err(RetryableError::status(&resp, &url)),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Move or rename a file or collection via WebDAV MOVE. Never overwrites:
+    /// an existing destination fails with 412 instead of being replaced.
+    pub async fn move_remote(&self, from_path: &str, to_path: &str) -> AppResult<()> {
+        let url = self.build_url(from_path);
+        let destination = self.build_url(to_path);
+        retry_request(self.retry, |attempt| {
+            let url = url.clone();
+            let destination = destination.clone();
+            let auth = self.auth_header();
+            let client = self.client.clone();
+            async move {
+                log::debug!("move_remote attempt {}: MOVE {} -> {}", attempt, url, destination);
+                let resp = client
+                    .request(reqwest::Method::from_bytes(b"MOVE").unwrap(), &url)
+                    .header("Authorization", &auth)
+                    .header("Destination", &destination)
+                    .header("Overwrite", "F")
+                    .send()
+                    .await
+                    .map_err(|e| RetryableError::transport(format!("Network error in MOVE {}", url), e))?;
+
+                let status = resp.status();
+                match status.as_u16() {
+                    201 | 204 => Ok(()),
+                    _ => Err(RetryableError::status(&resp, &url)),
+                }
+            }
+        })
+        .await?;
+
+        // Collections created under the old path no longer exist there
+        let from = from_path.trim_matches('/');
+        let prefix = format!("{}/", from);
+        self.known_dirs_guard()
+            .retain(|dir| dir != from && !dir.starts_with(&prefix));
+        Ok(())
+    }
+
     /// List directory contents via WebDAV PROPFIND (depth 1).
     /// Returns file/directory metadata for sync comparison.
     pub async fn propfind(&self, remote_path: &str) -> AppResult<Vec<RemoteEntry>> {
+        Ok(self.propfind_dir(remote_path).await?.children)
+    }
+
+    /// Like `propfind`, also returning the entry of the directory itself.
+    pub async fn propfind_dir(&self, remote_path: &str) -> AppResult<DirListing> {
         const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
-<D:propfind xmlns:D="DAV:">
+<D:propfind xmlns:D="DAV:" xmlns:oc="http://owncloud.org/ns" xmlns:cs="http://calendarserver.org/ns/">
   <D:prop>
     <D:getcontentlength/>
+    <D:getcontenttype/>
     <D:getetag/>
     <D:getlastmodified/>
     <D:resourcetype/>
+    <oc:checksums/>
+    <oc:fileid/>
+    <oc:permissions/>
+    <cs:getctag/>
   </D:prop>
 </D:propfind>"#;
 
         let url = self.build_url(remote_path);
-        let body = retry_request(3, |attempt| {
+        let body = retry_request(self.retry, |attempt| {
             let url = url.clone();
             let auth = self.auth_header();
             let client = self.client.clone();
@@ -265,32 +904,101 @@ impl WebDavTransfer {
                     .body(PROPFIND_BODY)
                     .send()
                     .await
-                    .map_err(|e| AppError::network(format!("Network error in PROPFIND {}: {}", url, e)))?;
+                    .map_err(|e| RetryableError::transport(format!("Network error in PROPFIND {}", url), e))?;
 
                 let status = resp.status();
                 if status.as_u16() != 207 && !status.is_success() {
-                    return Err(RetryableError {
-                        error: AppError::network(format!("HTTP {}: {}", status, url)),
-                        retryable: status.as_u16() >= 500,
-                    });
+                    return Err(RetryableError::status(&resp, &url));
                 }
 
                 let text = resp
                     .text()
                     .await
-                    .map_err(|e| AppError::network(format!("Failed to read PROPFIND response: {}", e)))?;
+                    .map_err(|e| RetryableError::transport("Failed to read PROPFIND response", e))?;
                 Ok(text)
             }
         })
         .await?;
 
-        parse_propfind_response(&body, remote_path)
+        parse_propfind_response(&body, &url)
+    }
+
+    /// Changes to the whole tree since `sync_token` via a `sync-collection` REPORT
+    /// (RFC 6578); without a token, an initial sync listing every member.
+    pub async fn sync_collection(&self, sync_token: Option<&str>) -> AppResult<SyncCollection> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<D:sync-collection xmlns:D="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <D:sync-token>{}</D:sync-token>
+  <D:sync-level>infinite</D:sync-level>
+  <D:prop>
+    <D:getcontentlength/>
+    <D:getcontenttype/>
+    <D:getetag/>
+    <D:getlastmodified/>
+    <D:resourcetype/>
+    <oc:checksums/>
+    <oc:fileid/>
+    <oc:permissions/>
+  </D:prop>
+</D:sync-collection>"#,
+            xml_escape(sync_token.unwrap_or_default())
+        );
+
+        let url = self.build_url("");
+        let (status, text) = retry_request(self.retry, |attempt| {
+            let url = url.clone();
+            let auth = self.auth_header();
+            let client = self.client.clone();
+            let body = body.clone();
+            async move {
+                log::debug!("sync_collection attempt {}: REPORT {}", attempt, url);
+                let resp = client
+                    .request(reqwest::Method::from_bytes(b"REPORT").unwrap(), &url)
+                    .header("Authorization", &auth)
+                    .header("Depth", "0")
+                    .header("Content-Type", "application/xml; charset=utf-8")
+                    .body(body)
+                    .send()
+                    .await
+                    .map_err(|e| RetryableError::transport(format!("Network error in REPORT {}", url), e))?;
+
+                let status = resp.status();
+                if is_retryable_status(status.as_u16()) {
+                    return Err(RetryableError::status(&resp, &url));
+                }
+
+                let text = resp
+                    .text()
+                    .await
+                    .map_err(|e| RetryableError::transport("Failed to read REPORT response", e))?;
+                Ok((status.as_u16(), text))
+            }
+        })
+        .await?;
+
+        match status {
+            200 | 207 => Ok(SyncCollection::Changes(parse_sync_collection_response(&text)?)),
+            // The DAV:valid-sync-token precondition failed
+            403 | 409
+                if multistatus::parse_error_conditions(&text)
+                    .iter()
+                    .any(|c| c == "valid-sync-token") =>
+            {
+                Ok(SyncCollection::TokenInvalid)
+            }
+            401 => Err(AppError::network(format!("HTTP {}: {}", status, url))),
+            _ => {
+                log::debug!("sync-collection not supported (HTTP {}): {}", status, url);
+                Ok(SyncCollection::Unsupported)
+            }
+        }
     }
 
     /// Create a remote directory via WebDAV MKCOL.
     pub async fn mkcol(&self, remote_path: &str) -> AppResult<()> {
         let url = self.build_url(remote_path);
-        retry_request(3, |attempt| {
+        retry_request(self.retry, |attempt| {
             let url = url.clone();
             let auth = self.auth_header();
             let client = self.client.clone();
@@ -301,16 +1009,13 @@ impl WebDavTransfer {
                     .header("Authorization", &auth)
                     .send()
                     .await
-                    .map_err(|e| AppError::network(format!("Network error in MKCOL {}: {}", url, e)))?;
+                    .map_err(|e| RetryableError::transport(format!("Network error in MKCOL {}", url), e))?;
 
                 let status = resp.status();
                 match status.as_u16() {
                     // 201 = created, 405 = already exists (Method Not Allowed on existing collection)
                     201 | 405 => Ok(()),
-                    _ => Err(RetryableError {
-                        error: AppError::network(format!("HTTP {}: {}", status, url)),
-                        retryable: status.as_u16() >= 500,
-                    }),
+                    _ => Err(RetryableError::status(&resp, &url)),
                 }
             }
         })
@@ -334,11 +1039,22 @@ impl WebDavTransfer {
                 current.push('/');
             }
             current.push_str(seg);
+            if self.known_dirs_guard().contains(&current) {
+                continue;
+            }
             // Best-effort: ignore errors on intermediate levels.
-            let _ = self.mkcol(&current).await;
+            if self.mkcol(&current).await.is_ok() {
+                self.known_dirs_guard().insert(current.clone());
+            }
         }
         Ok(())
     }
+
+    fn known_dirs_guard(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.known_dirs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
 
 // ==================== Helpers ====================
@@ -352,10 +1068,292 @@ fn encode_path(path: &str) -> String {
         .join("/")
 }
 
+/// Open a local file as a streaming request body, returning it with its length.
+async fn open_upload_body(
+    local_path: &Path,
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+) -> AppResult<(reqwest::Body, u64)> {
+    let file = tokio::fs::File::open(local_path)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to open file {}: {}", local_path.display(), e)))?;
+    let len = file
+        .metadata()
+        .await
+        .map_err(|e| AppError::io(format!("Failed to stat file {}: {}", local_path.display(), e)))?
+        .len();
+    Ok((upload_body(file, bandwidth), len))
+}
+
+/// Request body streaming `reader` piece by piece; with a limiter, each piece waits
+/// for upload bandwidth before it is sent. The length is unknown to reqwest, so the
+/// request needs an explicit `Content-Length`.
+fn upload_body<R>(reader: R, bandwidth: Option<Arc<BandwidthLimiter>>) -> reqwest::Body
+where
+    R: tokio::io::AsyncRead + Send + Unpin + 'static,
+{
+    use tokio::io::AsyncReadExt;
+
+    let pieces = futures_util::stream::try_unfold((reader, bandwidth), |(mut reader, bandwidth)| async move {
+        let mut piece = vec![0u8; UPLOAD_PIECE_SIZE];
+        let n = reader.read(&mut piece).await?;
+        if n == 0 {
+            return Ok::<_, std::io::Error>(None);
+        }
+        piece.truncate(n);
+        if let Some(bandwidth) = &bandwidth {
+            bandwidth.upload.take(n as u64).await;
+        }
+        Ok(Some((piece, (reader, bandwidth))))
+    });
+    reqwest::Body::wrap_stream(pieces)
+}
+
+/// Hidden partial file a download of `target` is written to: `dir/.name.veloryn-part`.
+pub fn partial_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{}{}", name, PARTIAL_SUFFIX))
+}
+
+/// Whether a file name is a leftover partial download.
+pub fn is_partial_file(name: &str) -> bool {
+    name.starts_with('.') && name.ends_with(PARTIAL_SUFFIX)
+}
+
+/// Read `len` bytes at `offset` of a file (one upload chunk).
+async fn read_chunk(path: &Path, offset: u64, len: u64) -> AppResult<Vec<u8>> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to open file {}: {}", path.display(), e)))?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| AppError::io(format!("Failed to read file {}: {}", path.display(), e)))?;
+    let mut buf = vec![0u8; len as usize];
+    file.read_exact(&mut buf)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to read file {}: {}", path.display(), e)))?;
+    Ok(buf)
+}
+
+/// Unique name for a chunked upload collection.
+fn new_transfer_id(key: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let hash = blake3::hash(format!("{}:{}", key, nanos).as_bytes());
+    format!("veloryn-{}", &hash.to_hex()[..32])
+}
+
+/// Version headers of a GET response, taken before its body is consumed.
+struct DownloadedVersion {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_length: Option<u64>,
+}
+
+fn lock_etag(etag: &Mutex<Option<String>>) -> std::sync::MutexGuard<'_, Option<String>> {
+    etag.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Strong ETag of a response, unquoted. Weak etags cannot be used with `If-Range`.
+fn strong_etag(resp: &reqwest::Response) -> Option<String> {
+    let etag = resp.headers().get("ETag")?.to_str().ok()?.trim();
+    if etag.starts_with("W/") {
+        return None;
+    }
+    Some(etag.trim_matches('"').to_string()).filter(|e| !e.is_empty())
+}
+
+/// Etag a PUT/MOVE/GET response reports for the file, unquoted: Nextcloud's
+/// `OC-ETag`, which is what PROPFIND returns, else a strong `ETag`. A weak one may
+/// describe an encoded representation rather than the file itself.
+fn response_etag(resp: &reqwest::Response) -> Option<String> {
+    header_str(resp, "OC-ETag")
+        .map(|etag| etag.trim_matches('"').to_string())
+        .filter(|etag| !etag.is_empty())
+        .or_else(|| strong_etag(resp))
+}
+
+fn header_str<'a>(resp: &'a reqwest::Response, name: &str) -> Option<&'a str> {
+    let value = resp.headers().get(name)?.to_str().ok()?.trim();
+    Some(value).filter(|v| !v.is_empty())
+}
+
+/// First byte offset of a 206 response's `Content-Range: bytes start-end/total`.
+fn content_range_start(resp: &reqwest::Response) -> Option<u64> {
+    parse_content_range(header_str(resp, "Content-Range")?).map(|(start, _)| start)
+}
+
+/// Full length from a 206 response's `Content-Range` (None for `*`).
+fn content_range_total(resp: &reqwest::Response) -> Option<u64> {
+    parse_content_range(header_str(resp, "Content-Range")?).and_then(|(_, total)| total)
+}
+
+/// Start offset and full length (None for `*`) of `bytes start-end/total`.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split('-').next()?.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Stream a response body into `path` chunk by chunk, appending after `offset` bytes
+/// already there (a resumed download) or replacing the file when `offset` is 0.
+/// Returns the full file length. The body is checked against Content-Length and, when
+/// the server sends one, the BLAKE3 entry of `OC-Checksum`, then fsynced. A connection
+/// dropped mid-body or a short/corrupt body is retryable; local write failures are not.
+async fn write_response_body(
+    mut resp: reqwest::Response,
+    path: &Path,
+    offset: u64,
+    bandwidth: Option<&BandwidthLimiter>,
+) -> Result<u64, RetryableError> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let expected_len = resp.content_length();
+    let expected_hash = resp
+        .headers()
+        .get("OC-Checksum")
+        .and_then(|v| v.to_str().ok())
+        .and_then(blake3_from_checksums)
+        .map(|h| h.to_lowercase());
+
+    let mut hasher = blake3::Hasher::new();
+    let mut file = if offset > 0 {
+        let mut file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to open file {}: {}", path.display(), e)))?;
+        // The checksum covers the whole file, including the part we already have
+        if expected_hash.is_some() {
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = file
+                    .read(&mut buf)
+                    .await
+                    .map_err(|e| AppError::io(format!("Failed to read file {}: {}", path.display(), e)))?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+        }
+        file
+    } else {
+        tokio::fs::File::create(path)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to create file {}: {}", path.display(), e)))?
+    };
+    let mut written: u64 = 0;
+
+    loop {
+        let chunk = match resp.chunk().await {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // What arrived is kept for resuming; make sure it is on disk before the
+                // next attempt looks at the file's length
+                let _ = file.flush().await;
+                return Err(RetryableError::transient(AppError::network(format!(
+                    "Failed to read response body: {}",
+                    e
+                ))));
+            }
+        };
+        let Some(chunk) = chunk else { break };
+        // Not reading on while over the limit makes TCP slow the sender down
+        if let Some(bandwidth) = bandwidth {
+            bandwidth.download.take(chunk.len() as u64).await;
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to write file {}: {}", path.display(), e)))?;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+    }
+
+    if let Some(expected) = expected_len {
+        if written != expected {
+            return Err(RetryableError::transient(AppError::network(format!(
+                "Incomplete download {}: {} of {} bytes",
+                path.display(),
+                written,
+                expected
+            ))));
+        }
+    }
+    if let Some(expected) = expected_hash {
+        let actual = hasher.finalize().to_hex().to_string();
+        if actual != expected {
+            return Err(RetryableError::transient(AppError::network(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                path.display(),
+                expected,
+                actual
+            ))));
+        }
+    }
+
+    file.sync_all()
+        .await
+        .map_err(|e| AppError::io(format!("Failed to sync file {}: {}", path.display(), e)))?;
+    Ok(offset + written)
+}
+
+/// Pick the BLAKE3 hex digest out of a checksums value ("SHA1:… BLAKE3:…").
+pub fn blake3_from_checksums(checksums: &str) -> Option<&str> {
+    checksums.split_whitespace().find_map(|entry| {
+        let (algorithm, digest) = entry.split_once(':')?;
+        algorithm.eq_ignore_ascii_case("blake3").then_some(digest)
+    })
+}
+
 /// Error wrapper that carries whether a retry is worthwhile.
 struct RetryableError {
     error: AppError,
     retryable: bool,
+    /// Wait the server asked for with `Retry-After`, used instead of the backoff
+    retry_after: Option<Duration>,
+}
+
+impl RetryableError {
+    /// A failure that is worth another attempt, e.g. a body cut short.
+    fn transient(error: AppError) -> Self {
+        Self {
+            error,
+            retryable: true,
+            retry_after: None,
+        }
+    }
+
+    /// An unexpected response status. Timeouts, rate limiting and server errors are
+    /// retried, honouring `Retry-After` on 429 and 503.
+    fn status(resp: &reqwest::Response, url: &str) -> Self {
+        let status = resp.status();
+        Self {
+            error: AppError::network(format!("HTTP {}: {}", status, url)),
+            retryable: is_retryable_status(status.as_u16()),
+            retry_after: match status.as_u16() {
+                429 | 503 => retry_after(resp),
+                _ => None,
+            },
+        }
+    }
+
+    /// A request that failed below HTTP. Timeouts and refused, reset or dropped
+    /// connections are retried; a request that cannot even be built is not.
+    fn transport(context: impl std::fmt::Display, e: reqwest::Error) -> Self {
+        Self {
+            retryable: e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            error: AppError::network(format!("{}: {}", context, e)),
+            retry_after: None,
+        }
+    }
 }
 
 impl From<AppError> for RetryableError {
@@ -363,161 +1361,724 @@ impl From<AppError> for RetryableError {
         Self {
             error,
             retryable: false,
+            retry_after: None,
         }
     }
 }
 
-/// Run an async operation with exponential backoff retry.
-/// Retries up to `max_attempts` times on retryable errors (5xx or network).
-/// Delays: 1s → 2s → 4s.
-async fn retry_request<F, Fut, T>(max_attempts: u32, mut f: F) -> AppResult<T>
+/// Statuses that may go away on their own: request timeout, rate limiting and
+/// server errors other than 501 Not Implemented.
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429) || (status >= 500 && status != 501)
+}
+
+/// `Retry-After` of a response, given in seconds or as an HTTP date.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    parse_retry_after(header_str(resp, "Retry-After")?, chrono::Utc::now())
+}
+
+/// Wait a `Retry-After` value asks for at `now`; a date already past means none.
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.timestamp() - now.timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+/// Random fraction in [0, 1), so clients that failed together do not retry in step.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Run an async operation, retrying retryable failures as `policy` allows. The
+/// delay doubles with each attempt, randomised within its upper half; a server's
+/// `Retry-After` replaces it, unless it is longer than the policy's longest delay.
+async fn retry_request<F, Fut, T>(policy: RetryPolicy, mut f: F) -> AppResult<T>
 where
     F: FnMut(u32) -> Fut,
     Fut: std::future::Future<Output = Result<T, RetryableError>>,
 {
-    let mut last_error = AppError::network("No attempts made");
-    for attempt in 1..=max_attempts {
-        match f(attempt).await {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let failure = match f(attempt).await {
             Ok(value) => return Ok(value),
-            Err(RetryableError { error, retryable }) => {
-                last_error = error;
-                if !retryable || attempt == max_attempts {
-                    break;
-                }
-                let delay = Duration::from_secs(2u64.pow(attempt - 1)); // 1s, 2s, 4s
+            Err(failure) => failure,
+        };
+        if !failure.retryable || attempt >= max_attempts {
+            return Err(failure.error);
+        }
+        let delay = match failure.retry_after {
+            Some(wait) if wait > policy.max_delay => {
                 log::warn!(
-                    "Request failed (attempt {}/{}), retrying in {:?}: {}",
-                    attempt,
-                    max_attempts,
-                    delay,
-                    last_error
+                    "Server asked to retry in {:?}, longer than {:?}, giving up: {}",
+                    wait,
+                    policy.max_delay,
+                    failure.error
                 );
-                tokio::time::sleep(delay).await;
+                return Err(failure.error);
             }
-        }
+            Some(wait) => wait,
+            None => policy.backoff(attempt),
+        };
+        log::warn!(
+            "Request failed (attempt {}/{}), retrying in {:?}: {}",
+            attempt,
+            max_attempts,
+            delay,
+            failure.error
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
     }
-    Err(last_error)
 }
 
-/// Parse a WebDAV PROPFIND XML response into a list of RemoteEntry.
-/// Uses simple string parsing to avoid adding an xml crate dependency.
-fn parse_propfind_response(xml: &str, request_path: &str) -> AppResult<Vec<RemoteEntry>> {
-    let mut entries = Vec::new();
-
-    // Split into <D:response> blocks
-    let mut remaining = xml;
-    while let Some(start) = find_tag_start(remaining, "response") {
-        remaining = &remaining[start..];
-        let end = find_tag_end(remaining, "response").unwrap_or(remaining.len());
-        let block = &remaining[..end];
-        remaining = &remaining[end..];
+/// Parse a WebDAV PROPFIND XML response into the requested directory and its entries.
+/// Members the server reports an error status for are left out.
+fn parse_propfind_response(xml: &str, request_url: &str) -> AppResult<DirListing> {
+    let multistatus = multistatus::parse_multistatus(xml)?;
 
-        if let Some(entry) = parse_response_block(block) {
-            entries.push(entry);
+    // Set apart the root entry (the requested directory itself)
+    let normalized_request = normalize_webdav_path(request_url);
+    let mut listing = DirListing::default();
+    for response in multistatus.responses {
+        if !response.is_success() {
+            continue;
         }
-    }
-
-    // Filter out the root entry (the requested directory itself)
-    let normalized_request = normalize_webdav_path(request_path);
-    entries.retain(|e| {
-        let normalized = normalize_webdav_path(&e.path);
-        normalized != normalized_request
-    });
-
-    Ok(entries)
-}
-
-/// Find the byte offset of the start of a tag (opening `<` including namespace prefix variants).
-fn find_tag_start(haystack: &str, tag_local: &str) -> Option<usize> {
-    // Match <D:response>, <response>, <d:response>, etc.
-    for prefix in &["<D:", "<d:", "<"] {
-        let needle = format!("{}{}", prefix, tag_local);
-        if let Some(pos) = haystack.find(needle.as_str()) {
-            return Some(pos);
+        let Some(entry) = remote_entry(response) else {
+            continue;
+        };
+        if normalize_webdav_path(&entry.path) == normalized_request {
+            listing.collection = Some(entry);
+        } else {
+            listing.children.push(entry);
         }
     }
-    None
-}
 
-/// Find the end of a closing tag block (past the `</D:response>` or variant).
-fn find_tag_end(haystack: &str, tag_local: &str) -> Option<usize> {
-    for prefix in &["</D:", "</d:", "</"] {
-        let needle = format!("{}{}>", prefix, tag_local);
-        if let Some(pos) = haystack.find(needle.as_str()) {
-            return Some(pos + needle.len());
-        }
-    }
-    None
+    Ok(listing)
 }
 
-/// Extract the text content of the first matching element in a block.
-fn extract_element_text<'a>(block: &'a str, tag_local: &str) -> Option<&'a str> {
-    for prefix in &["D:", "d:", ""] {
-        let open = format!("<{}{}>", prefix, tag_local);
-        let close = format!("</{}{}>", prefix, tag_local);
-        if let Some(start) = block.find(open.as_str()) {
-            let after_open = start + open.len();
-            if let Some(end) = block[after_open..].find(close.as_str()) {
-                return Some(&block[after_open..after_open + end]);
+/// Parse a `sync-collection` multistatus: members with properties changed, members
+/// with a 404 status were removed, and a 507 on the collection itself marks a
+/// truncated result.
+fn parse_sync_collection_response(xml: &str) -> AppResult<SyncCollectionChanges> {
+    let multistatus = multistatus::parse_multistatus(xml)?;
+    let mut changes = SyncCollectionChanges {
+        sync_token: multistatus
+            .sync_token
+            .ok_or_else(|| AppError::network("sync-collection response without a sync-token"))?,
+        ..Default::default()
+    };
+
+    for response in multistatus.responses {
+        match response.status {
+            Some(404) => changes.removed.push(href_path(&response.href)),
+            Some(507) => changes.truncated = true,
+            _ if response.is_success() => {
+                if let Some(entry) = remote_entry(response) {
+                    changes.changed.push(entry);
+                }
             }
+            _ => {}
         }
     }
-    None
-}
-
-/// Parse a single <response> block into a RemoteEntry.
-fn parse_response_block(block: &str) -> Option<RemoteEntry> {
-    // Extract href
-    let href = extract_element_text(block, "href")?;
-    let href = href.trim().to_string();
 
-    // Determine if directory: <resourcetype> contains <collection>
-    let is_directory = block.contains("collection");
-
-    // Extract size
-    let size: Option<u64> = extract_element_text(block, "getcontentlength")
-        .and_then(|s| s.trim().parse().ok());
-
-    // Extract etag (strip surrounding quotes if present)
-    let etag = extract_element_text(block, "getetag").map(|s| {
-        let s = s.trim();
-        let s = s.trim_matches('"');
-        s.to_string()
-    });
+    Ok(changes)
+}
 
-    // Extract last modified
-    let last_modified = extract_element_text(block, "getlastmodified")
-        .map(|s| s.trim().to_string());
+/// Escape text for use inside an XML element.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
 
-    // Derive name from href path
-    let decoded_href = urlencoding::decode(&href)
-        .map(|cow| cow.into_owned())
-        .unwrap_or_else(|_| href.clone());
-    let name = decoded_href
+/// Turn a multistatus response into a RemoteEntry (None for an unnamed href).
+fn remote_entry(response: multistatus::DavResponse) -> Option<RemoteEntry> {
+    let path = href_path(&response.href);
+    let name = path
         .trim_end_matches('/')
         .rsplit('/')
         .next()
-        .unwrap_or(&decoded_href)
+        .unwrap_or(&path)
         .to_string();
 
     if name.is_empty() {
         return None;
     }
 
+    let props = response.props;
     Some(RemoteEntry {
-        path: decoded_href,
+        path,
         name,
-        is_directory,
-        size,
-        etag,
-        last_modified,
+        is_directory: props.is_collection,
+        size: props.content_length,
+        etag: props.etag,
+        last_modified: props.last_modified,
+        checksum: props.checksums,
+        ctag: props.ctag,
+        content_type: props.content_type,
+        file_id: props.file_id,
+        permissions: props.permissions,
     })
 }
 
+/// Path of an href, percent-decoded. Hrefs may be absolute URLs.
+fn href_path(href: &str) -> String {
+    let path = match href.find("://") {
+        Some(pos) => {
+            let rest = &href[pos + 3..];
+            rest.find('/').map_or("/", |slash| &rest[slash..])
+        }
+        None => href,
+    };
+    urlencoding::decode(path)
+        .map(|cow| cow.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
 /// Normalize a WebDAV path for comparison (remove trailing slash, decode).
 fn normalize_webdav_path(path: &str) -> String {
-    let decoded = urlencoding::decode(path)
-        .map(|cow| cow.into_owned())
-        .unwrap_or_else(|_| path.to_string());
-    decoded.trim_end_matches('/').to_lowercase()
+    href_path(path).trim_end_matches('/').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, FakeDav};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one canned response per connection and return the request heads.
+    async fn serve(responses: Vec<Vec<u8>>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8_lossy(&head).to_lowercase());
+                let _ = socket.write_all(&response).await;
+                let _ = socket.shutdown().await;
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut response = head.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn memory_db() -> DbPool {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::migrate(&conn).unwrap();
+        Arc::new(Mutex::new(conn))
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("veloryn-transfer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn attempts(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        }
+    }
+
+    /// A 200 that announces 100 bytes but breaks off after 10 of them.
+    fn truncated_download(etag: &str) -> Vec<u8> {
+        response(
+            "200 OK",
+            &[("Content-Length", "100"), ("ETag", etag)],
+            &[b'a'; 10],
+        )
+    }
+
+    fn collection(etag: &str) -> RemoteEntry {
+        RemoteEntry {
+            path: "/dav/personal/Docs/".to_string(),
+            name: "Docs".to_string(),
+            is_directory: true,
+            size: None,
+            etag: Some(etag.to_string()),
+            last_modified: None,
+            checksum: None,
+            ctag: None,
+            content_type: None,
+            file_id: None,
+            permissions: None,
+        }
+    }
+
+    #[test]
+    fn collection_tag_needs_recursive_etag() {
+        // Apache mod_dav: the etag does not change for edits deeper down
+        assert_eq!(collection("\"1f-5e\"").collection_tag(), None);
+
+        let nextcloud = RemoteEntry {
+            file_id: Some("00000042oc".to_string()),
+            ..collection("65f1a2b3c4d5e")
+        };
+        assert_eq!(nextcloud.collection_tag(), Some("65f1a2b3c4d5e"));
+
+        let ctag = RemoteEntry {
+            ctag: Some("ctag-7".to_string()),
+            ..collection("\"1f-5e\"")
+        };
+        assert_eq!(ctag.collection_tag(), Some("ctag-7"));
+    }
+
+    fn policy(max_attempts: u32, base_ms: u64, max_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(base_ms),
+            max_delay: Duration::from_millis(max_ms),
+        }
+    }
+
+    fn failure(retryable: bool, retry_after: Option<Duration>) -> RetryableError {
+        RetryableError {
+            error: AppError::network("HTTP 503"),
+            retryable,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn backoff_stays_in_upper_half_of_step() {
+        let policy = policy(5, 100, 1000);
+        for _ in 0..100 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_delay() {
+        let policy = policy(50, 100, 1000);
+        for attempt in [5, 10, 40] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= Duration::from_millis(500), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(1000), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn retryable_statuses() {
+        for status in [408, 429, 500, 502, 503, 504] {
+            assert!(is_retryable_status(status), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 409, 412, 501] {
+            assert!(!is_retryable_status(status), "{}", status);
+        }
+    }
+
+    #[test]
+    fn retry_after_seconds_and_http_date() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-16T08:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Fri, 16 Oct 2026 08:01:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        // A date already past means retry right away
+        assert_eq!(
+            parse_retry_after("Fri, 16 Oct 2026 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result = retry_request(policy(3, 1, 10), |attempt| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 3 {
+                    Err(failure(true, None))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts_or_permanent_failure() {
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result: AppResult<()> = retry_request(policy(4, 1, 10), |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(failure(true, None)) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: AppResult<()> = retry_request(policy(4, 1, 10), |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(failure(false, None)) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_longer_than_max_delay_gives_up() {
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let start = std::time::Instant::now();
+        let result: AppResult<()> = retry_request(policy(5, 1, 1000), |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(failure(true, Some(Duration::from_secs(60)))) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn retry_after_replaces_backoff() {
+        let start = std::time::Instant::now();
+        let result = retry_request(policy(2, 1, 1000), |attempt| async move {
+            if attempt == 1 {
+                Err(failure(true, Some(Duration::from_millis(200))))
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn content_range_parsing() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200"),
+            Some((100, Some(200)))
+        );
+        assert_eq!(parse_content_range("bytes 100-199/*"), Some((100, None)));
+        assert_eq!(parse_content_range("bytes */200"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[tokio::test]
+    async fn partial_without_strong_etag_is_removed() {
+        let (url, server) = serve(vec![truncated_download("W/\"v1\"")]).await;
+        let target = test_dir("weak-etag").join("file.bin");
+        let db = memory_db();
+        let transfer = WebDavTransfer::new(&url, "token")
+            .with_db(db.clone())
+            .with_retry_policy(attempts(1));
+
+        assert!(transfer.download_file("file.bin", &target).await.is_err());
+        // A weak etag cannot be sent in If-Range, so there is nothing to resume
+        assert!(!partial_path(&target).exists());
+        assert_eq!(
+            db::get_partial_download(&db, &target.to_string_lossy()).unwrap(),
+            None
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn partial_with_strong_etag_is_resumed() {
+        let (url, server) = serve(vec![
+            truncated_download("\"v1\""),
+            response(
+                "206 Partial Content",
+                &[
+                    ("Content-Length", "90"),
+                    ("Content-Range", "bytes 10-99/100"),
+                    ("ETag", "\"v1\""),
+                ],
+                &[b'b'; 90],
+            ),
+        ])
+        .await;
+        let target = test_dir("strong-etag").join("file.bin");
+        let transfer = WebDavTransfer::new(&url, "token")
+            .with_db(memory_db())
+            .with_retry_policy(attempts(1));
+
+        assert!(transfer.download_file("file.bin", &target).await.is_err());
+        assert_eq!(std::fs::metadata(partial_path(&target)).unwrap().len(), 10);
+
+        let result = transfer.download_file("file.bin", &target).await.unwrap();
+        assert_eq!(result.content_length, Some(100));
+        let content = std::fs::read(&target).unwrap();
+        assert_eq!(&content[..10], &[b'a'; 10]);
+        assert_eq!(&content[10..], &[b'b'; 90]);
+        assert!(!partial_path(&target).exists());
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=10-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn resume_at_wrong_offset_starts_over() {
+        let (url, server) = serve(vec![
+            truncated_download("\"v1\""),
+            // Answers the range request from byte 0
+            response(
+                "206 Partial Content",
+                &[
+                    ("Content-Length", "100"),
+                    ("Content-Range", "bytes 0-99/100"),
+                ],
+                &[b'b'; 100],
+            ),
+            response(
+                "200 OK",
+                &[("Content-Length", "100"), ("ETag", "\"v1\"")],
+                &[b'c'; 100],
+            ),
+        ])
+        .await;
+        let target = test_dir("wrong-offset").join("file.bin");
+        let db = memory_db();
+        let transfer = WebDavTransfer::new(&url, "token").with_db(db.clone());
+
+        let first = transfer.with_retry_policy(attempts(1));
+        assert!(first.download_file("file.bin", &target).await.is_err());
+
+        let second = WebDavTransfer::new(&url, "token")
+            .with_db(db)
+            .with_retry_policy(attempts(2));
+        second.download_file("file.bin", &target).await.unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), vec![b'c'; 100]);
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].contains("range: bytes=10-"));
+        assert!(!requests[2].contains("range:"));
+    }
+
+    /// Files over 4 bytes go up in chunks of 4 bytes.
+    fn chunked(dav: &FakeDav, db: &DbPool, max_attempts: u32) -> WebDavTransfer {
+        let mut transfer = WebDavTransfer::new(&dav.url, "token")
+            .with_db(db.clone())
+            .with_retry_policy(attempts(max_attempts))
+            .with_chunked_uploads(&dav.uploads_url, 4);
+        transfer.chunking.as_mut().unwrap().chunk_size = 4;
+        transfer
+    }
+
+    /// A 10-byte local file, three chunks.
+    fn big_file(name: &str) -> PathBuf {
+        let local = test_dir(name).join("big.bin");
+        std::fs::write(&local, b"0123456789").unwrap();
+        local
+    }
+
+    fn count(requests: &[String], prefix: &str, suffix: &str) -> usize {
+        requests
+            .iter()
+            .filter(|r| r.starts_with(prefix) && r.ends_with(suffix))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn chunked_upload_resumes_after_the_chunks_sent() {
+        let dav = FakeDav::start().await;
+        let db = memory_db();
+        let local = big_file("chunked-resume");
+        let key = local.to_string_lossy().to_string();
+        // The second chunk is refused once
+        let mut refused = false;
+        dav.hook(move |request, _| {
+            if request.method == "PUT" && request.path.ends_with("/00002") && !refused {
+                refused = true;
+                return Some(test_server::response("400 Bad Request", &[], b""));
+            }
+            None
+        });
+
+        let first = chunked(&dav, &db, 1);
+        assert!(first.upload_file(&local, "big.bin").await.is_err());
+        let stored = db::get_chunked_upload(&db, &key).unwrap().unwrap();
+        assert_eq!(stored.chunks_done, 1);
+
+        let result = chunked(&dav, &db, 1)
+            .upload_file(&local, "big.bin")
+            .await
+            .unwrap();
+        assert_eq!(result.bytes_sent, 6);
+        assert_eq!(dav.tree().file("big.bin"), Some(&b"0123456789"[..]));
+        assert_eq!(result.etag.as_deref(), dav.tree().etag("big.bin"));
+        assert!(db::get_chunked_upload(&db, &key).unwrap().is_none());
+
+        let requests = dav.requests();
+        assert_eq!(count(&requests, "MKCOL ", ""), 1);
+        assert_eq!(count(&requests, "PUT ", "/00001"), 1);
+        assert_eq!(count(&requests, "PUT ", "/00002"), 2);
+        assert_eq!(count(&requests, "PUT ", "/00003"), 1);
+    }
+
+    #[tokio::test]
+    async fn server_without_chunking_gets_a_plain_put() {
+        let dav = FakeDav::start().await;
+        let db = memory_db();
+        let local = big_file("chunked-unsupported");
+        dav.hook(|request, _| {
+            (request.method == "MKCOL")
+                .then(|| test_server::response("501 Not Implemented", &[], b""))
+        });
+
+        let transfer = chunked(&dav, &db, 1);
+        let result = transfer.upload_file(&local, "big.bin").await.unwrap();
+        assert_eq!(result.bytes_sent, 10);
+        assert!(transfer.chunking_unsupported.load(Ordering::Relaxed));
+        assert_eq!(dav.tree().file("big.bin"), Some(&b"0123456789"[..]));
+
+        // Further files go straight to PUT
+        transfer.upload_file(&local, "again.bin").await.unwrap();
+        let requests = dav.requests();
+        assert_eq!(count(&requests, "MKCOL ", ""), 1);
+        assert_eq!(requests.last().unwrap(), "PUT again.bin");
+    }
+
+    #[tokio::test]
+    async fn refused_upload_dir_is_an_error_not_a_fallback() {
+        let dav = FakeDav::start().await;
+        let db = memory_db();
+        let local = big_file("chunked-forbidden");
+        dav.hook(|request, _| {
+            (request.method == "MKCOL").then(|| test_server::response("403 Forbidden", &[], b""))
+        });
+
+        let transfer = chunked(&dav, &db, 1);
+        assert!(transfer.upload_file(&local, "big.bin").await.is_err());
+        assert!(!transfer.chunking_unsupported.load(Ordering::Relaxed));
+        assert_eq!(count(&dav.requests(), "PUT ", ""), 0);
+    }
+
+    #[tokio::test]
+    async fn precondition_failed_assembly_discards_the_chunks() {
+        let dav = FakeDav::start().await;
+        let db = memory_db();
+        let local = big_file("chunked-412");
+        let key = local.to_string_lossy().to_string();
+        dav.tree().put("big.bin", b"theirs");
+
+        let error = chunked(&dav, &db, 3)
+            .upload_file_if(&local, "big.bin", WritePrecondition::Etag("stale"))
+            .await
+            .unwrap_err();
+        assert!(error.is_precondition_failed(), "{}", error);
+        assert_eq!(dav.tree().file("big.bin"), Some(&b"theirs"[..]));
+        assert!(db::get_chunked_upload(&db, &key).unwrap().is_none());
+
+        let requests = dav.requests();
+        assert_eq!(count(&requests, "MOVE ", "/.file"), 1);
+        let upload_dir = requests[0].strip_prefix("MKCOL ").unwrap();
+        assert!(requests.contains(&format!("DELETE {}", upload_dir)));
+    }
+
+    #[tokio::test]
+    async fn failed_assembly_keeps_the_chunks_for_the_next_attempt() {
+        let dav = FakeDav::start().await;
+        let db = memory_db();
+        let local = big_file("chunked-503");
+        let key = local.to_string_lossy().to_string();
+        let mut failures = 2;
+        dav.hook(move |request, _| {
+            if request.method == "MOVE" && failures > 0 {
+                failures -= 1;
+                return Some(test_server::response("503 Service Unavailable", &[], b""));
+            }
+            None
+        });
+
+        assert!(chunked(&dav, &db, 2)
+            .upload_file(&local, "big.bin")
+            .await
+            .is_err());
+        let stored = db::get_chunked_upload(&db, &key).unwrap().unwrap();
+        assert_eq!(stored.chunks_done, 3);
+        assert_eq!(count(&dav.requests(), "DELETE ", ""), 0);
+
+        let result = chunked(&dav, &db, 1)
+            .upload_file(&local, "big.bin")
+            .await
+            .unwrap();
+        assert_eq!(result.bytes_sent, 0);
+        assert_eq!(dav.tree().file("big.bin"), Some(&b"0123456789"[..]));
+        assert_eq!(count(&dav.requests(), "PUT ", ""), 3);
+        assert!(db::get_chunked_upload(&db, &key).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn upload_etag_prefers_oc_etag_and_skips_weak_ones() {
+        let (url, server) = serve(vec![
+            response(
+                "201 Created",
+                &[("ETag", "\"http-etag\""), ("OC-ETag", "\"oc-etag\"")],
+                b"",
+            ),
+            response("204 No Content", &[("ETag", "\"strong\"")], b""),
+            response("204 No Content", &[("ETag", "W/\"weak\"")], b""),
+        ])
+        .await;
+        let local = test_dir("upload-etag").join("a.txt");
+        std::fs::write(&local, b"a").unwrap();
+        let transfer = WebDavTransfer::new(&url, "token");
+
+        let etags = [
+            transfer.upload_file(&local, "a.txt").await.unwrap().etag,
+            transfer.upload_file(&local, "a.txt").await.unwrap().etag,
+            transfer.upload_file(&local, "a.txt").await.unwrap().etag,
+        ];
+        assert_eq!(
+            etags,
+            [
+                Some("oc-etag".to_string()),
+                Some("strong".to_string()),
+                None
+            ]
+        );
+        server.await.unwrap();
+    }
 }
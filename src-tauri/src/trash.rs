@@ -29,6 +29,17 @@ impl LocalTrashManager {
         &self,
         file_path: &Path,
         sync_zone: &str,
+    ) -> AppResult<PathBuf> {
+        self.trash_file_as(file_path, file_path, sync_zone)
+    }
+
+    /// Move `file_path` to the trash, recording `original_path` as the place
+    /// it will be restored to. Used for staged copies (see `staging_path`).
+    pub fn trash_file_as(
+        &self,
+        file_path: &Path,
+        original_path: &Path,
+        sync_zone: &str,
     ) -> AppResult<PathBuf> {
         if !file_path.exists() {
             return Err(AppError::io(format!(
@@ -45,7 +56,7 @@ impl LocalTrashManager {
         })?;
 
         // Generate unique trash filename to avoid collisions
-        let original_name = file_path
+        let original_name = original_path
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
@@ -74,7 +85,7 @@ impl LocalTrashManager {
 
         let entry = LocalTrashEntry {
             id: None,
-            original_path: file_path.to_string_lossy().to_string(),
+            original_path: original_path.to_string_lossy().to_string(),
             trash_path: trash_path.to_string_lossy().to_string(),
             sync_zone: sync_zone.to_string(),
             size_bytes,
@@ -86,13 +97,23 @@ impl LocalTrashManager {
 
         log::info!(
             "Moved to trash: {} -> {}",
-            file_path.display(),
+            original_path.display(),
             trash_path.display()
         );
 
         Ok(trash_path)
     }
 
+    /// Path inside the trash directory where a file (e.g. the server version of a
+    /// conflicted file) can be downloaded before handing it to `trash_file_as`.
+    pub fn staging_path(&self, name: &str) -> AppResult<PathBuf> {
+        let staging_dir = self.trash_dir.join(".staging");
+        std::fs::create_dir_all(&staging_dir).map_err(|e| {
+            AppError::io(format!("Cannot create trash staging directory: {}", e))
+        })?;
+        Ok(staging_dir.join(generate_unique_name(&staging_dir, name)))
+    }
+
     /// Restore a file from trash to its original location.
     pub fn restore_file(&self, trash_entry_id: i64) -> AppResult<PathBuf> {
         let entries = db::list_local_trash(&self.db)?;
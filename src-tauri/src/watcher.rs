@@ -1,19 +1,148 @@
 // is_running/has_changes are legacy helpers still reachable via get_db_pool etc.
 #![allow(dead_code)]
 
+use crate::sync::SyncScope;
+use notify::event::{ModifyKind, RemoveKind};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// Events queued before the watcher starts dropping them (and asks for a rescan).
+const WATCH_CHANNEL_CAPACITY: usize = 256;
+
+/// Above this many changed paths a targeted sync costs more than a full one.
+const MAX_TARGETED_PATHS: usize = 200;
+
+/// A local change reported by the watcher.
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// These files (or new folders) were created, modified, renamed or removed.
+    Changed(Vec<PathBuf>),
+    /// Changes that can't be followed path by path — a folder was renamed or
+    /// removed, or events were lost. The sync roots need a full scan.
+    Rescan,
+}
+
+/// Receiving end of the watcher channel, owned by the scheduler loop.
+pub struct WatchReceiver {
+    rx: mpsc::Receiver<WatchEvent>,
+    /// Set when an event was dropped because the channel was full.
+    overflowed: Arc<AtomicBool>,
+}
+
+impl WatchReceiver {
+    /// Wait for the next event. Dropped events are reported as `Rescan`.
+    pub async fn recv(&mut self) -> Option<WatchEvent> {
+        let event = self.rx.recv().await?;
+        Some(self.or_rescan(event))
+    }
+
+    /// Next queued event, if any (non-blocking).
+    pub fn try_recv(&mut self) -> Option<WatchEvent> {
+        let event = self.rx.try_recv().ok()?;
+        Some(self.or_rescan(event))
+    }
+
+    fn or_rescan(&self, event: WatchEvent) -> WatchEvent {
+        // The channel only overflows while full, so this is seen on the next receive
+        if self.overflowed.swap(false, Ordering::Relaxed) {
+            WatchEvent::Rescan
+        } else {
+            event
+        }
+    }
+}
+
+/// Local changes accumulated by the scheduler until the next sync run.
+#[derive(Debug, Default)]
+pub struct PendingChanges {
+    paths: HashSet<PathBuf>,
+    rescan: bool,
+}
+
+impl PendingChanges {
+    pub fn add(&mut self, event: WatchEvent) {
+        match event {
+            WatchEvent::Changed(paths) if !self.rescan => {
+                self.paths.extend(paths);
+                if self.paths.len() > MAX_TARGETED_PATHS {
+                    self.set_rescan();
+                }
+            }
+            WatchEvent::Changed(_) => {}
+            WatchEvent::Rescan => self.set_rescan(),
+        }
+    }
+
+    /// Put back changes taken for a run that did not happen.
+    pub fn merge(&mut self, other: PendingChanges) {
+        if other.rescan {
+            self.set_rescan();
+        } else {
+            self.add(WatchEvent::Changed(other.paths.into_iter().collect()));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.rescan && self.paths.is_empty()
+    }
+
+    /// True when the changes can only be picked up by a full scan.
+    pub fn needs_rescan(&self) -> bool {
+        self.rescan
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+
+    /// The scope of a sync run for these changes: only the changed files, unless
+    /// a full scan is needed or a path lies outside both roots.
+    pub fn scope(&self, personal_root: &Path, shared_root: &Path) -> SyncScope {
+        if self.needs_rescan() {
+            return SyncScope::Full;
+        }
+
+        let relative = |path: &Path, root: &Path| {
+            path.strip_prefix(root)
+                .ok()
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+                .filter(|rel| !rel.is_empty())
+        };
+
+        let mut personal = Vec::new();
+        let mut shared = Vec::new();
+        for path in self.paths() {
+            if let Some(rel) = relative(path, personal_root) {
+                personal.push(rel);
+            } else if let Some(rel) = relative(path, shared_root) {
+                shared.push(rel);
+            } else {
+                log::debug!("Watcher path outside sync roots: {}", path.display());
+                return SyncScope::Full;
+            }
+        }
+        SyncScope::Paths { personal, shared }
+    }
+
+    fn set_rescan(&mut self) {
+        self.rescan = true;
+        self.paths.clear();
+    }
+}
+
 /// File system watcher for detecting local changes.
 ///
-/// When a file changes locally, sends a notification via an async channel
+/// When a file changes locally, sends its path via an async channel
 /// so the scheduler can react immediately (Synology Drive-style).
 pub struct FileWatcher {
     watcher: Option<RecommendedWatcher>,
     /// Async receiver — scheduler awaits this for instant reaction.
-    rx: Option<mpsc::Receiver<()>>,
+    rx: Option<WatchReceiver>,
 }
 
 impl FileWatcher {
@@ -27,23 +156,19 @@ impl FileWatcher {
     /// Start watching the given directories for changes.
     /// Returns a channel receiver that fires whenever a relevant file changes.
     pub fn start(&mut self, paths: &[&Path]) -> Result<(), String> {
-        // Bounded channel — if sync can't keep up, events coalesce (capacity 1).
-        let (tx, rx) = mpsc::channel::<()>(1);
+        // Bounded channel — if sync can't keep up, the scheduler falls back to a full scan.
+        let (tx, rx) = mpsc::channel::<WatchEvent>(WATCH_CHANNEL_CAPACITY);
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let overflow_flag = overflowed.clone();
 
         let mut watcher = RecommendedWatcher::new(
             move |res: Result<Event, notify::Error>| {
-                if let Ok(event) = res {
-                    // Only trigger on real content changes, not metadata-only.
-                    if matches!(
-                        event.kind,
-                        EventKind::Create(_)
-                            | EventKind::Modify(notify::event::ModifyKind::Data(_))
-                            | EventKind::Modify(notify::event::ModifyKind::Name(_))
-                            | EventKind::Remove(_)
-                    ) {
-                        // try_send: if channel is full (sync already pending), skip.
-                        let _ = tx.try_send(());
-                    }
+                let event = match res {
+                    Ok(event) => event,
+                    Err(_) => return,
+                };
+                if let Some(change) = classify_event(event) {
+                    queue_event(&tx, &overflow_flag, change);
                 }
             },
             Config::default().with_poll_interval(Duration::from_secs(2)),
@@ -60,7 +185,7 @@ impl FileWatcher {
         }
 
         self.watcher = Some(watcher);
-        self.rx = Some(rx);
+        self.rx = Some(WatchReceiver { rx, overflowed });
 
         Ok(())
     }
@@ -77,25 +202,205 @@ impl FileWatcher {
 
     /// Take the async receiver. The scheduler loop owns it and awaits on it
     /// for instant reaction to file changes.
-    pub fn take_receiver(&mut self) -> Option<mpsc::Receiver<()>> {
+    pub fn take_receiver(&mut self) -> Option<WatchReceiver> {
         self.rx.take()
     }
 
     /// Check if there are pending file change events (non-blocking, legacy).
     pub fn has_changes(&mut self) -> bool {
         if let Some(rx) = &mut self.rx {
-            match rx.try_recv() {
-                Ok(()) => true,
-                _ => false,
-            }
+            rx.try_recv().is_some()
         } else {
             false
         }
     }
 }
 
+/// Queue an event for the scheduler; a full channel drops it and flags the loss.
+fn queue_event(tx: &mpsc::Sender<WatchEvent>, overflowed: &AtomicBool, change: WatchEvent) {
+    if let Err(mpsc::error::TrySendError::Full(_)) = tx.try_send(change) {
+        overflowed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Turn a notify event into a watcher event; None for metadata-only changes.
+fn classify_event(event: Event) -> Option<WatchEvent> {
+    if event.need_rescan() {
+        return Some(WatchEvent::Rescan);
+    }
+    match event.kind {
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Data(_))
+        | EventKind::Remove(RemoveKind::File | RemoveKind::Any | RemoveKind::Other) => {
+            Some(WatchEvent::Changed(event.paths))
+        }
+        // A renamed folder moves everything under it, which single paths can't describe
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            if event.paths.iter().any(|p| p.is_dir()) {
+                Some(WatchEvent::Rescan)
+            } else {
+                Some(WatchEvent::Changed(event.paths))
+            }
+        }
+        EventKind::Remove(RemoveKind::Folder) => Some(WatchEvent::Rescan),
+        _ => None,
+    }
+}
+
 impl Default for FileWatcher {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, Flag, MetadataKind, RenameMode};
+
+    fn changed(paths: &[&str]) -> WatchEvent {
+        WatchEvent::Changed(paths.iter().map(PathBuf::from).collect())
+    }
+
+    fn scope_paths(scope: SyncScope) -> (Vec<String>, Vec<String>) {
+        match scope {
+            SyncScope::Paths {
+                mut personal,
+                mut shared,
+            } => {
+                personal.sort();
+                shared.sort();
+                (personal, shared)
+            }
+            SyncScope::Full => panic!("expected a targeted scope"),
+        }
+    }
+
+    #[test]
+    fn dropped_events_turn_into_a_rescan() {
+        let (tx, rx) = mpsc::channel(1);
+        let overflowed = Arc::new(AtomicBool::new(false));
+        queue_event(&tx, &overflowed, changed(&["/home/u/CloudFile/a.txt"]));
+        queue_event(&tx, &overflowed, changed(&["/home/u/CloudFile/b.txt"]));
+        assert!(overflowed.load(Ordering::Relaxed));
+
+        let mut receiver = WatchReceiver { rx, overflowed };
+        assert!(matches!(receiver.try_recv(), Some(WatchEvent::Rescan)));
+        queue_event(
+            &tx,
+            &receiver.overflowed,
+            changed(&["/home/u/CloudFile/c.txt"]),
+        );
+        assert!(matches!(receiver.try_recv(), Some(WatchEvent::Changed(_))));
+    }
+
+    #[test]
+    fn too_many_paths_fall_back_to_a_rescan() {
+        let mut pending = PendingChanges::default();
+        let paths: Vec<PathBuf> = (0..MAX_TARGETED_PATHS)
+            .map(|i| PathBuf::from(format!("/root/file-{}", i)))
+            .collect();
+        pending.add(WatchEvent::Changed(paths.clone()));
+        // The same paths again do not count twice
+        pending.add(WatchEvent::Changed(paths));
+        assert!(!pending.needs_rescan());
+        assert_eq!(pending.paths().count(), MAX_TARGETED_PATHS);
+
+        pending.add(changed(&["/root/one-more"]));
+        assert!(pending.needs_rescan());
+        assert_eq!(pending.paths().count(), 0);
+        // Later changes are covered by the rescan
+        pending.add(changed(&["/root/later"]));
+        assert_eq!(pending.paths().count(), 0);
+        assert!(!pending.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_a_rescan() {
+        let mut pending = PendingChanges::default();
+        pending.add(changed(&["/root/a"]));
+        let mut taken = PendingChanges::default();
+        taken.add(WatchEvent::Rescan);
+        pending.merge(taken);
+        assert!(pending.needs_rescan());
+    }
+
+    #[test]
+    fn renamed_folder_needs_a_rescan_but_a_renamed_file_does_not() {
+        let dir = std::env::temp_dir().join(format!("veloryn-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        std::fs::write(dir.join("file.txt"), b"x").unwrap();
+        let rename = |path: PathBuf| {
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::To))).add_path(path)
+        };
+
+        let folder = classify_event(rename(dir.join("folder")));
+        assert!(matches!(folder, Some(WatchEvent::Rescan)));
+        let file = classify_event(rename(dir.join("file.txt")));
+        assert!(
+            matches!(file, Some(WatchEvent::Changed(paths)) if paths == [dir.join("file.txt")])
+        );
+
+        let removed = Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(dir.join("gone"));
+        assert!(matches!(classify_event(removed), Some(WatchEvent::Rescan)));
+    }
+
+    #[test]
+    fn classify_keeps_content_changes_only() {
+        let created = Event::new(EventKind::Create(CreateKind::File)).add_path("/root/a".into());
+        assert!(matches!(
+            classify_event(created),
+            Some(WatchEvent::Changed(_))
+        ));
+        let written = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path("/root/a".into());
+        assert!(matches!(
+            classify_event(written),
+            Some(WatchEvent::Changed(_))
+        ));
+        let chmod = Event::new(EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::Permissions,
+        )))
+        .add_path("/root/a".into());
+        assert!(classify_event(chmod).is_none());
+        let lost = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        assert!(matches!(classify_event(lost), Some(WatchEvent::Rescan)));
+    }
+
+    #[test]
+    fn scope_maps_paths_into_their_zone() {
+        let personal = Path::new("/home/u/CloudFile/Moje pliki");
+        let shared = Path::new("/home/u/CloudFile/Udostępnione");
+        let mut pending = PendingChanges::default();
+        pending.add(changed(&[
+            "/home/u/CloudFile/Moje pliki/docs/a.txt",
+            "/home/u/CloudFile/Udostępnione/team/b.txt",
+            "/home/u/CloudFile/Moje pliki/c.txt",
+        ]));
+        let (personal_paths, shared_paths) = scope_paths(pending.scope(personal, shared));
+        assert_eq!(personal_paths, ["c.txt", "docs/a.txt"]);
+        assert_eq!(shared_paths, ["team/b.txt"]);
+    }
+
+    #[test]
+    fn path_outside_the_roots_or_a_root_itself_scans_everything() {
+        let personal = Path::new("/home/u/CloudFile/Moje pliki");
+        let shared = Path::new("/home/u/CloudFile/Udostępnione");
+
+        // A sibling sharing the root's prefix is not inside it
+        let mut outside = PendingChanges::default();
+        outside.add(changed(&[
+            "/home/u/CloudFile/Moje pliki/a.txt",
+            "/home/u/CloudFile/Moje pliki (kopia)/a.txt",
+        ]));
+        assert!(matches!(outside.scope(personal, shared), SyncScope::Full));
+
+        let mut root = PendingChanges::default();
+        root.add(changed(&["/home/u/CloudFile/Udostępnione"]));
+        assert!(matches!(root.scope(personal, shared), SyncScope::Full));
+
+        let mut rescan = PendingChanges::default();
+        rescan.add(WatchEvent::Rescan);
+        assert!(matches!(rescan.scope(personal, shared), SyncScope::Full));
+    }
+}
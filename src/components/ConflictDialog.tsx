@@ -1,20 +1,45 @@
 import { useState, useEffect, useCallback } from 'react';
 import { listen } from '@tauri-apps/api/event';
-import type { SyncConflictPayload } from '@/lib/tauri';
+import {
+  listConflicts,
+  resolveConflict,
+  type ConflictResolution,
+  type SyncConflictPayload,
+} from '@/lib/tauri';
 
 /**
  * ConflictDialog — modal that appears when the sync engine detects a conflict.
  *
- * Listens for `sync-conflict` events emitted by the Rust backend.
- * Currently the backend auto-skips conflicts (Phase 4 resolution pending),
- * so this dialog acts as a notification + queue UI. Once the backend adds
- * a `resolve_conflict` command, the resolution buttons will wire through.
+ * On mount it loads conflicts persisted by earlier runs (`list_conflicts`),
+ * then listens for `sync-conflict` events emitted by the Rust backend and
+ * resolves them through the `resolve_conflict` command. "Skip" only hides the
+ * dialog; the file stays in conflict until it is resolved.
  */
 export default function ConflictDialog() {
   const [queue, setQueue] = useState<SyncConflictPayload[]>([]);
   const [dismissedPaths, setDismissedPaths] = useState<Set<string>>(new Set());
+  const [resolving, setResolving] = useState(false);
+  const [error, setError] = useState('');
 
   useEffect(() => {
+    listConflicts()
+      .then((entries) => {
+        setQueue((prev) => {
+          const pending = entries
+            .filter((e) => !prev.some((c) => c.path === e.path))
+            .map<SyncConflictPayload>((e) => ({
+              path: e.path,
+              zone: e.sync_zone,
+              localPath: '',
+              remotePath: e.path,
+              conflictType: e.conflict_type ?? 'BothModified',
+              resolution: 'skipped',
+            }));
+          return [...prev, ...pending];
+        });
+      })
+      .catch(() => {});
+
     const unlisten = listen<SyncConflictPayload>('sync-conflict', (event) => {
       const payload = event.payload;
       setQueue((prev) => {
@@ -32,15 +57,28 @@ export default function ConflictDialog() {
 
   const handleDismiss = useCallback(() => {
     if (!current) return;
+    setError('');
     setDismissedPaths((prev) => new Set(prev).add(current.path));
   }, [current]);
 
   const handleResolve = useCallback(
-    async (resolution: 'keep_local' | 'keep_remote' | 'keep_both' | 'skip') => {
+    async (resolution: ConflictResolution | 'skip') => {
       if (!current) return;
-      // TODO: wire to backend resolve_conflict command when implemented
-      console.log(`Conflict resolution: ${resolution} for ${current.path}`);
-      handleDismiss();
+      if (resolution === 'skip') {
+        handleDismiss();
+        return;
+      }
+
+      setResolving(true);
+      setError('');
+      try {
+        await resolveConflict(current.zone, current.path, resolution);
+        setQueue((prev) => prev.filter((c) => c.path !== current.path));
+      } catch (err) {
+        setError(`Nie udało się rozwiązać konfliktu: ${err}`);
+      } finally {
+        setResolving(false);
+      }
     },
     [current, handleDismiss]
   );
@@ -102,10 +140,17 @@ export default function ConflictDialog() {
           {current.path}
         </div>
 
+        {error && (
+          <p className="error" style={{ fontSize: 11, marginBottom: 8 }}>
+            {error}
+          </p>
+        )}
+
         <div style={{ display: 'flex', flexDirection: 'column', gap: 6 }}>
           <button
             type="button"
             className="btn btn-primary btn-sm"
+            disabled={resolving}
             onClick={() => void handleResolve('keep_local')}
           >
             Zachowaj lokalną wersję
@@ -113,6 +158,7 @@ export default function ConflictDialog() {
           <button
             type="button"
             className="btn btn-primary btn-sm"
+            disabled={resolving}
             onClick={() => void handleResolve('keep_remote')}
           >
             Zachowaj serwerową wersję
@@ -120,6 +166,7 @@ export default function ConflictDialog() {
           <button
             type="button"
             className="btn btn-outline btn-sm"
+            disabled={resolving}
             onClick={() => void handleResolve('keep_both')}
           >
             Zachowaj obie (zmień nazwę lokalnej)
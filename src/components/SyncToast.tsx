@@ -47,6 +47,8 @@ export default function SyncToast() {
         } else {
           setToast((prev) => (prev?.type === 'info' ? null : prev));
         }
+      } else if (phase === 'cancelled') {
+        showToast('Synchronizacja anulowana', 'info');
       } else if (phase === 'error') {
         showToast('Błąd synchronizacji', 'error');
       }
@@ -9,12 +9,41 @@ export interface AppConfig {
   sync_interval_secs: number;
   watch_local_changes: boolean;
   sync_on_startup: boolean;
-  max_file_size_bytes: number;
+  personal_max_file_size_bytes: number;
+  shared_max_file_size_bytes: number;
   max_upload_kbps: number;
   max_download_kbps: number;
+  bandwidth_schedule: BandwidthRule[];
+  max_parallel_uploads: number;
+  max_parallel_downloads: number;
   sync_include_paths: string[];
+  personal_conflict_policy: ConflictPolicy;
+  shared_conflict_policy: ConflictPolicy;
+  chunked_upload_threshold_bytes: number;
+  full_verify_interval_hours: number;
+  retry_max_attempts: number;
+  retry_max_delay_secs: number;
+  file_retry_max_minutes: number;
 }
 
+export interface BandwidthRule {
+  days: number[];
+  start_minute: number;
+  end_minute: number;
+  upload_kbps: number;
+  download_kbps: number;
+  pause_sync: boolean;
+}
+
+export interface EffectiveBandwidth {
+  upload_kbps: number;
+  download_kbps: number;
+  paused: boolean;
+  rule: number | null;
+}
+
+export type ConflictPolicy = 'ask' | 'keep_both' | 'newest_wins' | 'local_wins' | 'remote_wins';
+
 export type SyncStatus =
   | 'Idle'
   | 'Syncing'
@@ -35,7 +64,7 @@ export interface SyncRunEntry {
   id: number;
   started_at: string;
   completed_at: string | null;
-  status: 'running' | 'success' | 'error' | 'partial';
+  status: 'running' | 'success' | 'error' | 'partial' | 'cancelled';
   source: string | null;
   files_uploaded: number;
   files_downloaded: number;
@@ -46,14 +75,34 @@ export interface SyncRunEntry {
   duration_ms: number | null;
 }
 
+export type SyncZone = 'personal' | 'shared';
+
+export type ConflictResolution = 'keep_local' | 'keep_remote' | 'keep_both';
+
 export interface SyncConflictPayload {
   path: string;
+  zone: SyncZone;
   localPath: string;
   remotePath: string;
   conflictType: 'BothModified' | 'DeletedLocallyModifiedRemotely' | 'DeletedRemotelyModifiedLocally';
   resolution: string;
 }
 
+export interface ConflictEntry {
+  path: string;
+  sync_zone: SyncZone;
+  conflict_type: SyncConflictPayload['conflictType'] | null;
+  local_hash: string | null;
+  local_size: number | null;
+  local_mtime: number | null;
+  local_exists: boolean;
+  remote_etag: string | null;
+  remote_size: number | null;
+  remote_mtime: number | null;
+  remote_exists: boolean;
+  detected_at: string;
+}
+
 export interface SyncFileProgressPayload {
   path: string;
   action: 'upload' | 'download' | 'delete';
@@ -94,10 +143,18 @@ export async function updateConfig(config: AppConfig): Promise<void> {
   await invoke('update_config', { config });
 }
 
+export async function getEffectiveBandwidth(): Promise<EffectiveBandwidth> {
+  return invoke<EffectiveBandwidth>('get_effective_bandwidth');
+}
+
 export async function triggerSync(): Promise<void> {
   await invoke('trigger_sync');
 }
 
+export async function verifySync(): Promise<void> {
+  await invoke('verify_sync');
+}
+
 export async function getActivity(limit?: number): Promise<ActivityEntry[]> {
   return invoke<ActivityEntry[]>('get_activity', { limit: limit ?? 50 });
 }
@@ -106,10 +163,26 @@ export async function getSyncHistory(limit?: number): Promise<SyncRunEntry[]> {
   return invoke<SyncRunEntry[]>('get_sync_history', { limit: limit ?? 50 });
 }
 
+export async function resolveConflict(
+  zone: SyncZone,
+  path: string,
+  resolution: ConflictResolution
+): Promise<void> {
+  await invoke('resolve_conflict', { zone, path, resolution });
+}
+
+export async function listConflicts(): Promise<ConflictEntry[]> {
+  return invoke<ConflictEntry[]>('list_conflicts');
+}
+
 export async function pauseSync(): Promise<void> {
   await invoke('pause_sync');
 }
 
+export async function cancelSync(): Promise<boolean> {
+  return invoke<boolean>('cancel_sync');
+}
+
 export async function resumeSync(): Promise<void> {
   await invoke('resume_sync');
 }
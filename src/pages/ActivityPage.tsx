@@ -147,10 +147,8 @@ export default function ActivityPage() {
             <div className="activity-item" key={getEntryKey(entry)}>
               <div style={{ display: 'flex', justifyContent: 'space-between' }}>
                 <span style={{ fontWeight: 500 }}>{formatAction(entry.action)}</span>
-                <span
-                  className={`status-badge status-${entry.status === 'success' ? 'idle' : 'error'}`}
-                >
-                  {entry.status === 'success' ? 'OK' : 'Błąd'}
+                <span className={`status-badge ${getStatusBadgeClass(entry.status)}`}>
+                  {formatStatus(entry.status)}
                 </span>
               </div>
               {entry.file_path && (
@@ -268,6 +266,8 @@ function formatStatusLabel(status: string): string {
       return 'Błąd';
     case 'partial':
       return 'Częściowo';
+    case 'cancelled':
+      return 'Anulowano';
     case 'running':
       return 'W toku';
     default:
@@ -279,6 +279,7 @@ function getHistoryStatusClass(status: string): string {
   if (status === 'success') return 'idle';
   if (status === 'error') return 'error';
   if (status === 'partial') return 'conflict';
+  if (status === 'cancelled') return 'paused';
   return 'syncing';
 }
 
@@ -302,9 +303,30 @@ function getErrorSummary(entry: ActivityEntry): string {
     return '';
   }
 
+  if (entry.status === 'skipped') {
+    return 'Pominięto: plik przekracza limit rozmiaru';
+  }
+
+  if (entry.status === 'cancelled') {
+    return 'Synchronizacja przerwana przez użytkownika';
+  }
+
   return `Błąd: ${formatAction(entry.action)}`;
 }
 
+function formatStatus(status: string): string {
+  if (status === 'success') return 'OK';
+  if (status === 'skipped') return 'Pominięto';
+  if (status === 'cancelled') return 'Anulowano';
+  return 'Błąd';
+}
+
+function getStatusBadgeClass(status: string): string {
+  if (status === 'success') return 'status-idle';
+  if (status === 'skipped' || status === 'cancelled') return 'status-paused';
+  return 'status-error';
+}
+
 function getEntryKey(entry: ActivityEntry): string {
   return `${entry.timestamp}-${entry.action}-${entry.file_path}`;
 }
@@ -328,6 +350,12 @@ function formatAction(action: string): string {
     case 'download': return 'Download';
     case 'delete': return 'Usunięto';
     case 'conflict': return 'Konflikt';
+    case 'move': return 'Przeniesiono';
+    case 'create_local_dir':
+    case 'create_remote_dir': return 'Utworzono folder';
+    case 'delete_local_dir':
+    case 'delete_remote_dir': return 'Usunięto folder';
+    case 'skipped': return 'Pominięto';
     default: return action;
   }
 }
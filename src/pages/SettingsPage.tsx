@@ -1,5 +1,94 @@
 import { useState, useEffect, useCallback, FormEvent } from 'react';
-import { getConfig, updateConfig, logout, pickFolder, getDebugInfo, openLogFile, setDebugMode, getLogContents, type AppConfig } from '@/lib/tauri';
+import { getConfig, updateConfig, getEffectiveBandwidth, logout, pickFolder, verifySync, getDebugInfo, openLogFile, setDebugMode, getLogContents, type AppConfig, type BandwidthRule, type ConflictPolicy, type EffectiveBandwidth } from '@/lib/tauri';
+
+const CONFLICT_POLICY_OPTIONS: { value: ConflictPolicy; label: string }[] = [
+  { value: 'ask', label: 'Pytaj' },
+  { value: 'keep_both', label: 'Zachowaj obie wersje' },
+  { value: 'newest_wins', label: 'Nowsza wersja wygrywa' },
+  { value: 'local_wins', label: 'Lokalna wersja wygrywa' },
+  { value: 'remote_wins', label: 'Wersja z serwera wygrywa' },
+];
+
+const MB = 1024 * 1024;
+
+const FILE_SIZE_LIMIT_OPTIONS: { value: number; label: string }[] = [
+  { value: 0, label: 'Bez limitu' },
+  { value: 100 * MB, label: '100 MB' },
+  { value: 500 * MB, label: '500 MB' },
+  { value: 1024 * MB, label: '1 GB' },
+  { value: 5 * 1024 * MB, label: '5 GB' },
+];
+
+const SPEED_LIMIT_OPTIONS: { value: number; label: string }[] = [
+  { value: 0, label: 'Bez limitu' },
+  { value: 256, label: '256 KB/s' },
+  { value: 512, label: '512 KB/s' },
+  { value: 1024, label: '1 MB/s' },
+  { value: 5120, label: '5 MB/s' },
+  { value: 10240, label: '10 MB/s' },
+];
+
+const WEEKDAYS: { value: number; label: string }[] = [
+  { value: 1, label: 'Pn' },
+  { value: 2, label: 'Wt' },
+  { value: 3, label: 'Śr' },
+  { value: 4, label: 'Cz' },
+  { value: 5, label: 'Pt' },
+  { value: 6, label: 'So' },
+  { value: 7, label: 'Nd' },
+];
+
+const NEW_BANDWIDTH_RULE: BandwidthRule = {
+  days: [1, 2, 3, 4, 5],
+  start_minute: 8 * 60,
+  end_minute: 17 * 60,
+  upload_kbps: 512,
+  download_kbps: 0,
+  pause_sync: false,
+};
+
+// 24:00 is shown as 00:00, which ends a window at midnight all the same
+function minutesToTime(minutes: number): string {
+  const hours = Math.floor(minutes / 60) % 24;
+  return `${String(hours).padStart(2, '0')}:${String(minutes % 60).padStart(2, '0')}`;
+}
+
+function timeToMinutes(time: string): number | null {
+  const [hours, minutes] = time.split(':').map((part) => parseInt(part, 10));
+  if (Number.isNaN(hours) || Number.isNaN(minutes)) return null;
+  return hours * 60 + minutes;
+}
+
+function formatSpeed(kbps: number): string {
+  if (kbps === 0) return 'bez limitu';
+  return kbps % 1024 === 0 ? `${kbps / 1024} MB/s` : `${kbps} KB/s`;
+}
+
+function describeBandwidth(bandwidth: EffectiveBandwidth): string {
+  const source = bandwidth.rule === null ? 'limity domyślne' : `okno harmonogramu nr ${bandwidth.rule + 1}`;
+  if (bandwidth.paused) {
+    return `Teraz: automatyczna synchronizacja wstrzymana (${source})`;
+  }
+  return `Teraz: upload ${formatSpeed(bandwidth.upload_kbps)}, download ${formatSpeed(bandwidth.download_kbps)} (${source})`;
+}
+
+const PARALLEL_TRANSFER_OPTIONS = [1, 2, 4, 8];
+
+const FULL_VERIFY_OPTIONS: { value: number; label: string }[] = [
+  { value: 6, label: 'Co 6 godzin' },
+  { value: 24, label: 'Raz dziennie' },
+  { value: 168, label: 'Raz w tygodniu' },
+  { value: 0, label: 'Tylko na żądanie' },
+];
+
+const RETRY_ATTEMPT_OPTIONS = [1, 3, 5, 10];
+
+const FILE_RETRY_OPTIONS: { value: number; label: string }[] = [
+  { value: 0, label: 'Przy każdej synchronizacji' },
+  { value: 15, label: 'Najwyżej co 15 minut' },
+  { value: 60, label: 'Najwyżej co godzinę' },
+  { value: 360, label: 'Najwyżej co 6 godzin' },
+];
 
 interface SettingsPageProps {
   onLogout: () => void;
@@ -14,6 +103,9 @@ export default function SettingsPage({ onLogout }: SettingsPageProps) {
   const [logPath, setLogPath] = useState('');
   const [logContents, setLogContents] = useState<string | null>(null);
   const [diagnosticsMessage, setDiagnosticsMessage] = useState('');
+  const [verifying, setVerifying] = useState(false);
+  const [verifyMessage, setVerifyMessage] = useState('');
+  const [effectiveBandwidth, setEffectiveBandwidth] = useState<EffectiveBandwidth | null>(null);
 
   useEffect(() => {
     let cancelled = false;
@@ -31,6 +123,14 @@ export default function SettingsPage({ onLogout }: SettingsPageProps) {
         }
       });
 
+    getEffectiveBandwidth()
+      .then((bandwidth) => {
+        if (!cancelled) {
+          setEffectiveBandwidth(bandwidth);
+        }
+      })
+      .catch(() => {});
+
     getDebugInfo()
       .then(([enabled, path]) => {
         if (!cancelled) {
@@ -54,6 +154,7 @@ export default function SettingsPage({ onLogout }: SettingsPageProps) {
     try {
       await updateConfig(config);
       setMessage('Ustawienia zapisane');
+      getEffectiveBandwidth().then(setEffectiveBandwidth).catch(() => {});
     } catch (err) {
       setMessage(`Błąd: ${err}`);
     } finally {
@@ -207,11 +308,9 @@ export default function SettingsPage({ onLogout }: SettingsPageProps) {
                 setConfig({ ...config, max_upload_kbps: parseInt(e.target.value, 10) })
               }
             >
-              <option value={0}>Bez limitu</option>
-              <option value={256}>256 KB/s</option>
-              <option value={1024}>1 MB/s</option>
-              <option value={5120}>5 MB/s</option>
-              <option value={10240}>10 MB/s</option>
+              {SPEED_LIMIT_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
             </select>
           </div>
 
@@ -225,11 +324,364 @@ export default function SettingsPage({ onLogout }: SettingsPageProps) {
                 setConfig({ ...config, max_download_kbps: parseInt(e.target.value, 10) })
               }
             >
-              <option value={0}>Bez limitu</option>
-              <option value={256}>256 KB/s</option>
-              <option value={1024}>1 MB/s</option>
-              <option value={5120}>5 MB/s</option>
-              <option value={10240}>10 MB/s</option>
+              {SPEED_LIMIT_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+
+          <p style={{ fontSize: 11, color: 'var(--color-text-secondary)', margin: '12px 0 8px' }}>
+            Harmonogram: w podanych dniach i godzinach obowiązują limity okna zamiast
+            powyższych, a okno z wstrzymaniem nie uruchamia automatycznej synchronizacji.
+            Decyduje pierwsze pasujące okno.
+          </p>
+
+          {config.bandwidth_schedule.map((rule, index) => {
+            const updateRule = (changes: Partial<BandwidthRule>) =>
+              setConfig({
+                ...config,
+                bandwidth_schedule: config.bandwidth_schedule.map((r, i) =>
+                  i === index ? { ...r, ...changes } : r,
+                ),
+              });
+
+            return (
+              <div key={index} style={{ borderTop: '1px solid #e0e0e0', paddingTop: 8, marginBottom: 8 }}>
+                <div style={{ display: 'flex', gap: 4, flexWrap: 'wrap', marginBottom: 6 }}>
+                  <span style={{ fontSize: 11, marginRight: 4 }}>{index + 1}.</span>
+                  {WEEKDAYS.map((day) => (
+                    <label key={day.value} className="checkbox-row" style={{ fontSize: 11 }}>
+                      <input
+                        type="checkbox"
+                        checked={rule.days.includes(day.value)}
+                        onChange={(e) =>
+                          updateRule({
+                            days: e.target.checked
+                              ? [...rule.days, day.value].sort((a, b) => a - b)
+                              : rule.days.filter((d) => d !== day.value),
+                          })
+                        }
+                      />
+                      {day.label}
+                    </label>
+                  ))}
+                </div>
+
+                <div className="path-row" style={{ alignItems: 'center', marginBottom: 6 }}>
+                  <input
+                    type="time"
+                    className="input input-sm"
+                    aria-label="Początek okna"
+                    value={minutesToTime(rule.start_minute)}
+                    onChange={(e) => {
+                      const minutes = timeToMinutes(e.target.value);
+                      if (minutes !== null) updateRule({ start_minute: minutes });
+                    }}
+                  />
+                  <span style={{ fontSize: 11 }}>–</span>
+                  <input
+                    type="time"
+                    className="input input-sm"
+                    aria-label="Koniec okna"
+                    value={minutesToTime(rule.end_minute)}
+                    onChange={(e) => {
+                      const minutes = timeToMinutes(e.target.value);
+                      if (minutes !== null) updateRule({ end_minute: minutes });
+                    }}
+                  />
+                  <button
+                    type="button"
+                    className="btn btn-outline btn-sm"
+                    onClick={() =>
+                      setConfig({
+                        ...config,
+                        bandwidth_schedule: config.bandwidth_schedule.filter((_, i) => i !== index),
+                      })
+                    }
+                  >
+                    Usuń
+                  </button>
+                </div>
+
+                <label className="checkbox-row">
+                  <input
+                    type="checkbox"
+                    checked={rule.pause_sync}
+                    onChange={(e) => updateRule({ pause_sync: e.target.checked })}
+                  />
+                  Wstrzymaj synchronizację i transfery
+                </label>
+
+                <div className="path-row">
+                  <select
+                    className="input input-sm"
+                    aria-label="Upload w oknie"
+                    value={rule.upload_kbps}
+                    disabled={rule.pause_sync}
+                    onChange={(e) => updateRule({ upload_kbps: parseInt(e.target.value, 10) })}
+                    style={{ flex: 1 }}
+                  >
+                    {SPEED_LIMIT_OPTIONS.map((option) => (
+                      <option key={option.value} value={option.value}>Upload: {option.label}</option>
+                    ))}
+                  </select>
+                  <select
+                    className="input input-sm"
+                    aria-label="Download w oknie"
+                    value={rule.download_kbps}
+                    disabled={rule.pause_sync}
+                    onChange={(e) => updateRule({ download_kbps: parseInt(e.target.value, 10) })}
+                    style={{ flex: 1 }}
+                  >
+                    {SPEED_LIMIT_OPTIONS.map((option) => (
+                      <option key={option.value} value={option.value}>Download: {option.label}</option>
+                    ))}
+                  </select>
+                </div>
+              </div>
+            );
+          })}
+
+          <button
+            type="button"
+            className="btn btn-outline btn-sm"
+            onClick={() =>
+              setConfig({
+                ...config,
+                bandwidth_schedule: [...config.bandwidth_schedule, { ...NEW_BANDWIDTH_RULE }],
+              })
+            }
+          >
+            Dodaj okno
+          </button>
+
+          {effectiveBandwidth && (
+            <p style={{ fontSize: 10, color: 'var(--color-text-secondary)', marginTop: 8 }}>
+              {describeBandwidth(effectiveBandwidth)}
+            </p>
+          )}
+        </div>
+
+        <div className="card">
+          <h3 className="card-title">Transfery równoległe</h3>
+
+          <div className="input-group">
+            <label htmlFor="parallel-uploads">Jednoczesne wysyłanie</label>
+            <select
+              id="parallel-uploads"
+              className="input input-sm"
+              value={config.max_parallel_uploads}
+              onChange={(e) =>
+                setConfig({ ...config, max_parallel_uploads: parseInt(e.target.value, 10) })
+              }
+            >
+              {PARALLEL_TRANSFER_OPTIONS.map((n) => (
+                <option key={n} value={n}>{n}</option>
+              ))}
+            </select>
+          </div>
+
+          <div className="input-group">
+            <label htmlFor="parallel-downloads">Jednoczesne pobieranie</label>
+            <select
+              id="parallel-downloads"
+              className="input input-sm"
+              value={config.max_parallel_downloads}
+              onChange={(e) =>
+                setConfig({ ...config, max_parallel_downloads: parseInt(e.target.value, 10) })
+              }
+            >
+              {PARALLEL_TRANSFER_OPTIONS.map((n) => (
+                <option key={n} value={n}>{n}</option>
+              ))}
+            </select>
+          </div>
+        </div>
+
+        <div className="card">
+          <h3 className="card-title">Duże pliki</h3>
+          <p style={{ fontSize: 11, color: 'var(--color-text-secondary)', marginBottom: 8 }}>
+            Pliki powyżej limitu są pomijane po obu stronach. Większe niż próg pliki są
+            wysyłane w częściach i wznawiane po przerwaniu.
+          </p>
+
+          <div className="input-group">
+            <label htmlFor="personal-max-size">Limit: Moje pliki</label>
+            <select
+              id="personal-max-size"
+              className="input input-sm"
+              value={config.personal_max_file_size_bytes}
+              onChange={(e) =>
+                setConfig({ ...config, personal_max_file_size_bytes: parseInt(e.target.value, 10) })
+              }
+            >
+              {FILE_SIZE_LIMIT_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+
+          <div className="input-group">
+            <label htmlFor="shared-max-size">Limit: Udostępnione</label>
+            <select
+              id="shared-max-size"
+              className="input input-sm"
+              value={config.shared_max_file_size_bytes}
+              onChange={(e) =>
+                setConfig({ ...config, shared_max_file_size_bytes: parseInt(e.target.value, 10) })
+              }
+            >
+              {FILE_SIZE_LIMIT_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+
+          <div className="input-group">
+            <label htmlFor="chunked-threshold">Wysyłanie w częściach od</label>
+            <select
+              id="chunked-threshold"
+              className="input input-sm"
+              value={config.chunked_upload_threshold_bytes}
+              onChange={(e) =>
+                setConfig({
+                  ...config,
+                  chunked_upload_threshold_bytes: parseInt(e.target.value, 10),
+                })
+              }
+            >
+              <option value={0}>Nigdy</option>
+              <option value={10 * MB}>10 MB</option>
+              <option value={50 * MB}>50 MB</option>
+              <option value={100 * MB}>100 MB</option>
+              <option value={500 * MB}>500 MB</option>
+            </select>
+          </div>
+        </div>
+
+        <div className="card">
+          <h3 className="card-title">Weryfikacja plików</h3>
+          <p style={{ fontSize: 11, color: 'var(--color-text-secondary)', marginBottom: 8 }}>
+            Niezmienione pliki nie są ponownie odczytywane przy każdej synchronizacji.
+            Pełna weryfikacja oblicza sumy kontrolne wszystkich plików od nowa.
+          </p>
+
+          <div className="input-group">
+            <label htmlFor="full-verify-interval">Pełna weryfikacja</label>
+            <select
+              id="full-verify-interval"
+              className="input input-sm"
+              value={config.full_verify_interval_hours}
+              onChange={(e) =>
+                setConfig({ ...config, full_verify_interval_hours: parseInt(e.target.value, 10) })
+              }
+            >
+              {FULL_VERIFY_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+
+          {verifyMessage && (
+            <p style={{ fontSize: 11, marginBottom: 6 }}>{verifyMessage}</p>
+          )}
+
+          <button
+            type="button"
+            className="btn btn-outline btn-sm"
+            disabled={verifying}
+            onClick={async () => {
+              setVerifying(true);
+              setVerifyMessage('');
+              try {
+                await verifySync();
+                setVerifyMessage('Weryfikacja zakończona');
+              } catch (err) {
+                setVerifyMessage(`Błąd weryfikacji: ${err}`);
+              } finally {
+                setVerifying(false);
+              }
+            }}
+          >
+            {verifying ? 'Weryfikacja...' : 'Zweryfikuj wszystkie pliki teraz'}
+          </button>
+        </div>
+
+        <div className="card">
+          <h3 className="card-title">Ponawianie</h3>
+          <p style={{ fontSize: 11, color: 'var(--color-text-secondary)', marginBottom: 8 }}>
+            Przerwane połączenia i chwilowe błędy serwera są ponawiane z rosnącym odstępem.
+            Plik, którego nie udało się zsynchronizować, czeka coraz dłużej na kolejną próbę.
+          </p>
+
+          <div className="input-group">
+            <label htmlFor="retry-attempts">Próby każdego żądania</label>
+            <select
+              id="retry-attempts"
+              className="input input-sm"
+              value={config.retry_max_attempts}
+              onChange={(e) =>
+                setConfig({ ...config, retry_max_attempts: parseInt(e.target.value, 10) })
+              }
+            >
+              {RETRY_ATTEMPT_OPTIONS.map((n) => (
+                <option key={n} value={n}>{n}</option>
+              ))}
+            </select>
+          </div>
+
+          <div className="input-group">
+            <label htmlFor="file-retry-max">Ponawianie plików z błędem</label>
+            <select
+              id="file-retry-max"
+              className="input input-sm"
+              value={config.file_retry_max_minutes}
+              onChange={(e) =>
+                setConfig({ ...config, file_retry_max_minutes: parseInt(e.target.value, 10) })
+              }
+            >
+              {FILE_RETRY_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+        </div>
+
+        <div className="card">
+          <h3 className="card-title">Konflikty</h3>
+          <p style={{ fontSize: 11, color: 'var(--color-text-secondary)', marginBottom: 8 }}>
+            Nadpisana wersja zawsze trafia do lokalnego kosza.
+          </p>
+
+          <div className="input-group">
+            <label htmlFor="personal-conflict-policy">Moje pliki</label>
+            <select
+              id="personal-conflict-policy"
+              className="input input-sm"
+              value={config.personal_conflict_policy}
+              onChange={(e) =>
+                setConfig({ ...config, personal_conflict_policy: e.target.value as ConflictPolicy })
+              }
+            >
+              {CONFLICT_POLICY_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
+            </select>
+          </div>
+
+          <div className="input-group">
+            <label htmlFor="shared-conflict-policy">Udostępnione</label>
+            <select
+              id="shared-conflict-policy"
+              className="input input-sm"
+              value={config.shared_conflict_policy}
+              onChange={(e) =>
+                setConfig({ ...config, shared_conflict_policy: e.target.value as ConflictPolicy })
+              }
+            >
+              {CONFLICT_POLICY_OPTIONS.map((option) => (
+                <option key={option.value} value={option.value}>{option.label}</option>
+              ))}
             </select>
           </div>
         </div>
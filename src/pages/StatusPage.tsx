@@ -6,6 +6,7 @@ import {
   triggerSync,
   openFolder,
   pauseSync,
+  cancelSync,
   resumeSync,
   type SyncStatus,
   type AppConfig,
@@ -96,6 +97,14 @@ export default function StatusPage() {
     }
   }, [status, refreshStatus]);
 
+  const handleCancel = useCallback(async () => {
+    try {
+      await cancelSync();
+    } catch (err) {
+      console.error('Failed to cancel sync:', err);
+    }
+  }, []);
+
   const handleOpenPersonal = useCallback(() => {
     if (config) openFolder(config.personal_sync_path);
   }, [config]);
@@ -144,14 +153,24 @@ export default function StatusPage() {
           >
             {syncing ? 'Synchronizacja...' : 'Synchronizuj teraz'}
           </button>
-          <button
-            className="btn btn-secondary"
-            onClick={handleTogglePause}
-            disabled={status === 'NotConfigured' || status === 'Syncing'}
-            title={status === 'Paused' ? 'Wznów synchronizację' : 'Wstrzymaj synchronizację'}
-          >
-            {status === 'Paused' ? '▶ Wznów' : '⏸ Wstrzymaj'}
-          </button>
+          {syncing || status === 'Syncing' ? (
+            <button
+              className="btn btn-secondary"
+              onClick={handleCancel}
+              title="Przerwij bieżącą synchronizację"
+            >
+              ✕ Anuluj
+            </button>
+          ) : (
+            <button
+              className="btn btn-secondary"
+              onClick={handleTogglePause}
+              disabled={status === 'NotConfigured'}
+              title={status === 'Paused' ? 'Wznów synchronizację' : 'Wstrzymaj synchronizację'}
+            >
+              {status === 'Paused' ? '▶ Wznów' : '⏸ Wstrzymaj'}
+            </button>
+          )}
         </div>
       </div>
 